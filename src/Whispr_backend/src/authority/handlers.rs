@@ -1,39 +1,294 @@
+use crate::authority::ledger::{self, Account, TransferArg, TransferError, TransferFromArgs, TransferFromError};
 use crate::authority::store;
 use crate::authority::types::*;
 use candid::{Principal, Nat};
-use ic_cdk::{api, storage, caller, trap};
+use crate::authority::runtime::{self, api, caller, trap};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::time::Duration;
 
-// Authentication helper function
-fn ensure_authority() -> Result<Principal, String> {
+// Largest chunk accepted by upload_evidence_chunk (stays well below the ingress limit)
+pub(super) const MAX_EVIDENCE_CHUNK_SIZE: usize = 1024 * 1024;
+
+// Largest evidence file that can be uploaded
+pub(super) const MAX_EVIDENCE_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+// Largest file that can be attached to a message
+const MAX_ATTACHMENT_SIZE: u64 = 25 * 1024 * 1024;
+
+// Most unfinished evidence and attachment uploads one principal can have open
+const MAX_OPEN_UPLOADS_PER_CALLER: usize = 5;
+
+// Unfinished uploads are discarded this long after they were started
+const UPLOAD_EXPIRY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// How often expired uploads are swept
+const UPLOAD_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// File types accepted as message attachments
+const ALLOWED_ATTACHMENT_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "video/mp4",
+    "audio/mpeg",
+    "application/pdf",
+    "text/plain",
+];
+
+// Largest page returned by list_reports
+const MAX_REPORT_PAGE_SIZE: u32 = 100;
+
+// Most index entries list_reports will examine in one call
+const MAX_REPORT_INDEX_SCAN: usize = 5_000;
+
+// Largest page returned by get_messages_since
+const MAX_MESSAGE_PAGE_SIZE: u32 = 100;
+
+// Most notifications kept per inbox (the oldest are dropped first)
+const MAX_NOTIFICATIONS_PER_INBOX: usize = 500;
+
+// Largest page returned by get_notifications
+const MAX_NOTIFICATION_PAGE_SIZE: u32 = 100;
+
+// Largest page returned by get_my_transactions
+const MAX_TRANSACTION_PAGE_SIZE: u32 = 100;
+
+// Longest range of daily totals returned by get_treasury_report
+const MAX_TREASURY_REPORT_DAYS: u64 = 366;
+
+// Longest memo and idempotency key accepted by transfer
+const MAX_TRANSFER_MEMO_SIZE: usize = 32;
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
+// Invite codes are this many random bytes, shown as dash-separated hex groups
+const VOUCHER_CODE_BYTES: usize = 8;
+
+// Most invite codes issued by one issue_vouchers call
+const MAX_VOUCHERS_PER_CALL: u32 = 100;
+
+// Largest page returned by get_audit_log
+const MAX_AUDIT_PAGE_SIZE: u64 = 100;
+
+// Limits for the messaging key registry and encrypted message bodies
+const MAX_PUBLIC_KEY_SIZE: usize = 1024;
+const MAX_KEY_ALGORITHM_LEN: usize = 64;
+const MAX_MESSAGE_NONCE_SIZE: usize = 64;
+const MAX_MESSAGE_CIPHERTEXT_SIZE: usize = 64 * 1024;
+const MAX_WRAPPED_KEYS: usize = 64;
+
+// Ledger fee assumed until the ledger reports its own (ICP's transfer fee)
+const DEFAULT_LEDGER_FEE: u64 = 10_000;
+
+// How often queued ledger transfers are retried
+const LEDGER_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+// Check whether a principal is an authority holding a permission
+fn has_permission(id: Principal, permission: Permission) -> bool {
+    store::get_authority(id)
+        .is_some_and(|authority| authority.has_permission(permission))
+}
+
+// Authentication helper: the caller must be an authority with the given permission
+fn require_permission(permission: Permission) -> Result<Principal, String> {
     let caller = caller();
     
     if caller == Principal::anonymous() {
         return Err("Anonymous callers are not allowed".to_string());
     }
     
-    if !store::is_authority(caller) {
-        return Err("Caller is not an authorized authority".to_string());
+    let authority = match store::get_authority(caller) {
+        Some(authority) => authority,
+        None => return Err("Caller is not an authorized authority".to_string()),
+    };
+    
+    if !authority.has_permission(permission) {
+        return Err(format!("Caller lacks the {:?} permission", permission));
     }
     
     Ok(caller)
 }
 
-// Initialize system and create mock data
+// Report access helper: the submitter and authorities may view a report
+fn ensure_can_view_report(report_id: u64) -> Result<Report, String> {
+    let caller = caller();
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    if report.submitter_id != caller && !has_permission(caller, Permission::ViewReports) {
+        return Err("You are not allowed to view this report".to_string());
+    }
+    
+    Ok(report)
+}
+
+// Deliver a notification unless the recipient has muted its type
+fn notify(recipient: Principal, event: NotificationEvent) {
+    if store::get_notification_preferences(recipient).muted.contains(&event.kind()) {
+        return;
+    }
+    
+    let notification = Notification {
+        id: 0, // Will be assigned by add_notification
+        event,
+        created_at: api::time(),
+        read: false,
+    };
+    
+    store::add_notification(recipient, &notification, MAX_NOTIFICATIONS_PER_INBOX);
+}
+
+// Authority-facing view of a report, with the reporter shown by pseudonym
+fn authority_report_view(report: Report) -> AuthorityReportView {
+    let reporter = store::reporter_pseudonym(report.id);
+    AuthorityReportView::new(report, reporter)
+}
+
+// Seed the pseudonym secret from the management canister's randomness
+fn schedule_pseudonym_secret() {
+    runtime::set_timer(Duration::ZERO, || {
+        runtime::spawn(async {
+            if store::has_pseudonym_secret() {
+                return;
+            }
+            
+            match api::management_canister::main::raw_rand().await {
+                Ok((bytes,)) => store::set_pseudonym_secret(bytes),
+                Err((code, message)) => {
+                    ic_cdk::println!("raw_rand failed ({:?}: {}), retrying", code, message);
+                    schedule_pseudonym_secret();
+                }
+            }
+        });
+    });
+}
+
+// Append an audit log entry for the current call
+fn audit(method: &str, targets: Vec<String>, before: Option<Vec<u8>>, after: Option<Vec<u8>>) {
+    store::append_audit_entry(caller(), method, targets, before, after);
+}
+
+// Save a user record, auditing the balance change
+fn save_user(method: &str, before: Option<&User>, user: User) {
+    save_user_as(caller(), method, before, user);
+}
+
+// Save a user record on behalf of `actor` (for code that runs after an await)
+fn save_user_as(actor: Principal, method: &str, before: Option<&User>, user: User) {
+    store::append_audit_entry(
+        actor,
+        method,
+        vec![format!("user:{}", user.id)],
+        before.map(store::digest),
+        Some(store::digest(&user)),
+    );
+    store::create_or_update_user(user);
+}
+
+// Post a journal entry for a movement whose balance was already checked. A failure
+// means the books disagree with those checks, so trap to roll back the whole call.
+fn post_entry(kind: EntryKind, debit: BookAccount, credit: BookAccount, amount: u64, report_id: Option<u64>) {
+    if amount == 0 {
+        return;
+    }
+    
+    if let Err(error) = store::post_entry_now(kind, debit, credit, amount, report_id) {
+        trap(&format!("Journal entry could not be posted: {}", error));
+    }
+}
+
+// Initialize system and create mock data. Mock data is only created with internal
+// balances, since its stakes were never escrowed on a ledger.
 #[ic_cdk::init]
-fn init() {
-    store::initialize_mock_data();
+fn init(args: Option<InitArgs>) {
+    let InitArgs { ledger_canister_id, ledger_fee, admins } =
+        args.unwrap_or(InitArgs { ledger_canister_id: None, ledger_fee: None, admins: None });
+    
+    if let Some(ledger_canister_id) = ledger_canister_id {
+        store::set_ledger_config(LedgerConfig {
+            ledger_canister_id: Some(ledger_canister_id),
+            fee: ledger_fee.unwrap_or(DEFAULT_LEDGER_FEE),
+            treasury_balance: None,
+        });
+    }
+    
+    // Later authority changes need several admins' approval, so the first admins are set here
+    for id in admins.unwrap_or_default() {
+        if id != Principal::anonymous() {
+            store::add_authority(Authority {
+                id,
+                reports_reviewed: Vec::new(),
+                approval_rate: 0.0,
+                roles: vec![AuthorityRole::Admin],
+            });
+        }
+    }
+    
+    schedule_pseudonym_secret();
+    start_ledger_transfer_timer();
+    start_upload_expiry_timer();
+    store::seed_default_categories();
+    
+    // A fresh canister builds its indexes as data is written
+    store::set_index_schema_version(store::INDEX_SCHEMA_VERSION);
+    
+    if !ledger_mode() {
+        store::initialize_mock_data();
+    }
+}
+
+// All canister state lives in stable memory, so there is nothing to save here
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {}
+
+// Rebuild derived indexes and repair ID counters if their schema changed since
+// the last upgrade
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    if store::get_index_schema_version() != store::INDEX_SCHEMA_VERSION {
+        store::rebuild_indexes();
+        store::set_index_schema_version(store::INDEX_SCHEMA_VERSION);
+    }
+    
+    if !store::has_pseudonym_secret() {
+        schedule_pseudonym_secret();
+    }
+    
+    // Balances from before the journal get opening entries
+    store::open_journal();
+    
+    // Timers do not survive upgrades
+    store::requeue_in_flight_ledger_transfers();
+    start_ledger_transfer_timer();
+    start_upload_expiry_timer();
 }
 
-// Submit a new report (for users)
+// Submit a new report (for users). In ledger mode the caller must first approve
+// this canister to spend the stake plus the ledger fee.
 #[ic_cdk::update]
-fn submit_report(
+async fn submit_report(
+    title: String,
+    description: String,
+    category: String,
+    location: Option<Location>,
+    incident_date: Option<String>,
+    stake_amount: u64,
+) -> Result<u64, String> {
+    file_report(title, description, category, location, incident_date, stake_amount).await
+}
+
+// Validate a submission, take the caller's stake and store the report
+pub(super) async fn file_report(
     title: String,
     description: String,
     category: String,
     location: Option<Location>,
     incident_date: Option<String>,
     stake_amount: u64,
-    evidence_count: u32,
 ) -> Result<u64, String> {
     let caller = caller();
     
@@ -41,31 +296,40 @@ fn submit_report(
         return Err("Anonymous callers cannot submit reports".to_string());
     }
     
-    // Get or create user
+    // Reporters join through register, an onboarding grant or an invite code
     let user = match store::get_user(caller) {
         Some(user) => user,
-        None => {
-            // New user, create with default balance for testing
-            let new_user = User {
-                id: caller,
-                token_balance: 100, // Default balance for new users
-                reports_submitted: Vec::new(),
-                rewards_earned: 0,
-                stakes_active: 0,
-                stakes_lost: 0,
-            };
-            store::create_or_update_user(new_user);
-            store::get_user(caller).unwrap()
-        }
+        None => return Err("Register or redeem an invite code before submitting reports".to_string()),
+    };
+    
+    // Check the category is registered and still accepting reports
+    let category_info = match store::get_category(&category) {
+        Some(category_info) => category_info,
+        None => return Err(format!("Unknown report category '{}'", category)),
     };
     
-    // Check stake amount
-    if stake_amount < 5 {
-        return Err("Minimum stake amount is 5 tokens".to_string());
+    if !category_info.active {
+        return Err(format!("Report category '{}' is no longer accepting reports", category));
+    }
+    
+    // Check stake amount against the current reward config and category limits
+    let config = store::get_reward_config();
+    let min_stake = category_info.min_stake_amount
+        .map_or(config.min_stake_amount, |min| min.max(config.min_stake_amount));
+    let max_stake = category_info.max_stake_amount
+        .map_or(config.max_stake_amount, |max| max.min(config.max_stake_amount));
+    
+    if stake_amount < min_stake {
+        return Err(format!("Minimum stake amount is {} tokens", min_stake));
+    }
+    
+    if stake_amount > max_stake {
+        return Err(format!("Maximum stake amount is {} tokens", max_stake));
     }
     
     // Check user balance
-    if user.token_balance < stake_amount {
+    let ledger_canister_id = store::get_ledger_config().ledger_canister_id;
+    if ledger_canister_id.is_none() && user.token_balance < stake_amount {
         return Err("Insufficient token balance for staking".to_string());
     }
     
@@ -79,7 +343,7 @@ fn submit_report(
         incident_date,
         location,
         submitter_id: caller,
-        evidence_count,
+        evidence_count: 0, // Derived from evidence_files as uploads are finalized
         evidence_files: Vec::new(),
         stake_amount,
         reward_amount: 0,
@@ -87,16 +351,48 @@ fn submit_report(
         reviewer: None,
         review_date: None,
         review_notes: None,
+        reward_config_version: Some(config.version),
     };
     
-    let report_id = store::create_report(&report);
+    // Take the stake from the internal balance, or move it into the report's escrow
+    // subaccount on the ledger
+    let report_id = match ledger_canister_id {
+        None => store::create_report(&report),
+        Some(ledger_canister_id) => {
+            let report_id = store::reserve_report_id();
+            collect_stake(ledger_canister_id, caller, report_id, stake_amount).await?;
+            store::create_report_with_id(report_id, &report);
+            report_id
+        }
+    };
+    
+    if let Some(created) = store::get_report(report_id) {
+        store::append_audit_entry(
+            caller,
+            "submit_report",
+            vec![format!("report:{}", report_id)],
+            None,
+            Some(store::digest(&created)),
+        );
+    }
     
-    // Update user's balance and active stakes
-    let mut updated_user = user;
-    updated_user.token_balance -= stake_amount;
+    // Update user's balance and active stakes. Re-read the user, which may have
+    // changed while the ledger call was outstanding.
+    let user = store::get_user(caller).unwrap_or(user);
+    let mut updated_user = user.clone();
     updated_user.stakes_active += stake_amount;
     updated_user.reports_submitted.push(report_id);
-    store::create_or_update_user(updated_user);
+    save_user_as(caller, "submit_report", Some(&user), updated_user);
+    
+    if ledger_canister_id.is_none() {
+        post_entry(
+            EntryKind::StakeLock,
+            BookAccount::user(caller),
+            BookAccount::Escrow { report_id },
+            stake_amount,
+            Some(report_id),
+        );
+    }
     
     // Add a system message
     let system_message = Message {
@@ -106,308 +402,3663 @@ fn submit_report(
         content: format!("Report submitted with a stake of {} tokens", stake_amount),
         timestamp: api::time(),
         attachment: None,
+        encrypted: None,
     };
     
     store::create_message(&system_message);
     
+    // Let reviewers know there is a new report in the queue
+    for authority in store::get_all_authorities() {
+        if authority.has_permission(Permission::ReviewReports) {
+            notify(authority.id, NotificationEvent::NewReport { report_id, category: category_info.id.clone() });
+        }
+    }
+    
     Ok(report_id)
 }
 
 // Get all reports (for authority)
 #[ic_cdk::query]
-fn get_all_reports() -> Result<Vec<Report>, String> {
-    ensure_authority()?;
-    Ok(store::get_all_reports())
+fn get_all_reports() -> Result<Vec<AuthorityReportView>, String> {
+    require_permission(Permission::ViewReports)?;
+    Ok(store::get_all_reports().into_iter().map(authority_report_view).collect())
 }
 
 // Get reports by status (for authority)
 #[ic_cdk::query]
-fn get_reports_by_status(status: ReportStatus) -> Result<Vec<Report>, String> {
-    ensure_authority()?;
-    Ok(store::get_reports_by_status(status))
+fn get_reports_by_status(status: ReportStatus) -> Result<Vec<AuthorityReportView>, String> {
+    require_permission(Permission::ViewReports)?;
+    Ok(store::get_reports_by_status(status).into_iter().map(authority_report_view).collect())
 }
 
-// Get a single report by ID (for both users and authority)
+// Get reports in a category (for authority)
 #[ic_cdk::query]
-fn get_report(id: u64) -> Vec<Report> {
-    match store::get_report(id) {
-        Some(report) => {
-            let caller = caller();
+fn get_reports_by_category(category_id: String) -> Result<Vec<AuthorityReportView>, String> {
+    require_permission(Permission::ViewReports)?;
+    
+    if store::get_category(&category_id).is_none() {
+        return Err(format!("Unknown report category '{}'", category_id));
+    }
+    
+    Ok(store::get_reports_by_category(&category_id).into_iter().map(authority_report_view).collect())
+}
+
+// Check the filters of a ReportQuery against one report
+fn matches_report_query(report: &Report, query: &ReportQuery) -> bool {
+    query.status.as_ref().is_none_or(|status| report.status == *status)
+        && query.category.as_ref().is_none_or(|category| report.category == *category)
+        && query.submitted_from.is_none_or(|from| report.date_submitted >= from)
+        && query.submitted_to.is_none_or(|to| report.date_submitted <= to)
+        && query.reviewer.is_none_or(|reviewer| report.reviewer == Some(reviewer))
+        && query.min_stake.is_none_or(|min| report.stake_amount >= min)
+        && query.max_stake.is_none_or(|max| report.stake_amount <= max)
+        && query.has_evidence.is_none_or(|has_evidence| report.evidence_files.is_empty() != has_evidence)
+}
+
+// List report summaries page by page (for authority). Results are read from the
+// narrowest index matching the sort order; a page may come back short (even
+// empty) with a `next_cursor` when the scan budget runs out, and `next_cursor`
+// is None once the listing is exhausted.
+#[ic_cdk::query]
+fn list_reports(query: ReportQuery) -> Result<ReportPage, String> {
+    let authority_id = require_permission(Permission::ViewReports)?;
+    
+    let page_size = query.page_size.clamp(1, MAX_REPORT_PAGE_SIZE) as usize;
+    let descending = query.direction == SortDirection::Descending;
+    
+    let index = match query.sort_by {
+        ReportSortKey::StakeAmount => ReportIndex::Stake,
+        ReportSortKey::DateSubmitted => match (&query.status, &query.category) {
+            (Some(status), _) => ReportIndex::StatusDate(status.clone()),
+            (None, Some(category)) => ReportIndex::CategoryDate(category.clone()),
+            (None, None) => ReportIndex::Date,
+        },
+    };
+    let sorted_by_date = query.sort_by == ReportSortKey::DateSubmitted;
+    
+    // Start at the cursor, or jump straight to the date range when sorting by date
+    let mut after = match &query.cursor {
+        Some(cursor) => Some((cursor.sort_value, cursor.report_id)),
+        None if sorted_by_date && !descending => query.submitted_from
+            .filter(|from| *from > 0)
+            .map(|from| (from - 1, u64::MAX)),
+        None if sorted_by_date && descending => query.submitted_to.map(|to| (to, u64::MAX)),
+        None => None,
+    };
+    
+    let mut reports = Vec::new();
+    let mut scanned = 0;
+    let mut exhausted = false;
+    
+    'scan: while reports.len() < page_size && scanned < MAX_REPORT_INDEX_SCAN {
+        let batch_size = page_size.min(MAX_REPORT_INDEX_SCAN - scanned);
+        let batch = store::scan_report_index(&index, after, descending, batch_size);
+        
+        if batch.is_empty() {
+            exhausted = true;
+            break;
+        }
+        
+        for (sort_value, report_id) in batch {
+            // Past the end of the requested date range
+            if sorted_by_date {
+                let past_end = if descending {
+                    query.submitted_from.is_some_and(|from| sort_value < from)
+                } else {
+                    query.submitted_to.is_some_and(|to| sort_value > to)
+                };
+                
+                if past_end {
+                    exhausted = true;
+                    break 'scan;
+                }
+            }
             
-            // Check if caller is report submitter or an authority
-            if report.submitter_id == caller || store::is_authority(caller) {
-                vec![report]
-            } else {
-                // Return empty if not authorized
-                vec![]
+            scanned += 1;
+            after = Some((sort_value, report_id));
+            
+            if let Some(report) = store::get_report(report_id) {
+                if matches_report_query(&report, &query) {
+                    reports.push(ReportListItem {
+                        unread_messages: store::count_unread_messages(report.id, authority_id),
+                        ..ReportListItem::from(&report)
+                    });
+                    
+                    if reports.len() == page_size {
+                        break 'scan;
+                    }
+                }
             }
-        },
+        }
+    }
+    
+    let next_cursor = if exhausted {
+        None
+    } else {
+        after.map(|(sort_value, report_id)| ReportCursor { sort_value, report_id })
+    };
+    
+    Ok(ReportPage { reports, next_cursor })
+}
+
+// Get a single report by ID (for authority)
+#[ic_cdk::query]
+fn get_report(id: u64) -> Vec<AuthorityReportView> {
+    if !has_permission(caller(), Permission::ViewReports) {
+        return vec![];
+    }
+    
+    match store::get_report(id) {
+        Some(report) => vec![authority_report_view(report)],
         None => vec![]
     }
 }
 
+// Reporter-facing view of a report, with the reporter's unread message count
+fn reporter_report_view(report: Report) -> ReporterReportView {
+    let unread_messages = store::count_unread_messages(report.id, report.submitter_id);
+    ReporterReportView { unread_messages, ..ReporterReportView::from(report) }
+}
+
+// Get one of the caller's own reports (for users)
+#[ic_cdk::query]
+fn get_my_report(id: u64) -> Vec<ReporterReportView> {
+    match store::get_report(id) {
+        Some(report) if report.submitter_id == caller() => vec![reporter_report_view(report)],
+        _ => vec![]
+    }
+}
+
 // Get user's reports (for users)
 #[ic_cdk::query]
-fn get_user_reports() -> Vec<Report> {
+fn get_user_reports() -> Vec<ReporterReportView> {
     let caller = caller();
     
     if caller == Principal::anonymous() {
         return vec![];
     }
     
-    store::get_user_reports(caller)
+    store::get_user_reports(caller).into_iter().map(reporter_report_view).collect()
 }
 
-// Verify a report (for authority)
-#[ic_cdk::update]
-fn verify_report(report_id: u64, notes: Option<String>) -> Result<(), String> {
-    let authority_id = ensure_authority()?;
+// Report lifecycle: which statuses each action may be applied to, and where it leads
+const TRANSITIONS: &[(ReportAction, &[ReportStatus], ReportStatus)] = &[
+    (ReportAction::Claim, &[ReportStatus::Pending, ReportStatus::Reopened], ReportStatus::UnderReview),
+    (ReportAction::Release, &[ReportStatus::UnderReview], ReportStatus::Pending),
+    (ReportAction::Escalate, &[ReportStatus::UnderReview], ReportStatus::Escalated),
+    (
+        ReportAction::Verify,
+        &[ReportStatus::Pending, ReportStatus::UnderReview, ReportStatus::Escalated, ReportStatus::Reopened],
+        ReportStatus::Approved,
+    ),
+    (
+        ReportAction::Reject,
+        &[ReportStatus::Pending, ReportStatus::UnderReview, ReportStatus::Escalated, ReportStatus::Reopened],
+        ReportStatus::Rejected,
+    ),
+    (
+        ReportAction::SoftReject,
+        &[ReportStatus::Pending, ReportStatus::UnderReview, ReportStatus::Escalated, ReportStatus::Reopened],
+        ReportStatus::Rejected,
+    ),
+    (
+        ReportAction::Withdraw,
+        &[ReportStatus::Pending, ReportStatus::UnderReview, ReportStatus::Escalated, ReportStatus::Reopened],
+        ReportStatus::Withdrawn,
+    ),
+    (ReportAction::Reopen, &[ReportStatus::Rejected], ReportStatus::Reopened),
+];
+
+// Check an action against TRANSITIONS and any review claim, returning the
+// status it would move the report to
+fn check_transition(report: &Report, action: ReportAction, actor: Principal) -> Result<ReportStatus, String> {
+    let from = &report.status;
     
-    // Get the report
-    let report = match store::get_report(report_id) {
-        Some(report) => report,
-        None => return Err("Report not found".to_string()),
-    };
+    let to = TRANSITIONS.iter()
+        .find(|(candidate, allowed_from, _)| *candidate == action && allowed_from.contains(from))
+        .map(|(_, _, to)| to.clone())
+        .ok_or_else(|| format!("Cannot {:?} a report in {:?} state", action, from))?;
     
-    // Check if report is pending
-    if report.status != ReportStatus::Pending {
-        return Err(format!("Report is already in {:?} state", report.status));
+    // A claimed report belongs to its reviewer until they release or decide it
+    if *from == ReportStatus::UnderReview
+        && action != ReportAction::Withdraw
+        && report.reviewer != Some(actor)
+    {
+        return Err("Report is under review by another authority".to_string());
     }
     
-    let submitter_id = report.submitter_id;
-    let stake_amount = report.stake_amount;
-    
-    // Calculate reward
-    let reward_amount = stake_amount * 10; // 10x reward multiplier
+    Ok(to)
+}
+
+// Move a report through the lifecycle. Every status change goes through here so
+// it is validated against TRANSITIONS, recorded in the status history and
+// mirrored into the conversation as a system message. The report is written
+// here, so callers do their lookups and checks before calling it.
+fn transition_report(
+    report: Report,
+    action: ReportAction,
+    actor: Principal,
+    notes: Option<String>,
+    system_message: String,
+) -> Result<Report, String> {
+    let from = report.status.clone();
+    let to = check_transition(&report, action, actor)?;
     
-    // Update report status
+    let now = api::time();
+    let before_digest = store::digest(&report);
     let mut updated_report = report;
-    updated_report.status = ReportStatus::Approved;
-    updated_report.reviewer = Some(authority_id);
-    updated_report.review_date = Some(api::time());
-    updated_report.review_notes = notes;
-    updated_report.reward_amount = reward_amount;
+    updated_report.status = to.clone();
+    
+    match action {
+        ReportAction::Claim => {
+            updated_report.reviewer = Some(actor);
+        }
+        ReportAction::Release => {
+            updated_report.reviewer = None;
+        }
+        ReportAction::Verify | ReportAction::Reject | ReportAction::SoftReject => {
+            updated_report.reviewer = Some(actor);
+            updated_report.review_date = Some(now);
+            updated_report.review_notes = notes.clone();
+        }
+        ReportAction::Reopen => {
+            updated_report.review_date = None;
+            updated_report.review_notes = None;
+        }
+        ReportAction::Escalate | ReportAction::Withdraw => {}
+    }
     
-    store::update_report(updated_report)?;
+    store::update_report(updated_report.clone())?;
     
-    // Get submitter
-    let submitter = match store::get_user(submitter_id) {
-        Some(user) => user,
-        None => return Err("Report submitter not found".to_string()),
+    let method = match action {
+        ReportAction::Claim => "claim_report_for_review",
+        ReportAction::Release => "release_review",
+        ReportAction::Escalate => "escalate_report",
+        ReportAction::Verify => "verify_report",
+        ReportAction::Reject => "reject_report",
+        ReportAction::SoftReject => "soft_reject_report",
+        ReportAction::Withdraw => "withdraw_report",
+        ReportAction::Reopen => "reopen_report",
     };
+    audit(
+        method,
+        vec![format!("report:{}", updated_report.id)],
+        Some(before_digest),
+        Some(store::digest(&updated_report)),
+    );
     
-    // Update submitter's token balance (return stake + add reward)
-    let mut updated_submitter = submitter;
-    updated_submitter.token_balance += stake_amount + reward_amount;
-    updated_submitter.stakes_active -= stake_amount;
-    updated_submitter.rewards_earned += reward_amount;
+    store::record_status_change(&StatusChange {
+        report_id: updated_report.id,
+        action,
+        from: from.clone(),
+        to: to.clone(),
+        actor,
+        notes,
+        timestamp: now,
+    });
     
-    store::create_or_update_user(updated_submitter);
-    
-    // Add system message
     let message = Message {
         id: 0,
-        report_id,
+        report_id: updated_report.id,
         sender: MessageSender::System,
-        content: format!("This report has been verified. {} tokens have been awarded as a reward.", reward_amount),
-        timestamp: api::time(),
+        content: system_message,
+        timestamp: now,
         attachment: None,
+        encrypted: None,
     };
     
     store::create_message(&message);
     
-    // Update authority stats
-    let mut stats = store::get_authority_stats();
-    stats.total_rewards_distributed += reward_amount;
-    store::update_authority_stats(stats);
+    if actor != updated_report.submitter_id {
+        notify(
+            updated_report.submitter_id,
+            NotificationEvent::StatusChanged { report_id: updated_report.id, from, to },
+        );
+    }
     
-    Ok(())
+    Ok(updated_report)
 }
 
-// Reject a report (for authority)
+// Take a pending or reopened report for review (for authority)
 #[ic_cdk::update]
-fn reject_report(report_id: u64, notes: Option<String>) -> Result<(), String> {
-    let authority_id = ensure_authority()?;
+fn claim_report_for_review(report_id: u64) -> Result<(), String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
     
-    // Get the report
     let report = match store::get_report(report_id) {
         Some(report) => report,
         None => return Err("Report not found".to_string()),
     };
     
-    // Check if report is pending
-    if report.status != ReportStatus::Pending {
-        return Err(format!("Report is already in {:?} state", report.status));
-    }
-    
-    let submitter_id = report.submitter_id;
-    let stake_amount = report.stake_amount;
-    
-    // Update report status
-    let mut updated_report = report;
-    updated_report.status = ReportStatus::Rejected;
-    updated_report.reviewer = Some(authority_id);
-    updated_report.review_date = Some(api::time());
-    updated_report.review_notes = notes;
+    transition_report(
+        report,
+        ReportAction::Claim,
+        authority_id,
+        None,
+        "This report is now under review.".to_string(),
+    )?;
     
-    store::update_report(updated_report)?;
+    Ok(())
+}
+
+// Put a claimed report back in the queue (for the reviewing authority)
+#[ic_cdk::update]
+fn release_review(report_id: u64, notes: Option<String>) -> Result<(), String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
     
-    // Get submitter
-    let submitter = match store::get_user(submitter_id) {
-        Some(user) => user,
-        None => return Err("Report submitter not found".to_string()),
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
     };
     
-    // Update submitter's stakes (stake is lost)
-    let mut updated_submitter = submitter;
-    updated_submitter.stakes_active -= stake_amount;
-    updated_submitter.stakes_lost += stake_amount;
+    transition_report(
+        report,
+        ReportAction::Release,
+        authority_id,
+        notes,
+        "This report has been returned to the review queue.".to_string(),
+    )?;
     
-    store::create_or_update_user(updated_submitter);
+    Ok(())
+}
+
+// Hand a claimed report up for senior review (for the reviewing authority)
+#[ic_cdk::update]
+fn escalate_report(report_id: u64, notes: Option<String>) -> Result<(), String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
     
-    // Add system message
-    let message = Message {
-        id: 0,
-        report_id,
-        sender: MessageSender::System,
-        content: format!("This report has been rejected. The staked {} tokens have been lost.", stake_amount),
-        timestamp: api::time(),
-        attachment: None,
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
     };
     
-    store::create_message(&message);
+    transition_report(
+        report,
+        ReportAction::Escalate,
+        authority_id,
+        notes,
+        "This report has been escalated for further review.".to_string(),
+    )?;
     
     Ok(())
 }
 
-// Send a message as authority
-#[ic_cdk::update]
-fn send_message_as_authority(report_id: u64, content: String) -> Result<(), String> {
-    let authority_id = ensure_authority()?;
+// Reward owed for verifying a report, using the config it was submitted under
+fn calculate_reward(report: &Report) -> Result<u64, String> {
+    let config_version = report.reward_config_version.unwrap_or(1);
+    let config = store::get_reward_config_version(config_version)
+        .ok_or_else(|| format!("Reward config version {} not found", config_version))?;
     
-    // Check if report exists
-    if store::get_report(report_id).is_none() {
-        return Err("Report not found".to_string());
+    report.stake_amount
+        .checked_mul(config.reward_multiplier)
+        .ok_or_else(|| "Reward amount overflows".to_string())
+}
+
+// First day (since the epoch) of the payout period containing `day`
+fn payout_period_start(day: u64, period: PayoutPeriod) -> u64 {
+    match period {
+        PayoutPeriod::Daily => day,
+        PayoutPeriod::Monthly => day + 1 - day_of_month(day),
     }
+}
+
+// Day of the month (1-31) of a day counted from the epoch, using Howard Hinnant's
+// civil_from_days algorithm
+fn day_of_month(day: u64) -> u64 {
+    let days = day + 719_468; // Count from 0000-03-01 instead
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153; // March is 0
+    day_of_year - (153 * month + 2) / 5 + 1
+}
+
+// Rewards paid so far in the current cap period, and what the cap still allows
+fn payout_allowance(config: &TreasuryConfig) -> (u64, Option<u64>) {
+    let today = api::time() / store::DAY_NS;
+    let paid: u64 = store::get_treasury_days(payout_period_start(today, config.cap_period), today)
+        .iter()
+        .map(|(_, day)| day.rewards_paid)
+        .sum();
     
-    // Create message
-    let message = Message {
-        id: 0,
-        report_id,
-        sender: MessageSender::Authority(authority_id),
-        content,
-        timestamp: api::time(),
-        attachment: None,
+    (paid, config.payout_cap.map(|cap| cap.saturating_sub(paid)))
+}
+
+// A reward can only be paid if it fits under the payout cap and the treasury can
+// cover it. On an external ledger the treasury is the canister's default account.
+fn check_reward_funding(reward_amount: u64) -> Result<(), String> {
+    let (_, remaining) = payout_allowance(&store::get_treasury_config());
+    if let Some(remaining) = remaining {
+        if reward_amount > remaining {
+            return Err(format!(
+                "The reward of {} tokens exceeds the {} tokens left under this period's payout cap",
+                reward_amount, remaining
+            ));
+        }
+    }
+    
+    let balance = if ledger_mode() {
+        ledger_treasury_available()?
+    } else {
+        store::get_system_balance(&BookAccount::Treasury)
     };
     
-    store::create_message(&message);
+    if balance < reward_amount {
+        return Err(format!(
+            "The treasury holds {} tokens, not enough for the reward of {} tokens",
+            balance, reward_amount
+        ));
+    }
     
     Ok(())
 }
 
-// Send a message as informer
-#[ic_cdk::update]
-fn send_message_as_reporter(report_id: u64, content: String) -> Result<(), String> {
-    let caller = caller();
+// What the canister's ledger account can still pay out: its balance at the last
+// check, less the payouts from it (and their fees) that are queued or in flight
+fn ledger_treasury_available() -> Result<u64, String> {
+    let config = store::get_ledger_config();
+    let balance = config.treasury_balance
+        .ok_or_else(|| "The treasury balance on the ledger has not been checked yet; try again shortly".to_string())?;
     
-    if caller == Principal::anonymous() {
-        return Err("Anonymous callers cannot send messages".to_string());
-    }
+    let committed: u64 = store::get_ledger_transfers()
+        .iter()
+        .filter(|transfer| matches!(transfer.kind, LedgerTransferKind::Reward | LedgerTransferKind::ReinstateFromTreasury))
+        .filter(|transfer| matches!(transfer.status, LedgerTransferStatus::Pending | LedgerTransferStatus::InFlight))
+        .map(|transfer| transfer.amount.saturating_add(transfer.fee.unwrap_or(config.fee)))
+        .sum();
+    
+    Ok(balance.saturating_sub(committed))
+}
+
+// The submitter with a decided report's stake taken off their active stakes and
+// `lost` of it counted as lost. Checked, so counters that have drifted out of
+// step with the report are an error rather than a panic.
+fn settle_stake(submitter: &User, stake_amount: u64, lost: u64) -> Result<User, String> {
+    let mut updated = submitter.clone();
+    updated.stakes_active = submitter.stakes_active
+        .checked_sub(stake_amount)
+        .ok_or_else(|| "The submitter's active stakes do not include this report's stake".to_string())?;
+    updated.stakes_lost = submitter.stakes_lost
+        .checked_add(lost)
+        .ok_or_else(|| "The submitter's lost stakes overflow".to_string())?;
+    Ok(updated)
+}
+
+// Verify a report (for authority). Rewards above the governance threshold are
+// put up for multi-signature approval instead of being paid immediately.
+#[ic_cdk::update]
+fn verify_report(report_id: u64, notes: Option<String>) -> Result<ActionOutcome, String> {
+    let authority_id = require_permission(Permission::VerifyReports)?;
     
-    // Check if report exists and caller is the submitter
     let report = match store::get_report(report_id) {
         Some(report) => report,
         None => return Err("Report not found".to_string()),
     };
     
-    if report.submitter_id != caller {
-        return Err("You can only send messages for your own reports".to_string());
+    check_transition(&report, ReportAction::Verify, authority_id)?;
+    let reward_amount = calculate_reward(&report)?;
+    check_reward_funding(reward_amount)?;
+    
+    if reward_amount > store::get_governance_config().reward_approval_threshold {
+        return submit_for_approval(ProposalAction::VerifyReport { report_id, notes }, authority_id);
     }
     
-    // Create message
-    let message = Message {
-        id: 0,
-        report_id,
-        sender: MessageSender::Reporter(caller),
-        content,
+    apply_verification(report_id, authority_id, notes)?;
+    
+    Ok(ActionOutcome::Executed)
+}
+
+// Approve a report and pay out the stake and reward
+fn apply_verification(report_id: u64, reviewer: Principal, notes: Option<String>) -> Result<(), String> {
+    // Get the report
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    check_transition(&report, ReportAction::Verify, reviewer)?;
+    
+    let submitter_id = report.submitter_id;
+    let stake_amount = report.stake_amount;
+    let reward_amount = calculate_reward(&report)?;
+    check_reward_funding(reward_amount)?;
+    
+    // Get submitter
+    let submitter = match store::get_user(submitter_id) {
+        Some(user) => user,
+        None => return Err("Report submitter not found".to_string()),
+    };
+    
+    // Update submitter's stakes and rewards
+    let mut updated_submitter = settle_stake(&submitter, stake_amount, 0)?;
+    updated_submitter.rewards_earned = submitter.rewards_earned
+        .checked_add(reward_amount)
+        .ok_or_else(|| "The submitter's rewards overflow".to_string())?;
+    
+    // Update report status
+    let mut report = report;
+    report.reward_amount = reward_amount;
+    
+    transition_report(
+        report,
+        ReportAction::Verify,
+        reviewer,
+        notes,
+        format!("This report has been verified. {} tokens have been awarded as a reward.", reward_amount),
+    )?;
+    
+    save_user("verify_report", Some(&submitter), updated_submitter);
+    
+    // Return the stake from escrow and pay the reward from the treasury. In ledger
+    // mode the reward comes from the canister's ledger account.
+    if ledger_mode() {
+        queue_ledger_transfer(report_id, LedgerTransferKind::StakeRefund, submitter_id, stake_amount);
+        queue_ledger_transfer(report_id, LedgerTransferKind::Reward, submitter_id, reward_amount);
+    } else {
+        post_entry(
+            EntryKind::StakeRefund,
+            BookAccount::Escrow { report_id },
+            BookAccount::user(submitter_id),
+            stake_amount,
+            Some(report_id),
+        );
+        post_entry(
+            EntryKind::RewardPayout,
+            BookAccount::Treasury,
+            BookAccount::user(submitter_id),
+            reward_amount,
+            Some(report_id),
+        );
+    }
+    store::record_reward_paid(api::time(), reward_amount);
+    notify(submitter_id, NotificationEvent::RewardCredited { report_id, amount: reward_amount });
+    
+    // Update authority stats
+    let mut stats = store::get_authority_stats();
+    stats.total_rewards_distributed += reward_amount;
+    store::update_authority_stats(stats);
+    
+    Ok(())
+}
+
+// Reject a report (for authority)
+#[ic_cdk::update]
+fn reject_report(report_id: u64, notes: Option<String>) -> Result<(), String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
+    
+    // Get the report
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    check_transition(&report, ReportAction::Reject, authority_id)?;
+    
+    let submitter_id = report.submitter_id;
+    let stake_amount = report.stake_amount;
+    
+    // Get submitter
+    let submitter = match store::get_user(submitter_id) {
+        Some(user) => user,
+        None => return Err("Report submitter not found".to_string()),
+    };
+    
+    // Update submitter's stakes (stake is lost)
+    let updated_submitter = settle_stake(&submitter, stake_amount, stake_amount)?;
+    
+    transition_report(
+        report,
+        ReportAction::Reject,
+        authority_id,
+        notes,
+        format!("This report has been rejected. The staked {} tokens have been lost.", stake_amount),
+    )?;
+    
+    save_user("reject_report", Some(&submitter), updated_submitter);
+    
+    slash_stake(report_id, stake_amount);
+    notify(submitter_id, NotificationEvent::StakeLost { report_id, amount: stake_amount });
+    
+    Ok(())
+}
+
+// Reject a report that was made in good faith, such as a duplicate or one outside
+// this authority's jurisdiction (for authority). Part or all of the stake is
+// refunded per the slashing policy; the rest is slashed as usual.
+#[ic_cdk::update]
+fn soft_reject_report(report_id: u64, reason: SoftRejectReason, notes: Option<String>) -> Result<u64, String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    let submitter_id = report.submitter_id;
+    let stake_amount = report.stake_amount;
+    let refund_amount = stake_amount * store::get_slashing_policy().soft_reject_refund_percent / 100;
+    let slashed_amount = stake_amount - refund_amount;
+    
+    let grounds = match reason {
+        SoftRejectReason::Duplicate => "it duplicates an existing report",
+        SoftRejectReason::OutOfJurisdiction => "it is outside this authority's jurisdiction",
+    };
+    
+    check_transition(&report, ReportAction::SoftReject, authority_id)?;
+    
+    let submitter = match store::get_user(submitter_id) {
+        Some(user) => user,
+        None => return Err("Report submitter not found".to_string()),
+    };
+    
+    let updated_submitter = settle_stake(&submitter, stake_amount, slashed_amount)?;
+    
+    transition_report(
+        report,
+        ReportAction::SoftReject,
+        authority_id,
+        notes,
+        format!(
+            "This report has been rejected because {}. {} of the staked {} tokens have been refunded.",
+            grounds, refund_amount, stake_amount
+        ),
+    )?;
+    
+    save_user("soft_reject_report", Some(&submitter), updated_submitter);
+    
+    if ledger_mode() {
+        queue_ledger_transfer(report_id, LedgerTransferKind::StakeRefund, submitter_id, refund_amount);
+    } else {
+        post_entry(
+            EntryKind::StakeRefund,
+            BookAccount::Escrow { report_id },
+            BookAccount::user(submitter_id),
+            refund_amount,
+            Some(report_id),
+        );
+    }
+    slash_stake(report_id, slashed_amount);
+    if slashed_amount > 0 {
+        notify(submitter_id, NotificationEvent::StakeLost { report_id, amount: slashed_amount });
+    }
+    
+    Ok(refund_amount)
+}
+
+// Split a slashed stake out of its escrow per the slashing policy, one journal
+// entry per destination. Rounding leftovers are burned. On an external ledger each
+// share is a transfer out of the escrow subaccount, with the burned share sent to
+// the ledger's minting account.
+fn slash_stake(report_id: u64, amount: u64) {
+    let policy = store::get_slashing_policy();
+    let to_treasury = amount * policy.treasury_percent / 100;
+    let to_reviewers = amount * policy.reviewer_pool_percent / 100;
+    let burned = amount - to_treasury - to_reviewers;
+    
+    if ledger_mode() {
+        for (kind, share) in [
+            (LedgerTransferKind::SlashBurn, burned),
+            (LedgerTransferKind::SlashToTreasury, to_treasury),
+            (LedgerTransferKind::SlashToReviewerPool, to_reviewers),
+        ] {
+            queue_ledger_transfer(report_id, kind, api::id(), share);
+        }
+        return;
+    }
+    
+    for (destination, share) in [
+        (BookAccount::Minting, burned),
+        (BookAccount::Treasury, to_treasury),
+        (BookAccount::ReviewerPool, to_reviewers),
+    ] {
+        post_entry(EntryKind::StakeSlash, BookAccount::Escrow { report_id }, destination, share, Some(report_id));
+    }
+}
+
+// Withdraw one of the caller's own reports before it is decided. Part of the
+// stake is refunded; the rest is forfeited.
+#[ic_cdk::update]
+fn withdraw_report(report_id: u64, reason: Option<String>) -> Result<u64, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot withdraw reports".to_string());
+    }
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    if report.submitter_id != caller {
+        return Err("You can only withdraw your own reports".to_string());
+    }
+    
+    let stake_amount = report.stake_amount;
+    let refund_amount = stake_amount * store::get_slashing_policy().withdrawal_refund_percent / 100;
+    let forfeited_amount = stake_amount - refund_amount;
+    
+    check_transition(&report, ReportAction::Withdraw, caller)?;
+    
+    let submitter = match store::get_user(caller) {
+        Some(user) => user,
+        None => return Err("Report submitter not found".to_string()),
+    };
+    
+    let updated_submitter = settle_stake(&submitter, stake_amount, forfeited_amount)?;
+    
+    transition_report(
+        report,
+        ReportAction::Withdraw,
+        caller,
+        reason,
+        format!(
+            "This report has been withdrawn by the reporter. {} of the staked {} tokens have been refunded.",
+            refund_amount, stake_amount
+        ),
+    )?;
+    
+    save_user("withdraw_report", Some(&submitter), updated_submitter);
+    
+    if ledger_mode() {
+        queue_ledger_transfer(report_id, LedgerTransferKind::WithdrawalRefund, caller, refund_amount);
+    } else {
+        post_entry(
+            EntryKind::StakeRefund,
+            BookAccount::Escrow { report_id },
+            BookAccount::user(caller),
+            refund_amount,
+            Some(report_id),
+        );
+    }
+    slash_stake(report_id, forfeited_amount);
+    
+    Ok(refund_amount)
+}
+
+// Work out where a reopened report's slashed stake is paid back from. Each share
+// comes back from the book it was slashed to; burned tokens, which no book holds,
+// come from the old slashed-stake pool and then the treasury. Stakes slashed before
+// the journal existed count as burned. Fails if the books cannot cover it.
+fn stake_reinstatement_sources(report_id: u64, stake_amount: u64) -> Result<Vec<(BookAccount, u64)>, String> {
+    let (mut from_pool, mut from_treasury, mut from_reviewers) = (0u64, 0u64, 0u64);
+    for entry in store::get_report_slashes(report_id) {
+        match entry.credit {
+            BookAccount::SlashedPool => from_pool += entry.amount,
+            BookAccount::Treasury => from_treasury += entry.amount,
+            BookAccount::ReviewerPool => from_reviewers += entry.amount,
+            _ => {}
+        }
+    }
+    
+    let burned = stake_amount.saturating_sub(from_pool + from_treasury + from_reviewers);
+    let pool_spare = store::get_system_balance(&BookAccount::SlashedPool).saturating_sub(from_pool);
+    let burned_from_pool = burned.min(pool_spare);
+    from_pool += burned_from_pool;
+    from_treasury += burned - burned_from_pool;
+    
+    let sources = [
+        (BookAccount::SlashedPool, from_pool, "slashed-stake pool"),
+        (BookAccount::Treasury, from_treasury, "treasury"),
+        (BookAccount::ReviewerPool, from_reviewers, "reviewer pool"),
+    ];
+    for (book, amount, name) in &sources {
+        let balance = store::get_system_balance(book);
+        if balance < *amount {
+            return Err(format!(
+                "The {} holds {} tokens, not enough to put back the {} tokens it received from this report",
+                name, balance, amount
+            ));
+        }
+    }
+    
+    Ok(sources.into_iter()
+        .filter(|(_, amount, _)| *amount > 0)
+        .map(|(book, amount, _)| (book, amount))
+        .collect())
+}
+
+// The ledger transfers that put a reopened report's slashed stake back in escrow.
+// Each completed slash took its share out of escrow fee included, so the reviewer
+// pool only received its share less that fee; it returns what it received less
+// the fee for sending it back, and the canister's account makes up the rest.
+// Shares whose slash failed never left escrow. The ledger rejects a reinstatement
+// the source cannot cover, leaving it failed for a retry.
+fn ledger_stake_reinstatement(report_id: u64) -> Result<Vec<(LedgerTransferKind, u64)>, String> {
+    let (mut slashed, mut reviewer_pool_received) = (0u64, 0u64);
+    
+    let transfers = store::get_ledger_transfers();
+    let since_reinstatement = transfers.iter()
+        .rev()
+        .filter(|transfer| transfer.report_id == report_id)
+        .take_while(|transfer| !matches!(
+            transfer.kind,
+            LedgerTransferKind::ReinstateFromTreasury | LedgerTransferKind::ReinstateFromReviewerPool
+        ));
+    
+    for transfer in since_reinstatement {
+        if !matches!(
+            transfer.kind,
+            LedgerTransferKind::SlashToReviewerPool | LedgerTransferKind::SlashToTreasury | LedgerTransferKind::SlashBurn
+        ) {
+            continue;
+        }
+        
+        match transfer.status {
+            LedgerTransferStatus::Completed { .. } => {
+                slashed += transfer.amount;
+                if transfer.kind == LedgerTransferKind::SlashToReviewerPool {
+                    reviewer_pool_received += transfer.amount.saturating_sub(transfer.fee.unwrap_or(0));
+                }
+            }
+            LedgerTransferStatus::Failed { .. } => {}
+            LedgerTransferStatus::Pending | LedgerTransferStatus::InFlight => {
+                return Err("The slashed stake is still being moved on the ledger; try again once its transfers complete".to_string());
+            }
+        }
+    }
+    
+    let from_reviewers = reviewer_pool_received.saturating_sub(store::get_ledger_config().fee);
+    
+    Ok(vec![
+        (LedgerTransferKind::ReinstateFromTreasury, slashed - from_reviewers),
+        (LedgerTransferKind::ReinstateFromReviewerPool, from_reviewers),
+    ])
+}
+
+// Give a rejected report another review (for authority). The slashed stake is paid
+// back into escrow from where the slashing policy sent it, so it can still be
+// returned if the report is verified.
+#[ic_cdk::update]
+fn reopen_report(report_id: u64, notes: Option<String>) -> Result<(), String> {
+    let authority_id = require_permission(Permission::ReviewReports)?;
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    // A soft reject already refunded the stake, so there is nothing to put back at stake
+    let last_action = store::get_report_status_history(report_id).last().map(|change| change.action);
+    if last_action == Some(ReportAction::SoftReject) {
+        return Err("Soft-rejected reports cannot be reopened; the reporter can submit a new report".to_string());
+    }
+    
+    check_transition(&report, ReportAction::Reopen, authority_id)?;
+    
+    let submitter_id = report.submitter_id;
+    let stake_amount = report.stake_amount;
+    
+    let submitter = match store::get_user(submitter_id) {
+        Some(user) => user,
+        None => return Err("Report submitter not found".to_string()),
+    };
+    
+    let mut updated_submitter = submitter.clone();
+    updated_submitter.stakes_lost = submitter.stakes_lost
+        .checked_sub(stake_amount)
+        .ok_or_else(|| "The submitter's lost stakes do not include this report's stake".to_string())?;
+    updated_submitter.stakes_active = submitter.stakes_active
+        .checked_add(stake_amount)
+        .ok_or_else(|| "The submitter's active stakes overflow".to_string())?;
+    
+    // Work out the reinstatement before changing anything, so a shortfall leaves the report rejected
+    let (ledger_transfers, book_sources) = if ledger_mode() {
+        (ledger_stake_reinstatement(report_id)?, Vec::new())
+    } else {
+        (Vec::new(), stake_reinstatement_sources(report_id, stake_amount)?)
+    };
+    
+    transition_report(
+        report,
+        ReportAction::Reopen,
+        authority_id,
+        notes,
+        format!("This report has been reopened. The staked {} tokens are at stake again.", stake_amount),
+    )?;
+    
+    save_user("reopen_report", Some(&submitter), updated_submitter);
+    
+    for (kind, amount) in ledger_transfers {
+        queue_ledger_transfer(report_id, kind, api::id(), amount);
+    }
+    for (source, amount) in book_sources {
+        post_entry(
+            EntryKind::StakeReinstate,
+            source,
+            BookAccount::Escrow { report_id },
+            amount,
+            Some(report_id),
+        );
+    }
+    
+    Ok(())
+}
+
+// Ask the reporter for more evidence (for authority)
+#[ic_cdk::update]
+fn request_more_evidence(report_id: u64, note: Option<String>) -> Result<(), String> {
+    require_permission(Permission::ReviewReports)?;
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    if matches!(report.status, ReportStatus::Approved | ReportStatus::Rejected | ReportStatus::Withdrawn) {
+        return Err(format!("Cannot request evidence for a report in {:?} state", report.status));
+    }
+    
+    let message = Message {
+        id: 0,
+        report_id,
+        sender: MessageSender::System,
+        content: match &note {
+            Some(note) => format!("An authority has requested more evidence: {}", note),
+            None => "An authority has requested more evidence.".to_string(),
+        },
         timestamp: api::time(),
         attachment: None,
+        encrypted: None,
+    };
+    
+    let message_id = store::create_message(&message);
+    audit(
+        "request_more_evidence",
+        vec![format!("report:{}", report_id), format!("message:{}", message_id)],
+        None,
+        Some(store::digest(&Message { id: message_id, ..message })),
+    );
+    
+    notify(report.submitter_id, NotificationEvent::EvidenceRequested { report_id, note });
+    
+    Ok(())
+}
+
+// Get the status history of a report (for both users and authority)
+#[ic_cdk::query]
+fn get_report_history(report_id: u64) -> Result<Vec<StatusChangeView>, String> {
+    let report = ensure_can_view_report(report_id)?;
+    let reporter = store::reporter_pseudonym(report_id);
+    
+    Ok(store::get_report_status_history(report_id)
+        .into_iter()
+        .map(|change| StatusChangeView::new(change, report.submitter_id, &reporter))
+        .collect())
+}
+
+// Check a key before it goes into the registry
+fn validate_public_key(algorithm: &str, public_key: &[u8]) -> Result<(), String> {
+    if algorithm.trim().is_empty() || algorithm.len() > MAX_KEY_ALGORITHM_LEN {
+        return Err(format!("Key algorithm must be 1 to {} characters", MAX_KEY_ALGORITHM_LEN));
+    }
+    
+    if public_key.is_empty() || public_key.len() > MAX_PUBLIC_KEY_SIZE {
+        return Err(format!("Public key must be 1 to {} bytes", MAX_PUBLIC_KEY_SIZE));
+    }
+    
+    Ok(())
+}
+
+// Registry keys shown with reporter keys labelled by pseudonym
+fn public_key_view(key: PublicKeyRecord) -> PublicKeyView {
+    let owner = match &key.owner {
+        KeyOwner::Reporter { report_id } => Participant::Reporter(store::reporter_pseudonym(*report_id)),
+        KeyOwner::Authority(id) => Participant::Authority(*id),
+    };
+    
+    PublicKeyView::new(key, owner)
+}
+
+// Current keys a message on this report must be wrapped for: the reporter's key
+// for the report and the keys of authorities who can read reports
+fn message_recipient_keys(report_id: u64) -> Vec<PublicKeyRecord> {
+    let mut keys: Vec<PublicKeyRecord> = store::get_current_key(&KeyOwner::Reporter { report_id })
+        .into_iter()
+        .collect();
+    
+    keys.extend(store::get_current_authority_keys().into_iter().filter(|key| match key.owner {
+        KeyOwner::Authority(id) => has_permission(id, Permission::ViewReports),
+        KeyOwner::Reporter { .. } => false,
+    }));
+    
+    keys
+}
+
+// Check an encrypted body against the sender's current key and the report's recipients
+fn validate_encrypted_content(report_id: u64, sender: &KeyOwner, content: &EncryptedContent) -> Result<(), String> {
+    if content.algorithm.trim().is_empty() || content.algorithm.len() > MAX_KEY_ALGORITHM_LEN {
+        return Err(format!("Encryption algorithm must be 1 to {} characters", MAX_KEY_ALGORITHM_LEN));
+    }
+    
+    if content.ciphertext.is_empty() || content.ciphertext.len() > MAX_MESSAGE_CIPHERTEXT_SIZE {
+        return Err(format!("Ciphertext must be 1 to {} bytes", MAX_MESSAGE_CIPHERTEXT_SIZE));
+    }
+    
+    if content.nonce.len() > MAX_MESSAGE_NONCE_SIZE {
+        return Err(format!("Nonce must be at most {} bytes", MAX_MESSAGE_NONCE_SIZE));
+    }
+    
+    match store::get_current_key(sender) {
+        Some(key) if key.id == content.sender_key_id => {}
+        Some(_) => return Err("sender_key_id is not your current messaging key".to_string()),
+        None => return Err("Register a messaging key before sending messages".to_string()),
+    }
+    
+    if content.wrapped_keys.is_empty() || content.wrapped_keys.len() > MAX_WRAPPED_KEYS {
+        return Err(format!("A message must be wrapped for 1 to {} keys", MAX_WRAPPED_KEYS));
+    }
+    
+    let recipients = message_recipient_keys(report_id);
+    let mut seen = BTreeSet::new();
+    
+    for wrapped in &content.wrapped_keys {
+        if !seen.insert(wrapped.key_id) {
+            return Err(format!("Key {} is wrapped more than once", wrapped.key_id));
+        }
+        
+        if wrapped.wrapped_key.is_empty() || wrapped.wrapped_key.len() > MAX_PUBLIC_KEY_SIZE {
+            return Err(format!("Wrapped key for key {} must be 1 to {} bytes", wrapped.key_id, MAX_PUBLIC_KEY_SIZE));
+        }
+        
+        if !recipients.iter().any(|key| key.id == wrapped.key_id) {
+            return Err(format!(
+                "Key {} is not a current recipient key for this report; fetch the keys again",
+                wrapped.key_id
+            ));
+        }
+    }
+    
+    // The other side of the conversation must be able to read the message
+    let counterpart_included = recipients.iter()
+        .filter(|key| matches!(
+            (sender, &key.owner),
+            (KeyOwner::Authority(_), KeyOwner::Reporter { .. }) | (KeyOwner::Reporter { .. }, KeyOwner::Authority(_))
+        ))
+        .any(|key| seen.contains(&key.id));
+    
+    if !counterpart_included {
+        return Err(match sender {
+            KeyOwner::Authority(_) => "The message must be wrapped for the reporter's current key".to_string(),
+            KeyOwner::Reporter { .. } => "The message must be wrapped for at least one authority key".to_string(),
+        });
+    }
+    
+    Ok(())
+}
+
+// Register or rotate the caller's messaging key for one of their reports
+#[ic_cdk::update]
+fn register_reporter_key(report_id: u64, algorithm: String, public_key: Vec<u8>) -> Result<u64, String> {
+    let caller = caller();
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    if report.submitter_id != caller {
+        return Err("You can only register keys for your own reports".to_string());
+    }
+    
+    validate_public_key(&algorithm, &public_key)?;
+    
+    let key = PublicKeyRecord {
+        id: 0, // Will be assigned by register_public_key
+        owner: KeyOwner::Reporter { report_id },
+        algorithm,
+        public_key,
+        registered_at: api::time(),
+        superseded_at: None,
+    };
+    
+    let key_id = store::register_public_key(&key);
+    audit(
+        "register_reporter_key",
+        vec![format!("report:{}", report_id), format!("key:{}", key_id)],
+        None,
+        Some(store::digest(&PublicKeyRecord { id: key_id, ..key })),
+    );
+    
+    Ok(key_id)
+}
+
+// Register or rotate the caller's messaging key (for authority)
+#[ic_cdk::update]
+fn register_authority_key(algorithm: String, public_key: Vec<u8>) -> Result<u64, String> {
+    let authority_id = require_permission(Permission::ViewReports)?;
+    
+    validate_public_key(&algorithm, &public_key)?;
+    
+    let key = PublicKeyRecord {
+        id: 0, // Will be assigned by register_public_key
+        owner: KeyOwner::Authority(authority_id),
+        algorithm,
+        public_key,
+        registered_at: api::time(),
+        superseded_at: None,
+    };
+    
+    let key_id = store::register_public_key(&key);
+    audit(
+        "register_authority_key",
+        vec![format!("authority:{}", authority_id), format!("key:{}", key_id)],
+        None,
+        Some(store::digest(&PublicKeyRecord { id: key_id, ..key })),
+    );
+    
+    // Evidence wrapped for the previous key now needs wrapping for this one
+    sync_evidence_access("register_authority_key", authority_id);
+    
+    Ok(key_id)
+}
+
+// Current keys a new message on a report must be wrapped for
+#[ic_cdk::query]
+fn get_message_keys(report_id: u64) -> Result<Vec<PublicKeyView>, String> {
+    ensure_can_view_report(report_id)?;
+    Ok(message_recipient_keys(report_id).into_iter().map(public_key_view).collect())
+}
+
+// Look up any registry key, including rotated ones
+#[ic_cdk::query]
+fn get_public_key(key_id: u64) -> Result<PublicKeyView, String> {
+    let key = match store::get_public_key(key_id) {
+        Some(key) => key,
+        None => return Err("Key not found".to_string()),
+    };
+    
+    match key.owner {
+        KeyOwner::Reporter { report_id } => {
+            ensure_can_view_report(report_id)?;
+        }
+        KeyOwner::Authority(_) => {
+            if caller() == Principal::anonymous() {
+                return Err("Anonymous callers cannot look up keys".to_string());
+            }
+        }
+    }
+    
+    Ok(public_key_view(key))
+}
+
+// Check that an attachment can go out with the caller's next message on a report
+fn unsent_attachment(attachment_id: u64, report_id: u64, sender: Principal) -> Result<Attachment, String> {
+    let attachment = match store::get_attachment(attachment_id) {
+        Some(attachment) => attachment,
+        None => return Err("Attachment not found".to_string()),
+    };
+    
+    if attachment.uploader != sender {
+        return Err("You can only send your own attachments".to_string());
+    }
+    
+    if attachment.report_id != report_id {
+        return Err("Attachment was uploaded for a different report".to_string());
+    }
+    
+    if attachment.message_id.is_some() {
+        return Err("Attachment has already been sent".to_string());
+    }
+    
+    Ok(attachment)
+}
+
+// Store a message, link its attachment to it and audit both
+fn post_message(method: &str, message: Message, attachment: Option<Attachment>) -> u64 {
+    let message_id = store::create_message(&message);
+    let mut targets = vec![format!("report:{}", message.report_id), format!("message:{}", message_id)];
+    
+    if let Some(mut attachment) = attachment {
+        targets.push(format!("attachment:{}", attachment.id));
+        attachment.message_id = Some(message_id);
+        store::update_attachment(attachment);
+    }
+    
+    // Tell the other side of the conversation
+    if let Some(report) = store::get_report(message.report_id) {
+        let recipient = match message.sender {
+            MessageSender::Authority(_) => Some(report.submitter_id),
+            MessageSender::Reporter(_) => report.reviewer,
+            MessageSender::System => None,
+        };
+        
+        if let Some(recipient) = recipient {
+            notify(recipient, NotificationEvent::NewMessage { report_id: report.id, message_id });
+        }
+    }
+    
+    audit(method, targets, None, Some(store::digest(&Message { id: message_id, ..message })));
+    
+    message_id
+}
+
+// Send an encrypted message as authority, optionally with an uploaded attachment
+#[ic_cdk::update]
+fn send_message_as_authority(
+    report_id: u64,
+    content: EncryptedContent,
+    attachment_id: Option<u64>,
+) -> Result<(), String> {
+    let authority_id = require_permission(Permission::MessageReporters)?;
+    
+    // Check if report exists
+    if store::get_report(report_id).is_none() {
+        return Err("Report not found".to_string());
+    }
+    
+    validate_encrypted_content(report_id, &KeyOwner::Authority(authority_id), &content)?;
+    
+    let attachment = match attachment_id {
+        Some(attachment_id) => Some(unsent_attachment(attachment_id, report_id, authority_id)?),
+        None => None,
+    };
+    
+    // Create message
+    let message = Message {
+        id: 0,
+        report_id,
+        sender: MessageSender::Authority(authority_id),
+        content: String::new(),
+        timestamp: api::time(),
+        attachment: attachment.as_ref().map(MessageAttachment::from),
+        encrypted: Some(content),
+    };
+    
+    post_message("send_message_as_authority", message, attachment);
+    
+    Ok(())
+}
+
+// Send an encrypted message as informer, optionally with an uploaded attachment
+#[ic_cdk::update]
+fn send_message_as_reporter(
+    report_id: u64,
+    content: EncryptedContent,
+    attachment_id: Option<u64>,
+) -> Result<(), String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot send messages".to_string());
+    }
+    
+    // Check if report exists and caller is the submitter
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    if report.submitter_id != caller {
+        return Err("You can only send messages for your own reports".to_string());
+    }
+    
+    validate_encrypted_content(report_id, &KeyOwner::Reporter { report_id }, &content)?;
+    
+    let attachment = match attachment_id {
+        Some(attachment_id) => Some(unsent_attachment(attachment_id, report_id, caller)?),
+        None => None,
+    };
+    
+    // Create message
+    let message = Message {
+        id: 0,
+        report_id,
+        sender: MessageSender::Reporter(caller),
+        content: String::new(),
+        timestamp: api::time(),
+        attachment: attachment.as_ref().map(MessageAttachment::from),
+        encrypted: Some(content),
+    };
+    
+    post_message("send_message_as_reporter", message, attachment);
+    
+    Ok(())
+}
+
+// Get messages for a report
+#[ic_cdk::query]
+fn get_messages(report_id: u64) -> Vec<MessageView> {
+    let caller = caller();
+    
+    // Check if report exists
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return vec![],
+    };
+    
+    // Check if caller is authorized to see messages
+    if report.submitter_id != caller && !has_permission(caller, Permission::ViewReports) {
+        return vec![];
+    }
+    
+    let reporter = store::reporter_pseudonym(report_id);
+    store::get_report_messages(report_id)
+        .into_iter()
+        .map(|message| MessageView::new(message, &reporter))
+        .collect()
+}
+
+// Poll for messages newer than after_id (for both users and authority)
+#[ic_cdk::query]
+fn get_messages_since(report_id: u64, after_id: u64, limit: u32) -> Result<Vec<MessageView>, String> {
+    ensure_can_view_report(report_id)?;
+    
+    let limit = limit.clamp(1, MAX_MESSAGE_PAGE_SIZE) as usize;
+    let reporter = store::reporter_pseudonym(report_id);
+    
+    Ok(store::get_report_messages_after(report_id, after_id, limit)
+        .into_iter()
+        .map(|message| MessageView::new(message, &reporter))
+        .collect())
+}
+
+// Move the caller's read cursor forward (for both users and authority).
+// Returns the cursor, which never moves backwards or past the latest message.
+#[ic_cdk::update]
+fn mark_messages_read(report_id: u64, up_to_id: u64) -> Result<u64, String> {
+    ensure_can_view_report(report_id)?;
+    
+    let caller = caller();
+    let current = store::get_read_cursor(report_id, caller);
+    let latest = store::get_last_message_id(report_id).unwrap_or(0);
+    let cursor = up_to_id.min(latest).max(current);
+    
+    if cursor != current {
+        store::set_read_cursor(report_id, caller, cursor);
+    }
+    
+    Ok(cursor)
+}
+
+// How far each side of a conversation has read (for both users and authority)
+#[ic_cdk::query]
+fn get_read_receipts(report_id: u64) -> Result<ReadReceipts, String> {
+    let report = ensure_can_view_report(report_id)?;
+    
+    let mut receipts = ReadReceipts {
+        report_id,
+        reporter_read_up_to: 0,
+        authorities: Vec::new(),
+    };
+    
+    for (reader, up_to_id) in store::get_read_cursors(report_id) {
+        if reader == report.submitter_id {
+            receipts.reporter_read_up_to = up_to_id;
+        } else {
+            receipts.authorities.push((reader, up_to_id));
+        }
+    }
+    
+    Ok(receipts)
+}
+
+// Page through the caller's notification inbox, oldest first
+#[ic_cdk::query]
+fn get_notifications(after_id: u64, unread_only: bool, limit: u32) -> Vec<Notification> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return vec![];
+    }
+    
+    let limit = limit.clamp(1, MAX_NOTIFICATION_PAGE_SIZE) as usize;
+    store::get_notifications(caller, after_id, unread_only, limit)
+}
+
+// Mark one of the caller's notifications as read
+#[ic_cdk::update]
+fn mark_notification_read(notification_id: u64) -> Result<(), String> {
+    let caller = caller();
+    
+    let mut notification = match store::get_notification(caller, notification_id) {
+        Some(notification) => notification,
+        None => return Err("Notification not found".to_string()),
+    };
+    
+    if !notification.read {
+        notification.read = true;
+        store::update_notification(caller, notification);
+    }
+    
+    Ok(())
+}
+
+#[ic_cdk::query]
+fn get_notification_preferences() -> NotificationPreferences {
+    store::get_notification_preferences(caller())
+}
+
+// Choose which event types reach the caller's inbox
+#[ic_cdk::update]
+fn set_notification_preferences(preferences: NotificationPreferences) -> Result<(), String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot set preferences".to_string());
+    }
+    
+    let mut muted = Vec::new();
+    for kind in preferences.muted {
+        if !muted.contains(&kind) {
+            muted.push(kind);
+        }
+    }
+    
+    store::set_notification_preferences(caller, NotificationPreferences { muted });
+    
+    Ok(())
+}
+
+// Get user token balance
+#[ic_cdk::query]
+fn get_user_balance() -> u64 {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return 0;
+    }
+    
+    match store::get_user(caller) {
+        Some(user) => user.token_balance,
+        None => 0
+    }
+}
+
+// Check that the internal token books balance (for authority)
+#[ic_cdk::query]
+fn check_ledger_invariants() -> Result<LedgerInvariants, String> {
+    require_permission(Permission::ViewStatistics)?;
+    
+    Ok(store::check_ledger_invariants())
+}
+
+// Journal entries that touch the caller's tokens or the stakes of their reports,
+// oldest first. Pass the last seen entry ID to page forward.
+#[ic_cdk::query]
+fn get_my_transactions(after_id: u64, limit: u32) -> Vec<JournalEntry> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Vec::new();
+    }
+    
+    let limit = limit.clamp(1, MAX_TRANSACTION_PAGE_SIZE) as usize;
+    store::get_party_entries(caller, after_id, limit)
+}
+
+// Checks shared by transfer and icrc1_transfer: internal balances only, a registered
+// recipient, and the sender's daily limits. Returns the day to record the transfer under.
+pub(crate) fn check_peer_transfer(sender: Principal, to: Principal, amount: u64, now: u64) -> Result<u64, String> {
+    if ledger_mode() {
+        return Err("With ledger-backed staking, tokens are transferred on the ledger".to_string());
+    }
+    
+    if store::get_user(to).is_none() {
+        return Err("Recipient is not registered".to_string());
+    }
+    
+    let config = store::get_transfer_config();
+    let day = now / store::DAY_NS;
+    let (sent_today, transfers_today) = store::get_daily_transfers(sender, day);
+    
+    if let Some(limit) = config.daily_amount_limit {
+        if sent_today.saturating_add(amount) > limit {
+            return Err(format!(
+                "Transfers are limited to {} tokens per day and {} have been sent today",
+                limit, sent_today
+            ));
+        }
+    }
+    
+    if let Some(limit) = config.daily_count_limit {
+        if transfers_today >= limit {
+            return Err(format!("Transfers are limited to {} per day", limit));
+        }
+    }
+    
+    Ok(day)
+}
+
+// Send tokens from the caller's balance to another registered user. Staked tokens
+// are held in report escrows rather than the balance, so they cannot be sent.
+// Retrying with the same idempotency key returns the original transfer.
+#[ic_cdk::update]
+fn transfer(to: Principal, amount: u64, memo: Option<String>, idempotency_key: String) -> Result<JournalEntry, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot transfer tokens".to_string());
+    }
+    
+    if idempotency_key.is_empty() || idempotency_key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(format!("Idempotency key must be 1 to {} bytes", MAX_IDEMPOTENCY_KEY_LEN));
+    }
+    
+    let memo = memo.map(String::into_bytes);
+    let mut hasher = Sha256::new();
+    hasher.update([caller.as_slice().len() as u8]);
+    hasher.update(caller.as_slice());
+    hasher.update(idempotency_key.as_bytes());
+    let key_hash: [u8; 32] = hasher.finalize().into();
+    
+    if let Some(entry) = store::find_idempotent_entry(&key_hash) {
+        if entry.credit == BookAccount::user(to) && entry.amount == amount && entry.memo == memo {
+            return Ok(entry);
+        }
+        return Err("This idempotency key was already used for a different transfer".to_string());
+    }
+    
+    if to == caller {
+        return Err("Cannot transfer tokens to yourself".to_string());
+    }
+    
+    if amount == 0 {
+        return Err("Amount must be at least 1 token".to_string());
+    }
+    
+    if memo.as_ref().is_some_and(|memo| memo.len() > MAX_TRANSFER_MEMO_SIZE) {
+        return Err(format!("Memos are limited to {} bytes", MAX_TRANSFER_MEMO_SIZE));
+    }
+    
+    let now = api::time();
+    let day = check_peer_transfer(caller, to, amount, now)?;
+    
+    let balance = store::get_user(caller).map_or(0, |user| user.token_balance);
+    if balance < amount {
+        return Err(format!(
+            "Insufficient balance: {} tokens available (staked tokens cannot be transferred)",
+            balance
+        ));
+    }
+    
+    let entry = store::post_entry(&JournalEntry {
+        id: 0, // Will be assigned by post_entry
+        timestamp: now,
+        kind: EntryKind::Transfer,
+        debit: BookAccount::user(caller),
+        credit: BookAccount::user(to),
+        amount,
+        report_id: None,
+        memo,
+        created_at_time: None,
+        block_index: None,
+    })?;
+    store::record_daily_transfer(caller, day, amount);
+    store::record_idempotency_key(key_hash, entry.id);
+    
+    audit(
+        "transfer",
+        vec![format!("user:{}", caller), format!("user:{}", to), format!("journal_entry:{}", entry.id)],
+        None,
+        Some(store::digest(&entry)),
+    );
+    
+    Ok(entry)
+}
+
+// Get the daily transfer limits
+#[ic_cdk::query]
+fn get_transfer_config() -> TransferConfig {
+    store::get_transfer_config()
+}
+
+// Change the daily transfer limits (for authority)
+#[ic_cdk::update]
+fn update_transfer_config(config: TransferConfig) -> Result<TransferConfig, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    if config.daily_amount_limit == Some(0) || config.daily_count_limit == Some(0) {
+        return Err("Limits must allow at least one token and one transfer; leave them empty for no limit".to_string());
+    }
+    
+    let current = store::get_transfer_config();
+    audit(
+        "update_transfer_config",
+        vec!["transfer_config".to_string()],
+        Some(store::digest(&current)),
+        Some(store::digest(&config)),
+    );
+    store::set_transfer_config(config.clone());
+    
+    Ok(config)
+}
+
+// Create a user record for `id` if it has none yet
+fn ensure_user(id: Principal, method: &str) {
+    if store::get_user(id).is_none() {
+        save_user_as(id, method, None, User::new(id));
+    }
+}
+
+// Check an onboarding grant against the day's budget and, for open grants, the
+// recipient's cooldown. Invite codes are single-use, so they skip the cooldown.
+fn check_onboarding_grant(recipient: Principal, amount: u64, apply_cooldown: bool) -> Result<(), String> {
+    if ledger_mode() {
+        return Err("Onboarding grants are only available with internal balances".to_string());
+    }
+    
+    let config = store::get_onboarding_config();
+    let now = api::time();
+    
+    if let Some(last_grant) = store::get_last_grant(recipient).filter(|_| apply_cooldown) {
+        match config.grant_cooldown_ns {
+            None => return Err("This account has already received an onboarding grant".to_string()),
+            Some(cooldown) if now < last_grant.saturating_add(cooldown) => {
+                return Err("This account received a grant recently; try again later".to_string());
+            }
+            Some(_) => {}
+        }
+    }
+    
+    let granted_today = store::get_tokens_granted(now / store::DAY_NS);
+    if granted_today.saturating_add(amount) > config.daily_grant_budget {
+        return Err("Today's onboarding budget has been used up; try again tomorrow".to_string());
+    }
+    
+    Ok(())
+}
+
+// Grant onboarding tokens that check_onboarding_grant has allowed
+fn grant_onboarding_tokens(recipient: Principal, method: &str, amount: u64) {
+    ensure_user(recipient, method);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::user(recipient), amount, None);
+    store::record_grant(recipient, api::time(), amount);
+}
+
+// Hash under which an invite code is stored. Dashes, spaces and case are ignored.
+fn voucher_code_hash(code: &str) -> [u8; 32] {
+    let normalized: String = code
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    Sha256::digest(normalized.as_bytes()).into()
+}
+
+// Create the caller's user record without any tokens. With ledger-backed staking
+// this is the only way to join.
+#[ic_cdk::update]
+fn register() -> Result<User, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot register".to_string());
+    }
+    
+    if store::get_user(caller).is_some() {
+        return Err("Already registered".to_string());
+    }
+    
+    ensure_user(caller, "register");
+    store::get_user(caller).ok_or_else(|| "Registration failed".to_string())
+}
+
+// Claim the starter grant without an invite code, when open grants are enabled.
+// Returns the caller's new balance.
+#[ic_cdk::update]
+fn claim_onboarding_grant() -> Result<u64, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot claim grants".to_string());
+    }
+    
+    let config = store::get_onboarding_config();
+    if !config.open_grants {
+        return Err("Onboarding grants require an invite code".to_string());
+    }
+    
+    check_onboarding_grant(caller, config.grant_amount, true)?;
+    grant_onboarding_tokens(caller, "claim_onboarding_grant", config.grant_amount);
+    
+    Ok(store::get_user(caller).map_or(0, |user| user.token_balance))
+}
+
+// Redeem an invite code for its grant. Returns the caller's new balance.
+#[ic_cdk::update]
+fn redeem_voucher(code: String) -> Result<u64, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot redeem invite codes".to_string());
+    }
+    
+    let voucher = match store::find_voucher(&voucher_code_hash(&code)) {
+        Some(voucher) => voucher,
+        None => return Err("Invalid invite code".to_string()),
+    };
+    
+    if voucher.redeemed_by.is_some() {
+        return Err("This invite code has already been used".to_string());
+    }
+    
+    let now = api::time();
+    if voucher.expires_at.is_some_and(|expires_at| now >= expires_at) {
+        return Err("This invite code has expired".to_string());
+    }
+    
+    check_onboarding_grant(caller, voucher.amount, false)?;
+    
+    // Mark the code used before granting, so it can never pay out twice
+    let mut redeemed = voucher.clone();
+    redeemed.redeemed_by = Some(caller);
+    redeemed.redeemed_at = Some(now);
+    
+    store::update_voucher(redeemed.clone())?;
+    audit(
+        "redeem_voucher",
+        vec![format!("voucher:{}", voucher.id), format!("user:{}", caller)],
+        Some(store::digest(&voucher)),
+        Some(store::digest(&redeemed)),
+    );
+    
+    grant_onboarding_tokens(caller, "redeem_voucher", voucher.amount);
+    
+    Ok(store::get_user(caller).map_or(0, |user| user.token_balance))
+}
+
+// Issue invite codes worth `amount` tokens each (for authorities and partners).
+// The codes are only returned here; the canister keeps just their hashes.
+#[ic_cdk::update]
+async fn issue_vouchers(count: u32, amount: u64, expires_at: Option<u64>) -> Result<Vec<String>, String> {
+    let issuer = require_permission(Permission::IssueVouchers)?;
+    
+    if ledger_mode() {
+        return Err("Invite code grants are only available with internal balances".to_string());
+    }
+    
+    if count == 0 || count > MAX_VOUCHERS_PER_CALL {
+        return Err(format!("Between 1 and {} invite codes can be issued at a time", MAX_VOUCHERS_PER_CALL));
+    }
+    
+    let max_amount = store::get_onboarding_config().max_voucher_amount;
+    if amount == 0 || amount > max_amount {
+        return Err(format!("Invite codes can carry between 1 and {} tokens", max_amount));
+    }
+    
+    if expires_at.is_some_and(|expires_at| expires_at <= api::time()) {
+        return Err("Expiry must be in the future".to_string());
+    }
+    
+    let seed = match api::management_canister::main::raw_rand().await {
+        Ok((bytes,)) => bytes,
+        Err((code, message)) => return Err(format!("Could not generate invite codes ({:?}): {}", code, message)),
+    };
+    
+    let mut codes = Vec::new();
+    for index in 0..count {
+        let mut hasher = Sha256::new();
+        hasher.update(&seed);
+        hasher.update(index.to_be_bytes());
+        let digest = hasher.finalize();
+        
+        let code = digest[..VOUCHER_CODE_BYTES]
+            .chunks(2)
+            .map(|group| group.iter().map(|byte| format!("{:02X}", byte)).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-");
+        
+        let voucher = Voucher {
+            id: 0, // Will be assigned by create_voucher
+            issuer,
+            amount,
+            created_at: api::time(),
+            expires_at,
+            redeemed_by: None,
+            redeemed_at: None,
+        };
+        let voucher_id = store::create_voucher(voucher_code_hash(&code), &voucher);
+        store::append_audit_entry(
+            issuer,
+            "issue_vouchers",
+            vec![format!("voucher:{}", voucher_id)],
+            None,
+            Some(store::digest(&voucher)),
+        );
+        
+        codes.push(code);
+    }
+    
+    Ok(codes)
+}
+
+// Invite codes the caller has issued, without the codes themselves
+#[ic_cdk::query]
+fn get_my_vouchers() -> Result<Vec<Voucher>, String> {
+    let issuer = require_permission(Permission::IssueVouchers)?;
+    Ok(store::get_vouchers_by_issuer(issuer))
+}
+
+// Get the onboarding grant rules (for authority)
+#[ic_cdk::query]
+fn get_onboarding_config() -> Result<OnboardingConfig, String> {
+    require_permission(Permission::ViewStatistics)?;
+    Ok(store::get_onboarding_config())
+}
+
+// Change the onboarding grant rules (for authority)
+#[ic_cdk::update]
+fn update_onboarding_config(config: OnboardingConfig) -> Result<OnboardingConfig, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    if config.grant_amount == 0 || config.max_voucher_amount == 0 {
+        return Err("Grants must be at least 1 token".to_string());
+    }
+    
+    if config.grant_amount > config.daily_grant_budget || config.max_voucher_amount > config.daily_grant_budget {
+        return Err("A single grant cannot exceed the daily grant budget".to_string());
+    }
+    
+    let current = store::get_onboarding_config();
+    audit(
+        "update_onboarding_config",
+        vec!["onboarding_config".to_string()],
+        Some(store::digest(&current)),
+        Some(store::digest(&config)),
+    );
+    store::set_onboarding_config(config.clone());
+    
+    Ok(config)
+}
+
+// Whether stakes and payouts go through an external ledger
+fn ledger_mode() -> bool {
+    store::get_ledger_config().ledger_canister_id.is_some()
+}
+
+// Remember the fee the ledger asked for in a BadFee reply
+fn set_ledger_fee(fee: u64) {
+    let mut config = store::get_ledger_config();
+    config.fee = fee;
+    store::set_ledger_config(config);
+}
+
+// Move a new report's stake from the reporter into its escrow subaccount. If the
+// call fails without an answer, a refund of whatever reached escrow is queued.
+async fn collect_stake(ledger_canister_id: Principal, reporter: Principal, report_id: u64, amount: u64) -> Result<(), String> {
+    let fee = store::get_ledger_config().fee;
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account { owner: reporter, subaccount: None },
+        to: Account { owner: api::id(), subaccount: Some(ledger::escrow_subaccount(report_id)) },
+        amount: Nat::from(amount),
+        fee: Some(Nat::from(fee)),
+        memo: Some(ledger::report_memo(report_id)),
+        created_at_time: Some(api::time()),
+    };
+    
+    match ledger::icrc2_transfer_from(ledger_canister_id, args).await {
+        Ok(Ok(_)) | Ok(Err(TransferFromError::Duplicate { .. })) => Ok(()),
+        Ok(Err(TransferFromError::BadFee { expected_fee })) => {
+            let expected_fee = ledger::nat_to_u64(&expected_fee);
+            set_ledger_fee(expected_fee);
+            Err(format!("The ledger fee is now {} tokens; approve the stake plus the new fee and resubmit", expected_fee))
+        }
+        Ok(Err(TransferFromError::InsufficientAllowance { allowance })) => Err(format!(
+            "Approve this canister to spend {} tokens (stake plus ledger fee) before submitting; the current allowance is {}",
+            amount.saturating_add(fee),
+            allowance
+        )),
+        Ok(Err(TransferFromError::InsufficientFunds { balance })) => Err(format!(
+            "Insufficient ledger balance for staking ({} tokens available)",
+            balance
+        )),
+        Ok(Err(error)) => Err(format!("The ledger rejected the stake transfer: {:?}", error)),
+        Err(error) => {
+            ic_cdk::println!("Stake for report {} has an unknown outcome: {}", report_id, error);
+            queue_ledger_transfer(report_id, LedgerTransferKind::UnconfirmedStake, reporter, amount);
+            Err("The ledger did not confirm the stake transfer. Anything it took will be refunded; please try again".to_string())
+        }
+    }
+}
+
+// Queue a ledger payout and start sending it. Nothing is queued for a zero amount.
+fn queue_ledger_transfer(report_id: u64, kind: LedgerTransferKind, to: Principal, amount: u64) {
+    if amount == 0 {
+        return;
+    }
+    
+    let transfer = LedgerTransfer {
+        id: 0, // Will be assigned by create_ledger_transfer
+        report_id,
+        kind,
+        to,
+        amount,
+        fee: None,
+        created_at_time: api::time(),
+        attempts: 0,
+        last_attempt_at: None,
+        status: LedgerTransferStatus::Pending,
+    };
+    
+    let transfer_id = store::create_ledger_transfer(&transfer);
+    if let Some(created) = store::get_ledger_transfer(transfer_id) {
+        store::append_audit_entry(
+            api::id(),
+            "queue_ledger_transfer",
+            vec![format!("ledger_transfer:{}", transfer_id), format!("report:{}", report_id)],
+            None,
+            Some(store::digest(&created)),
+        );
+    }
+    
+    schedule_ledger_transfers();
+}
+
+// Retry queued ledger transfers periodically (ledger mode only)
+fn start_ledger_transfer_timer() {
+    if !ledger_mode() {
+        return;
+    }
+    
+    runtime::set_timer_interval(LEDGER_RETRY_INTERVAL, || runtime::spawn(process_ledger_transfers()));
+    schedule_ledger_transfers();
+}
+
+// Send queued ledger transfers as soon as the current call finishes
+fn schedule_ledger_transfers() {
+    runtime::set_timer(Duration::ZERO, || runtime::spawn(process_ledger_transfers()));
+}
+
+async fn process_ledger_transfers() {
+    let ledger_canister_id = match store::get_ledger_config().ledger_canister_id {
+        Some(ledger_canister_id) => ledger_canister_id,
+        None => return,
+    };
+    
+    for transfer_id in store::get_pending_ledger_transfer_ids() {
+        send_ledger_transfer(ledger_canister_id, transfer_id).await;
+    }
+    
+    refresh_treasury_balance(ledger_canister_id).await;
+}
+
+// Record the canister account's ledger balance for the reward funding check
+async fn refresh_treasury_balance(ledger_canister_id: Principal) {
+    let account = Account { owner: api::id(), subaccount: None };
+    match ledger::icrc1_balance_of(ledger_canister_id, account).await {
+        Ok(balance) => {
+            let mut config = store::get_ledger_config();
+            config.treasury_balance = Some(ledger::nat_to_u64(&balance));
+            store::set_ledger_config(config);
+        }
+        Err(error) => ic_cdk::println!("Could not check the treasury balance: {}", error),
+    }
+}
+
+// Send one pending transfer. It is marked in flight before the call so overlapping
+// runs never send it twice; an unanswered call puts it back in the queue with the
+// same created_at_time and fee, so the ledger treats the retry as a duplicate.
+async fn send_ledger_transfer(ledger_canister_id: Principal, transfer_id: u64) {
+    // Burns go to the ledger's minting account, looked up before the transfer is claimed
+    let is_burn = store::get_ledger_transfer(transfer_id)
+        .is_some_and(|transfer| transfer.kind == LedgerTransferKind::SlashBurn);
+    let minting_account = if is_burn {
+        match ledger::icrc1_minting_account(ledger_canister_id).await {
+            Ok(account) => account,
+            Err(error) => {
+                ic_cdk::println!("Ledger transfer {} is waiting for the minting account: {}", transfer_id, error);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    
+    let mut transfer = match store::get_ledger_transfer(transfer_id) {
+        Some(transfer) if transfer.status == LedgerTransferStatus::Pending => transfer,
+        _ => return,
+    };
+    let before_digest = store::digest(&transfer);
+    
+    // Burns carry no fee
+    let fee = if is_burn { 0 } else { transfer.fee.unwrap_or_else(|| store::get_ledger_config().fee) };
+    let escrow = ledger::escrow_subaccount(transfer.report_id);
+    let (from_subaccount, amount) = match transfer.kind {
+        kind if kind.paid_from_escrow() => (Some(escrow.clone()), transfer.amount.saturating_sub(fee)),
+        LedgerTransferKind::ReinstateFromReviewerPool => (Some(ledger::reviewer_pool_subaccount()), transfer.amount),
+        _ => (None, transfer.amount),
+    };
+    let to = match transfer.kind {
+        LedgerTransferKind::SlashToTreasury => Some(Account { owner: api::id(), subaccount: None }),
+        LedgerTransferKind::SlashToReviewerPool => {
+            Some(Account { owner: api::id(), subaccount: Some(ledger::reviewer_pool_subaccount()) })
+        }
+        LedgerTransferKind::SlashBurn => minting_account,
+        LedgerTransferKind::ReinstateFromTreasury | LedgerTransferKind::ReinstateFromReviewerPool => {
+            Some(Account { owner: api::id(), subaccount: Some(escrow) })
+        }
+        _ => Some(Account { owner: transfer.to, subaccount: None }),
+    };
+    
+    transfer.fee = Some(fee);
+    transfer.attempts += 1;
+    transfer.last_attempt_at = Some(api::time());
+    
+    let status = if amount == 0 {
+        LedgerTransferStatus::Failed { error: "Amount does not cover the ledger fee".to_string() }
+    } else if let Some(to) = to {
+        transfer.status = LedgerTransferStatus::InFlight;
+        store::update_ledger_transfer(transfer.clone());
+        
+        let arg = TransferArg {
+            from_subaccount,
+            to,
+            amount: Nat::from(amount),
+            fee: Some(Nat::from(fee)),
+            memo: Some(ledger::report_memo(transfer.report_id)),
+            created_at_time: Some(transfer.created_at_time),
+        };
+        
+        match ledger::icrc1_transfer(ledger_canister_id, arg).await {
+            Ok(Ok(block_index)) => LedgerTransferStatus::Completed { block_index: ledger::nat_to_u64(&block_index) },
+            Ok(Err(TransferError::Duplicate { duplicate_of })) => {
+                LedgerTransferStatus::Completed { block_index: ledger::nat_to_u64(&duplicate_of) }
+            }
+            Ok(Err(TransferError::BadFee { expected_fee })) => {
+                // Definitely not executed, so the next attempt may use the new fee
+                set_ledger_fee(ledger::nat_to_u64(&expected_fee));
+                transfer.fee = None;
+                LedgerTransferStatus::Pending
+            }
+            Ok(Err(TransferError::TemporarilyUnavailable)) | Ok(Err(TransferError::CreatedInFuture { .. })) => {
+                LedgerTransferStatus::Pending
+            }
+            Ok(Err(error)) => LedgerTransferStatus::Failed { error: format!("{:?}", error) },
+            Err(error) => {
+                ic_cdk::println!("Ledger transfer {} has an unknown outcome, retrying: {}", transfer_id, error);
+                LedgerTransferStatus::Pending
+            }
+        }
+    } else {
+        LedgerTransferStatus::Failed { error: "The ledger has no minting account to burn to".to_string() }
+    };
+    transfer.status = status;
+    
+    store::append_audit_entry(
+        api::id(),
+        "send_ledger_transfer",
+        vec![format!("ledger_transfer:{}", transfer_id), format!("report:{}", transfer.report_id)],
+        Some(before_digest),
+        Some(store::digest(&transfer)),
+    );
+    store::update_ledger_transfer(transfer);
+}
+
+// Get the token mode: the ledger canister (if any) and its last known fee
+#[ic_cdk::query]
+fn get_ledger_config() -> LedgerConfig {
+    store::get_ledger_config()
+}
+
+// Get every ledger transfer (for authority). Reporters are shown by pseudonym.
+#[ic_cdk::query]
+fn get_ledger_transfers() -> Result<Vec<LedgerTransferView>, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    Ok(store::get_ledger_transfers()
+        .into_iter()
+        .map(|transfer| {
+            let reporter = store::reporter_pseudonym(transfer.report_id);
+            LedgerTransferView::new(transfer, api::id(), reporter)
+        })
+        .collect())
+}
+
+// Get the ledger payouts made to the caller
+#[ic_cdk::query]
+fn get_my_ledger_transfers() -> Vec<LedgerTransfer> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return vec![];
+    }
+    
+    store::get_ledger_transfers()
+        .into_iter()
+        .filter(|transfer| transfer.to == caller)
+        .collect()
+}
+
+// Queue a failed ledger transfer again (for authority). The retry is a new ledger
+// transaction, so only use this once the original is known not to have gone through
+// (a TooOld failure in particular needs checking against the ledger first).
+#[ic_cdk::update]
+fn retry_ledger_transfer(transfer_id: u64) -> Result<(), String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    let mut transfer = match store::get_ledger_transfer(transfer_id) {
+        Some(transfer) => transfer,
+        None => return Err("Ledger transfer not found".to_string()),
+    };
+    
+    if !matches!(transfer.status, LedgerTransferStatus::Failed { .. }) {
+        return Err("Only failed ledger transfers can be retried".to_string());
+    }
+    
+    let before_digest = store::digest(&transfer);
+    transfer.status = LedgerTransferStatus::Pending;
+    transfer.fee = None;
+    transfer.created_at_time = api::time();
+    
+    audit(
+        "retry_ledger_transfer",
+        vec![format!("ledger_transfer:{}", transfer_id), format!("report:{}", transfer.report_id)],
+        Some(before_digest),
+        Some(store::digest(&transfer)),
+    );
+    store::update_ledger_transfer(transfer);
+    schedule_ledger_transfers();
+    
+    Ok(())
+}
+
+// Get authority stats
+#[ic_cdk::query]
+fn get_authority_statistics() -> Result<AuthorityStats, String> {
+    require_permission(Permission::ViewStatistics)?;
+    Ok(store::get_authority_stats())
+}
+
+// Add a new authority (only for existing authorities). Membership changes
+// always go through multi-signature approval.
+#[ic_cdk::update]
+fn add_new_authority(id: Principal) -> Result<ActionOutcome, String> {
+    let caller = require_permission(Permission::ManageAuthorities)?;
+    submit_for_approval(ProposalAction::AddAuthority { id }, caller)
+}
+
+// List all authorities and their roles (for authority management)
+#[ic_cdk::query]
+fn get_authorities() -> Result<Vec<Authority>, String> {
+    require_permission(Permission::ManageAuthorities)?;
+    Ok(store::get_all_authorities())
+}
+
+// Get the caller's own authority record, if any
+#[ic_cdk::query]
+fn get_my_authority() -> Option<Authority> {
+    store::get_authority(caller())
+}
+
+// Grant a role to an authority (for authority management)
+#[ic_cdk::update]
+fn grant_role(id: Principal, role: AuthorityRole) -> Result<ActionOutcome, String> {
+    let caller = require_permission(Permission::ManageAuthorities)?;
+    submit_for_approval(ProposalAction::GrantRole { id, role }, caller)
+}
+
+// Revoke a role from an authority (for authority management)
+#[ic_cdk::update]
+fn revoke_role(id: Principal, role: AuthorityRole) -> Result<ActionOutcome, String> {
+    let caller = require_permission(Permission::ManageAuthorities)?;
+    submit_for_approval(ProposalAction::RevokeRole { id, role }, caller)
+}
+
+// Remove an authority entirely (for authority management)
+#[ic_cdk::update]
+fn remove_authority(id: Principal) -> Result<ActionOutcome, String> {
+    let caller = require_permission(Permission::ManageAuthorities)?;
+    submit_for_approval(ProposalAction::RemoveAuthority { id }, caller)
+}
+
+// Permission needed to propose, approve or reject an action
+fn proposal_permission(action: &ProposalAction) -> Permission {
+    match action {
+        ProposalAction::VerifyReport { .. } => Permission::VerifyReports,
+        ProposalAction::AddAuthority { .. }
+        | ProposalAction::RemoveAuthority { .. }
+        | ProposalAction::GrantRole { .. }
+        | ProposalAction::RevokeRole { .. }
+        | ProposalAction::UpdateGovernanceConfig { .. } => Permission::ManageAuthorities,
+    }
+}
+
+// Authority changes need the governance config's number of admins to approve
+// them, so an admin can only be dropped if that many would be left
+fn check_admins_remain(change: &str) -> Result<(), String> {
+    let required = store::get_governance_config().required_approvals.max(1) as usize;
+    let admins = store::count_authorities_with_role(AuthorityRole::Admin);
+    
+    if admins <= required {
+        return Err(format!(
+            "Cannot {}: at least {} admins must remain to approve authority changes",
+            change, required
+        ));
+    }
+    
+    Ok(())
+}
+
+// Check that an action could be executed right now
+fn validate_proposal_action(action: &ProposalAction, proposer: Principal) -> Result<(), String> {
+    match action {
+        ProposalAction::VerifyReport { report_id, .. } => {
+            let report = match store::get_report(*report_id) {
+                Some(report) => report,
+                None => return Err("Report not found".to_string()),
+            };
+            check_transition(&report, ReportAction::Verify, proposer)?;
+        }
+        ProposalAction::AddAuthority { id } => {
+            if *id == Principal::anonymous() {
+                return Err("The anonymous principal cannot be an authority".to_string());
+            }
+            
+            if store::is_authority(*id) {
+                return Err("Principal is already an authority".to_string());
+            }
+        }
+        ProposalAction::RemoveAuthority { id } => {
+            let authority = match store::get_authority(*id) {
+                Some(authority) => authority,
+                None => return Err("Principal is not an authority".to_string()),
+            };
+            
+            if authority.has_role(AuthorityRole::Admin) {
+                check_admins_remain("remove this admin")?;
+            }
+        }
+        ProposalAction::GrantRole { id, role } => {
+            let authority = match store::get_authority(*id) {
+                Some(authority) => authority,
+                None => return Err("Principal is not an authority".to_string()),
+            };
+            
+            if authority.has_role(*role) {
+                return Err(format!("Authority already has the {:?} role", role));
+            }
+        }
+        ProposalAction::RevokeRole { id, role } => {
+            let authority = match store::get_authority(*id) {
+                Some(authority) => authority,
+                None => return Err("Principal is not an authority".to_string()),
+            };
+            
+            if !authority.has_role(*role) {
+                return Err(format!("Authority does not have the {:?} role", role));
+            }
+            
+            if *role == AuthorityRole::Admin {
+                check_admins_remain("revoke the Admin role")?;
+            }
+        }
+        ProposalAction::UpdateGovernanceConfig { config } => {
+            if config.required_approvals == 0 {
+                return Err("At least one approval must be required".to_string());
+            }
+            
+            let admins = store::count_authorities_with_role(AuthorityRole::Admin) as u32;
+            if config.required_approvals > admins {
+                return Err(format!(
+                    "Cannot require {} approvals with only {} admins to give them",
+                    config.required_approvals, admins
+                ));
+            }
+            
+            if config.proposal_ttl_ns < 3_600_000_000_000 {
+                return Err("Proposals must stay open for at least an hour".to_string());
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+// Carry out an approved action
+fn execute_proposal_action(action: &ProposalAction, proposer: Principal) -> Result<(), String> {
+    validate_proposal_action(action, proposer)?;
+    
+    match action {
+        ProposalAction::VerifyReport { report_id, notes } => {
+            apply_verification(*report_id, proposer, notes.clone())?;
+        }
+        ProposalAction::AddAuthority { id } => {
+            // New authorities start as reviewers; admins can grant further roles
+            let authority = Authority {
+                id: *id,
+                reports_reviewed: Vec::new(),
+                approval_rate: 0.0,
+                roles: vec![AuthorityRole::Reviewer],
+            };
+            
+            audit("add_new_authority", vec![format!("authority:{}", id)], None, Some(store::digest(&authority)));
+            store::add_authority(authority);
+            sync_evidence_access("add_new_authority", *id);
+        }
+        ProposalAction::RemoveAuthority { id } => {
+            if let Some(authority) = store::remove_authority(*id) {
+                audit("remove_authority", vec![format!("authority:{}", id)], Some(store::digest(&authority)), None);
+            }
+            sync_evidence_access("remove_authority", *id);
+        }
+        ProposalAction::GrantRole { id, role } => {
+            if let Some(mut authority) = store::get_authority(*id) {
+                let before_digest = store::digest(&authority);
+                authority.roles.push(*role);
+                audit(
+                    "grant_role",
+                    vec![format!("authority:{}", id)],
+                    Some(before_digest),
+                    Some(store::digest(&authority)),
+                );
+                store::add_authority(authority);
+                sync_evidence_access("grant_role", *id);
+            }
+        }
+        ProposalAction::RevokeRole { id, role } => {
+            if let Some(mut authority) = store::get_authority(*id) {
+                let before_digest = store::digest(&authority);
+                authority.roles.retain(|held| held != role);
+                audit(
+                    "revoke_role",
+                    vec![format!("authority:{}", id)],
+                    Some(before_digest),
+                    Some(store::digest(&authority)),
+                );
+                store::add_authority(authority);
+                sync_evidence_access("revoke_role", *id);
+            }
+        }
+        ProposalAction::UpdateGovernanceConfig { config } => {
+            audit(
+                "update_governance_config",
+                vec!["governance_config".to_string()],
+                Some(store::digest(&store::get_governance_config())),
+                Some(store::digest(config)),
+            );
+            store::set_governance_config(config.clone());
+        }
+    }
+    
+    Ok(())
+}
+
+// Open a proposal for an action, with the proposer's approval already counted.
+// If that is enough approvals (the governance config requires just one), the
+// action is executed straight away. The threshold is never lowered to fit the
+// authorities there are; a proposal that could not reach it is refused.
+fn submit_for_approval(action: ProposalAction, proposer: Principal) -> Result<ActionOutcome, String> {
+    validate_proposal_action(&action, proposer)?;
+    
+    // Only one open proposal per report
+    if let ProposalAction::VerifyReport { report_id, .. } = &action {
+        let already_proposed = store::get_all_proposals().iter().any(|proposal| {
+            proposal.status == ProposalStatus::Open
+                && matches!(&proposal.action, ProposalAction::VerifyReport { report_id: id, .. } if id == report_id)
+        });
+        
+        if already_proposed {
+            return Err("A verification proposal is already open for this report".to_string());
+        }
+    }
+    
+    let config = store::get_governance_config();
+    let eligible = store::count_authorities_with_permission(proposal_permission(&action)) as u32;
+    if eligible < config.required_approvals {
+        return Err(format!(
+            "This needs {} approvals but only {} authorities can approve it",
+            config.required_approvals, eligible
+        ));
+    }
+    
+    let now = api::time();
+    
+    let proposal = Proposal {
+        id: 0, // Will be assigned by create_proposal
+        action,
+        proposer,
+        created_at: now,
+        expires_at: now + config.proposal_ttl_ns,
+        required_approvals: config.required_approvals,
+        approvals: vec![proposer],
+        rejections: Vec::new(),
+        status: ProposalStatus::Open,
+        resolved_at: None,
+        execution_result: None,
+    };
+    
+    let proposal_id = store::create_proposal(&proposal);
+    let proposal = Proposal { id: proposal_id, ..proposal };
+    audit("create_proposal", vec![format!("proposal:{}", proposal_id)], None, Some(store::digest(&proposal)));
+    
+    if proposal.approvals.len() as u32 >= proposal.required_approvals {
+        let proposal = execute_proposal(proposal);
+        return match proposal.status {
+            ProposalStatus::Executed => Ok(ActionOutcome::Executed),
+            _ => Err(proposal.execution_result.unwrap_or_default()),
+        };
+    }
+    
+    Ok(ActionOutcome::PendingApproval { proposal_id })
+}
+
+// Execute a proposal that has enough approvals and record the outcome
+fn execute_proposal(proposal: Proposal) -> Proposal {
+    let before_digest = store::digest(&proposal);
+    let mut proposal = proposal;
+    
+    match execute_proposal_action(&proposal.action, proposal.proposer) {
+        Ok(()) => {
+            proposal.status = ProposalStatus::Executed;
+            proposal.execution_result = None;
+        }
+        Err(error) => {
+            proposal.status = ProposalStatus::Failed;
+            proposal.execution_result = Some(error);
+        }
+    }
+    proposal.resolved_at = Some(api::time());
+    
+    audit(
+        "execute_proposal",
+        vec![format!("proposal:{}", proposal.id)],
+        Some(before_digest),
+        Some(store::digest(&proposal)),
+    );
+    store::update_proposal(proposal.clone());
+    
+    proposal
+}
+
+// Load an open proposal the caller may vote on, expiring it if it is past due
+fn open_proposal_for_vote(proposal_id: u64) -> Result<(Principal, Proposal), String> {
+    let mut proposal = match store::get_proposal(proposal_id) {
+        Some(proposal) => proposal,
+        None => return Err("Proposal not found".to_string()),
+    };
+    
+    let caller = require_permission(proposal_permission(&proposal.action))?;
+    
+    if proposal.status != ProposalStatus::Open {
+        return Err(format!("Proposal is already {:?}", proposal.status));
+    }
+    
+    let now = api::time();
+    if now > proposal.expires_at {
+        proposal.status = ProposalStatus::Expired;
+        proposal.resolved_at = Some(now);
+        store::update_proposal(proposal);
+        return Err("Proposal has expired".to_string());
+    }
+    
+    if proposal.approvals.contains(&caller) || proposal.rejections.contains(&caller) {
+        return Err("You have already voted on this proposal".to_string());
+    }
+    
+    Ok((caller, proposal))
+}
+
+// Approve a proposal; it executes once it has enough distinct approvals
+#[ic_cdk::update]
+fn approve_proposal(proposal_id: u64) -> Result<Proposal, String> {
+    let (caller, mut proposal) = open_proposal_for_vote(proposal_id)?;
+    
+    let before_digest = store::digest(&proposal);
+    proposal.approvals.push(caller);
+    audit(
+        "approve_proposal",
+        vec![format!("proposal:{}", proposal_id)],
+        Some(before_digest),
+        Some(store::digest(&proposal)),
+    );
+    
+    if proposal.approvals.len() as u32 >= proposal.required_approvals {
+        return Ok(execute_proposal(proposal));
+    }
+    
+    store::update_proposal(proposal.clone());
+    
+    Ok(proposal)
+}
+
+// Reject a proposal; it is closed once it can no longer reach its approvals
+#[ic_cdk::update]
+fn reject_proposal(proposal_id: u64) -> Result<Proposal, String> {
+    let (caller, mut proposal) = open_proposal_for_vote(proposal_id)?;
+    
+    let before_digest = store::digest(&proposal);
+    proposal.rejections.push(caller);
+    
+    let eligible = store::count_authorities_with_permission(proposal_permission(&proposal.action));
+    let remaining_voters = eligible.saturating_sub(proposal.approvals.len() + proposal.rejections.len());
+    if proposal.approvals.len() + remaining_voters < proposal.required_approvals as usize {
+        proposal.status = ProposalStatus::Rejected;
+        proposal.resolved_at = Some(api::time());
+    }
+    
+    audit(
+        "reject_proposal",
+        vec![format!("proposal:{}", proposal_id)],
+        Some(before_digest),
+        Some(store::digest(&proposal)),
+    );
+    store::update_proposal(proposal.clone());
+    
+    Ok(proposal)
+}
+
+// Show open proposals that have run out of time as expired
+fn with_effective_status(mut proposal: Proposal, now: u64) -> Proposal {
+    if proposal.status == ProposalStatus::Open && now > proposal.expires_at {
+        proposal.status = ProposalStatus::Expired;
+    }
+    proposal
+}
+
+// Get a single proposal (for authority)
+#[ic_cdk::query]
+fn get_proposal(proposal_id: u64) -> Result<Proposal, String> {
+    require_permission(Permission::ViewReports)?;
+    
+    match store::get_proposal(proposal_id) {
+        Some(proposal) => Ok(with_effective_status(proposal, api::time())),
+        None => Err("Proposal not found".to_string()),
+    }
+}
+
+// List proposals, optionally only those in one status (for authority)
+#[ic_cdk::query]
+fn get_proposals(status: Option<ProposalStatus>) -> Result<Vec<Proposal>, String> {
+    require_permission(Permission::ViewReports)?;
+    
+    let now = api::time();
+    Ok(store::get_all_proposals()
+        .into_iter()
+        .map(|proposal| with_effective_status(proposal, now))
+        .filter(|proposal| status.as_ref().is_none_or(|status| proposal.status == *status))
+        .collect())
+}
+
+// Get the multi-signature rules (for authority)
+#[ic_cdk::query]
+fn get_governance_config() -> Result<GovernanceConfig, String> {
+    require_permission(Permission::ViewReports)?;
+    Ok(store::get_governance_config())
+}
+
+// Propose new multi-signature rules (for authority management)
+#[ic_cdk::update]
+fn update_governance_config(config: GovernanceConfig) -> Result<ActionOutcome, String> {
+    let caller = require_permission(Permission::ManageAuthorities)?;
+    submit_for_approval(ProposalAction::UpdateGovernanceConfig { config }, caller)
+}
+
+// Get the current reward config (for authority)
+#[ic_cdk::query]
+fn get_reward_config() -> Result<RewardConfig, String> {
+    require_permission(Permission::ViewStatistics)?;
+    Ok(store::get_reward_config())
+}
+
+// Change the reward config (for authority). `config.version` must match the
+// current version so concurrent edits do not overwrite each other.
+#[ic_cdk::update]
+fn update_reward_config(config: RewardConfig) -> Result<RewardConfig, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    let current = store::get_reward_config();
+    
+    if config.version != current.version {
+        return Err(format!(
+            "Reward config has changed (current version is {})",
+            current.version
+        ));
+    }
+    
+    if config.reward_multiplier == 0 || config.reward_multiplier > 100 {
+        return Err("Reward multiplier must be between 1 and 100".to_string());
+    }
+    
+    if config.min_stake_amount == 0 {
+        return Err("Minimum stake amount must be at least 1 token".to_string());
+    }
+    
+    if config.min_stake_amount > config.max_stake_amount {
+        return Err("Minimum stake amount cannot exceed the maximum".to_string());
+    }
+    
+    if config.max_stake_amount.checked_mul(config.reward_multiplier).is_none() {
+        return Err("Maximum stake amount is too large for the reward multiplier".to_string());
+    }
+    
+    let updated = RewardConfig {
+        version: current.version + 1,
+        ..config
+    };
+    
+    audit(
+        "update_reward_config",
+        vec![format!("reward_config:{}", updated.version)],
+        Some(store::digest(&current)),
+        Some(store::digest(&updated)),
+    );
+    store::set_reward_config(updated.clone());
+    
+    Ok(updated)
+}
+
+// Get the slashing policy (for authority)
+#[ic_cdk::query]
+fn get_slashing_policy() -> Result<SlashingPolicy, String> {
+    require_permission(Permission::ViewStatistics)?;
+    Ok(store::get_slashing_policy())
+}
+
+// Change where slashed stakes go (for authority)
+#[ic_cdk::update]
+fn update_slashing_policy(policy: SlashingPolicy) -> Result<SlashingPolicy, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    let split = policy.burn_percent
+        .checked_add(policy.treasury_percent)
+        .and_then(|sum| sum.checked_add(policy.reviewer_pool_percent));
+    if split != Some(100) {
+        return Err("Burn, treasury and reviewer pool percentages must add up to 100".to_string());
+    }
+    
+    if policy.soft_reject_refund_percent > 100 {
+        return Err("Soft reject refund cannot exceed 100 percent".to_string());
+    }
+    
+    if policy.withdrawal_refund_percent > 100 {
+        return Err("Withdrawal refund cannot exceed 100 percent".to_string());
+    }
+    
+    let current = store::get_slashing_policy();
+    audit(
+        "update_slashing_policy",
+        vec!["slashing_policy".to_string()],
+        Some(store::digest(&current)),
+        Some(store::digest(&policy)),
+    );
+    store::set_slashing_policy(policy.clone());
+    
+    Ok(policy)
+}
+
+// Move tokens from the caller's balance into the reward treasury (for agencies and donors)
+#[ic_cdk::update]
+fn fund_treasury(amount: u64) -> Result<u64, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot fund the treasury".to_string());
+    }
+    
+    if ledger_mode() {
+        return Err("With ledger-backed staking, rewards are paid from the canister's ledger account".to_string());
+    }
+    
+    if amount == 0 {
+        return Err("Amount must be at least 1 token".to_string());
+    }
+    
+    let entry = store::post_entry_now(
+        EntryKind::TreasuryFunding,
+        BookAccount::user(caller),
+        BookAccount::Treasury,
+        amount,
+        None,
+    )?;
+    
+    audit(
+        "fund_treasury",
+        vec![format!("user:{}", caller), format!("journal_entry:{}", entry.id)],
+        None,
+        Some(store::digest(&entry)),
+    );
+    
+    Ok(store::get_system_balance(&BookAccount::Treasury))
+}
+
+// Get the reward payout limits (for authority)
+#[ic_cdk::query]
+fn get_treasury_config() -> Result<TreasuryConfig, String> {
+    require_permission(Permission::ViewFinances)?;
+    Ok(store::get_treasury_config())
+}
+
+// Change the reward payout limits (for authority)
+#[ic_cdk::update]
+fn update_treasury_config(config: TreasuryConfig) -> Result<TreasuryConfig, String> {
+    require_permission(Permission::ManageRewardConfig)?;
+    
+    if config.payout_cap == Some(0) {
+        return Err("Payout cap must be at least 1 token; leave it empty for no cap".to_string());
+    }
+    
+    let current = store::get_treasury_config();
+    audit(
+        "update_treasury_config",
+        vec!["treasury_config".to_string()],
+        Some(store::digest(&current)),
+        Some(store::digest(&config)),
+    );
+    store::set_treasury_config(config.clone());
+    
+    Ok(config)
+}
+
+// Treasury balance, inflows and outflows, with daily totals for the days between
+// two timestamps (for finance authorities)
+#[ic_cdk::query]
+fn get_treasury_report(from: u64, to: u64) -> Result<TreasuryReport, String> {
+    require_permission(Permission::ViewFinances)?;
+    
+    let first_day = from / store::DAY_NS;
+    let last_day = to / store::DAY_NS;
+    
+    if first_day > last_day {
+        return Err("The report must start before it ends".to_string());
+    }
+    
+    if last_day - first_day >= MAX_TREASURY_REPORT_DAYS {
+        return Err(format!("Reports cover at most {} days", MAX_TREASURY_REPORT_DAYS));
+    }
+    
+    let config = store::get_treasury_config();
+    let (period_rewards_paid, period_remaining) = payout_allowance(&config);
+    
+    let all_days = store::get_treasury_days(0, u64::MAX);
+    
+    // On an external ledger the treasury is the canister's account, as last checked
+    let balance = if ledger_mode() {
+        store::get_ledger_config().treasury_balance.unwrap_or(0)
+    } else {
+        store::get_system_balance(&BookAccount::Treasury)
+    };
+    
+    Ok(TreasuryReport {
+        balance,
+        total_inflow: all_days.iter().map(|(_, day)| day.inflow).sum(),
+        total_outflow: all_days.iter().map(|(_, day)| day.outflow).sum(),
+        total_rewards_paid: all_days.iter().map(|(_, day)| day.rewards_paid).sum(),
+        config,
+        period_rewards_paid,
+        period_remaining,
+        days: store::get_treasury_days(first_day, last_day)
+            .into_iter()
+            .map(|(day, totals)| (day * store::DAY_NS, totals))
+            .collect(),
+    })
+}
+
+// Category validation shared by create_category and update_category
+fn validate_category(category: &Category) -> Result<(), String> {
+    let valid_id = !category.id.is_empty()
+        && category.id.len() <= 64
+        && category.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    
+    if !valid_id {
+        return Err("Category id must be 1-64 characters of a-z, 0-9, '-' or '_'".to_string());
+    }
+    
+    if category.name.trim().is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
+    
+    if let (Some(min), Some(max)) = (category.min_stake_amount, category.max_stake_amount) {
+        if min > max {
+            return Err("Minimum stake amount cannot exceed the maximum".to_string());
+        }
+    }
+    
+    // Walk up the parent chain to make sure it exists and does not loop back
+    let mut parent_id = category.parent_id.clone();
+    while let Some(id) = parent_id {
+        if id == category.id {
+            return Err("A category cannot be its own ancestor".to_string());
+        }
+        
+        parent_id = match store::get_category(&id) {
+            Some(parent) => parent.parent_id,
+            None => return Err(format!("Parent category '{}' not found", id)),
+        };
+    }
+    
+    Ok(())
+}
+
+// List all categories, including retired ones
+#[ic_cdk::query]
+fn get_categories() -> Vec<Category> {
+    store::get_all_categories()
+}
+
+// Register a new category (for authority)
+#[ic_cdk::update]
+fn create_category(category: Category) -> Result<(), String> {
+    require_permission(Permission::ManageCategories)?;
+    
+    if store::get_category(&category.id).is_some() {
+        return Err(format!("Category '{}' already exists", category.id));
+    }
+    
+    validate_category(&category)?;
+    audit("create_category", vec![format!("category:{}", category.id)], None, Some(store::digest(&category)));
+    store::save_category(category);
+    
+    Ok(())
+}
+
+// Change an existing category's details, limits or active flag (for authority).
+// The id itself is immutable because reports refer to it.
+#[ic_cdk::update]
+fn update_category(category: Category) -> Result<(), String> {
+    require_permission(Permission::ManageCategories)?;
+    
+    let existing = match store::get_category(&category.id) {
+        Some(existing) => existing,
+        None => return Err(format!("Category '{}' not found", category.id)),
+    };
+    
+    validate_category(&category)?;
+    audit(
+        "update_category",
+        vec![format!("category:{}", category.id)],
+        Some(store::digest(&existing)),
+        Some(store::digest(&category)),
+    );
+    store::save_category(category);
+    
+    Ok(())
+}
+
+// Stop accepting new reports in a category (for authority)
+#[ic_cdk::update]
+fn retire_category(category_id: String) -> Result<(), String> {
+    require_permission(Permission::ManageCategories)?;
+    
+    let mut category = match store::get_category(&category_id) {
+        Some(category) => category,
+        None => return Err(format!("Category '{}' not found", category_id)),
+    };
+    
+    let before_digest = store::digest(&category);
+    category.active = false;
+    audit(
+        "retire_category",
+        vec![format!("category:{}", category.id)],
+        Some(before_digest),
+        Some(store::digest(&category)),
+    );
+    store::save_category(category);
+    
+    Ok(())
+}
+
+// For development: Reset to initial state with mock data
+#[ic_cdk::update]
+fn reset_to_mock_data() -> Result<(), String> {
+    require_permission(Permission::ResetData)?;
+    
+    if ledger_mode() {
+        return Err("Mock data is only available with internal balances".to_string());
+    }
+    
+    // This would be implemented to clear existing data and reinitialize mock data
+    // For brevity, we'll just call initialize again
+    audit("reset_to_mock_data", Vec::new(), None, None);
+    store::initialize_mock_data();
+    
+    Ok(())
+}
+
+// Evidence can be added until a report is decided or withdrawn
+fn check_accepts_evidence(report: &Report) -> Result<(), String> {
+    if matches!(report.status, ReportStatus::Approved | ReportStatus::Rejected | ReportStatus::Withdrawn) {
+        return Err(format!("Cannot add evidence to a report in {:?} state", report.status));
+    }
+    
+    Ok(())
+}
+
+// Each principal can only have a few uploads open at a time
+fn check_open_upload_limit(caller: Principal) -> Result<(), String> {
+    if store::count_open_uploads(caller) >= MAX_OPEN_UPLOADS_PER_CALLER {
+        return Err(format!(
+            "At most {} uploads can be open at a time; finish or wait out an earlier one",
+            MAX_OPEN_UPLOADS_PER_CALLER
+        ));
+    }
+    
+    Ok(())
+}
+
+// Discard uploads that were never finalized, together with their chunks
+fn start_upload_expiry_timer() {
+    runtime::set_timer_interval(UPLOAD_SWEEP_INTERVAL, expire_stale_uploads);
+}
+
+fn expire_stale_uploads() {
+    let cutoff = api::time().saturating_sub(UPLOAD_EXPIRY_NS);
+    for upload_id in store::get_stale_upload_ids(cutoff) {
+        store::remove_evidence_upload(upload_id);
+    }
+}
+
+// Start a chunked evidence upload for one of the caller's reports
+#[ic_cdk::update]
+fn begin_evidence_upload(
+    report_id: u64,
+    name: String,
+    file_type: String,
+    total_size: u64,
+) -> Result<u64, String> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot upload evidence".to_string());
+    }
+    
+    let report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
     };
     
-    store::create_message(&message);
+    if report.submitter_id != caller {
+        return Err("You can only upload evidence for your own reports".to_string());
+    }
     
-    Ok(())
+    check_accepts_evidence(&report)?;
+    
+    if total_size == 0 {
+        return Err("Evidence file cannot be empty".to_string());
+    }
+    
+    if total_size > MAX_EVIDENCE_FILE_SIZE {
+        return Err(format!("Evidence files are limited to {} bytes", MAX_EVIDENCE_FILE_SIZE));
+    }
+    
+    check_open_upload_limit(caller)?;
+    
+    let upload = EvidenceUpload {
+        id: 0, // Will be assigned by create_evidence_upload
+        report_id,
+        uploader: caller,
+        name,
+        file_type,
+        total_size,
+        received_size: 0,
+        started_at: api::time(),
+        purpose: Some(UploadPurpose::Evidence),
+    };
+    
+    Ok(store::create_evidence_upload(&upload))
 }
 
-// Get messages for a report
-#[ic_cdk::query]
-fn get_messages(report_id: u64) -> Vec<Message> {
+// Start a chunked upload of a message attachment (for the report submitter and authorities).
+// Chunks are sent with upload_evidence_chunk.
+#[ic_cdk::update]
+fn begin_attachment_upload(
+    report_id: u64,
+    name: String,
+    file_type: String,
+    total_size: u64,
+) -> Result<u64, String> {
     let caller = caller();
     
-    // Check if report exists
+    if caller == Principal::anonymous() {
+        return Err("Anonymous callers cannot upload attachments".to_string());
+    }
+    
     let report = match store::get_report(report_id) {
         Some(report) => report,
-        None => return vec![],
+        None => return Err("Report not found".to_string()),
     };
     
-    // Check if caller is authorized to see messages
-    if report.submitter_id != caller && !store::is_authority(caller) {
-        return vec![];
+    if report.submitter_id != caller && !has_permission(caller, Permission::MessageReporters) {
+        return Err("You can only attach files to conversations you take part in".to_string());
     }
     
-    store::get_report_messages(report_id)
+    if !ALLOWED_ATTACHMENT_TYPES.contains(&file_type.as_str()) {
+        return Err(format!(
+            "Attachments must be one of: {}",
+            ALLOWED_ATTACHMENT_TYPES.join(", ")
+        ));
+    }
+    
+    if total_size == 0 {
+        return Err("Attachment cannot be empty".to_string());
+    }
+    
+    if total_size > MAX_ATTACHMENT_SIZE {
+        return Err(format!("Attachments are limited to {} bytes", MAX_ATTACHMENT_SIZE));
+    }
+    
+    check_open_upload_limit(caller)?;
+    
+    let upload = EvidenceUpload {
+        id: 0, // Will be assigned by create_evidence_upload
+        report_id,
+        uploader: caller,
+        name,
+        file_type,
+        total_size,
+        received_size: 0,
+        started_at: api::time(),
+        purpose: Some(UploadPurpose::Attachment),
+    };
+    
+    Ok(store::create_evidence_upload(&upload))
 }
 
-// Get user token balance
-#[ic_cdk::query]
-fn get_user_balance() -> u64 {
+// Upload one chunk of an evidence file or attachment (chunks may be sent in any order or retried)
+#[ic_cdk::update]
+fn upload_evidence_chunk(upload_id: u64, index: u32, bytes: Vec<u8>) -> Result<(), String> {
     let caller = caller();
     
-    if caller == Principal::anonymous() {
-        return 0;
+    let mut upload = match store::get_evidence_upload(upload_id) {
+        Some(upload) => upload,
+        None => return Err("Upload not found".to_string()),
+    };
+    
+    if upload.uploader != caller {
+        return Err("You can only upload chunks to your own uploads".to_string());
     }
     
-    match store::get_user(caller) {
-        Some(user) => user.token_balance,
-        None => 0
+    if api::time() >= upload.started_at.saturating_add(UPLOAD_EXPIRY_NS) {
+        return Err("Upload has expired; start it again".to_string());
+    }
+    
+    // The report may have been decided or withdrawn since the upload started
+    if upload.purpose.unwrap_or(UploadPurpose::Evidence) == UploadPurpose::Evidence {
+        match store::get_report(upload.report_id) {
+            Some(report) => check_accepts_evidence(&report)?,
+            None => return Err("Report not found".to_string()),
+        }
+    }
+    
+    if bytes.is_empty() {
+        return Err("Chunk cannot be empty".to_string());
+    }
+    
+    if bytes.len() > MAX_EVIDENCE_CHUNK_SIZE {
+        return Err(format!("Chunks are limited to {} bytes", MAX_EVIDENCE_CHUNK_SIZE));
+    }
+    
+    let replaced_size = store::get_upload_chunk(upload_id, index)
+        .map(|chunk| chunk.len() as u64)
+        .unwrap_or(0);
+    let received_size = upload.received_size - replaced_size + bytes.len() as u64;
+    
+    if received_size > upload.total_size {
+        return Err("Chunk exceeds the declared file size".to_string());
+    }
+    
+    store::put_upload_chunk(upload_id, index, bytes);
+    
+    upload.received_size = received_size;
+    store::update_evidence_upload(upload);
+    
+    Ok(())
+}
+
+// Resolve the key IDs of an encrypted upload to the reporter's key and authority principals
+fn resolve_evidence_encryption(report_id: u64, input: EvidenceEncryptionInput) -> Result<EvidenceEncryption, String> {
+    if input.algorithm.trim().is_empty() || input.algorithm.len() > MAX_KEY_ALGORITHM_LEN {
+        return Err(format!("Encryption algorithm must be 1 to {} characters", MAX_KEY_ALGORITHM_LEN));
+    }
+    
+    if input.nonce.len() > MAX_MESSAGE_NONCE_SIZE {
+        return Err(format!("Nonce must be at most {} bytes", MAX_MESSAGE_NONCE_SIZE));
+    }
+    
+    if input.wrapped_keys.is_empty() || input.wrapped_keys.len() > MAX_WRAPPED_KEYS {
+        return Err(format!("The content key must be wrapped for 1 to {} keys", MAX_WRAPPED_KEYS));
+    }
+    
+    let recipients = message_recipient_keys(report_id);
+    let mut seen = BTreeSet::new();
+    let mut encryption = EvidenceEncryption {
+        algorithm: input.algorithm,
+        nonce: input.nonce,
+        reporter_key: None,
+        wrapped_keys: Vec::new(),
+    };
+    
+    for wrapped in input.wrapped_keys {
+        if !seen.insert(wrapped.key_id) {
+            return Err(format!("Key {} is wrapped more than once", wrapped.key_id));
+        }
+        
+        if wrapped.wrapped_key.is_empty() || wrapped.wrapped_key.len() > MAX_PUBLIC_KEY_SIZE {
+            return Err(format!("Wrapped key for key {} must be 1 to {} bytes", wrapped.key_id, MAX_PUBLIC_KEY_SIZE));
+        }
+        
+        let key = match recipients.iter().find(|key| key.id == wrapped.key_id) {
+            Some(key) => key,
+            None => return Err(format!(
+                "Key {} is not a current recipient key for this report; fetch the keys again",
+                wrapped.key_id
+            )),
+        };
+        
+        match key.owner {
+            KeyOwner::Reporter { .. } => encryption.reporter_key = Some(wrapped),
+            KeyOwner::Authority(id) => encryption.wrapped_keys.push((id, wrapped)),
+        }
     }
+    
+    if encryption.wrapped_keys.is_empty() {
+        return Err("The content key must be wrapped for at least one authority key".to_string());
+    }
+    
+    Ok(encryption)
 }
 
-// Get authority stats
+// Bring one authority's evidence access in line with their permissions and current key.
+// Authorities who can read reports are queued for a re-wrap of every encrypted file
+// that has no content key for their current key; anyone else loses their wrapped keys.
+fn sync_evidence_access(method: &str, authority_id: Principal) {
+    let eligible = has_permission(authority_id, Permission::ViewReports);
+    let current_key = store::get_current_key(&KeyOwner::Authority(authority_id));
+    let now = api::time();
+    
+    for mut file in store::get_encrypted_evidence_files() {
+        let encryption = match file.encryption.as_ref() {
+            Some(encryption) => encryption,
+            None => continue,
+        };
+        
+        if !eligible {
+            store::remove_rewrap_request(file.id, authority_id);
+            
+            if encryption.has_reader(authority_id) {
+                let before_digest = store::digest(&file);
+                if let Some(encryption) = file.encryption.as_mut() {
+                    encryption.wrapped_keys.retain(|(reader, _)| *reader != authority_id);
+                }
+                audit(
+                    method,
+                    vec![format!("evidence:{}", file.id), format!("authority:{}", authority_id)],
+                    Some(before_digest),
+                    Some(store::digest(&file)),
+                );
+                store::update_evidence_file(file);
+            }
+            continue;
+        }
+        
+        if let Some(key) = &current_key {
+            let has_current = encryption.wrapped_keys.iter()
+                .any(|(reader, wrapped)| *reader == authority_id && wrapped.key_id == key.id);
+            
+            if !has_current {
+                store::add_rewrap_request(file.id, authority_id, now);
+            }
+        }
+    }
+}
+
+// Queue re-wraps for authorities left out of a newly encrypted file
+fn queue_evidence_rewraps(file: &EvidenceFile) {
+    let encryption = match &file.encryption {
+        Some(encryption) => encryption,
+        None => return,
+    };
+    let now = api::time();
+    
+    for key in message_recipient_keys(file.report_id) {
+        if let KeyOwner::Authority(id) = key.owner {
+            let has_current = encryption.wrapped_keys.iter()
+                .any(|(reader, wrapped)| *reader == id && wrapped.key_id == key.id);
+            
+            if !has_current {
+                store::add_rewrap_request(file.id, id, now);
+            }
+        }
+    }
+}
+
+// Evidence access helper: plaintext files follow report access, encrypted files are
+// only served to the submitter and authorities holding a wrapped content key
+fn ensure_can_read_evidence(file: &EvidenceFile) -> Result<(), String> {
+    let report = ensure_can_view_report(file.report_id)?;
+    
+    if let Some(encryption) = &file.encryption {
+        let caller = caller();
+        if report.submitter_id != caller && !encryption.has_reader(caller) {
+            return Err("No content key has been wrapped for you on this evidence file".to_string());
+        }
+    }
+    
+    Ok(())
+}
+
+// Check a completed upload against its hash and attach it to the report.
+// Encrypted uploads pass the wrapped content keys; the hash covers the ciphertext.
+#[ic_cdk::update]
+fn finalize_evidence_upload(
+    upload_id: u64,
+    sha256: Vec<u8>,
+    encryption: Option<EvidenceEncryptionInput>,
+) -> Result<u64, String> {
+    let upload = completed_upload(upload_id, UploadPurpose::Evidence, &sha256)?;
+    let indexes = store::get_upload_chunk_indexes(upload_id);
+    
+    let mut report = match store::get_report(upload.report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    check_accepts_evidence(&report)?;
+    
+    let encryption = match encryption {
+        Some(input) => Some(resolve_evidence_encryption(upload.report_id, input)?),
+        None => None,
+    };
+    
+    let file = EvidenceFile {
+        id: 0, // Will be assigned by add_evidence_file
+        report_id: upload.report_id,
+        name: upload.name,
+        file_type: upload.file_type,
+        size: upload.total_size,
+        chunk_count: indexes.len() as u32,
+        sha256,
+        upload_date: api::time(),
+        encryption,
+        source_message_id: None,
+    };
+    
+    let file_id = store::add_evidence_file(&file);
+    queue_evidence_rewraps(&EvidenceFile { id: file_id, ..file });
+    store::move_upload_chunks_to_evidence(upload_id, file_id);
+    store::remove_evidence_upload(upload_id);
+    
+    let before_digest = store::digest(&report);
+    report.evidence_files.push(file_id);
+    report.evidence_count = report.evidence_files.len() as u32;
+    audit(
+        "finalize_evidence_upload",
+        vec![format!("report:{}", report.id), format!("evidence:{}", file_id)],
+        Some(before_digest),
+        Some(store::digest(&report)),
+    );
+    store::update_report(report)?;
+    
+    Ok(file_id)
+}
+
+// Check that one of the caller's uploads is complete, contiguous and matches its hash
+fn completed_upload(upload_id: u64, purpose: UploadPurpose, sha256: &[u8]) -> Result<EvidenceUpload, String> {
+    let caller = caller();
+    
+    let upload = match store::get_evidence_upload(upload_id) {
+        Some(upload) => upload,
+        None => return Err("Upload not found".to_string()),
+    };
+    
+    if upload.uploader != caller {
+        return Err("You can only finalize your own uploads".to_string());
+    }
+    
+    if upload.purpose.unwrap_or(UploadPurpose::Evidence) != purpose {
+        return Err(format!("Upload was not started as {:?}", purpose));
+    }
+    
+    if upload.received_size != upload.total_size {
+        return Err(format!(
+            "Upload is incomplete: received {} of {} bytes",
+            upload.received_size, upload.total_size
+        ));
+    }
+    
+    // Chunks must form a contiguous sequence starting at 0
+    let indexes = store::get_upload_chunk_indexes(upload_id);
+    if indexes.iter().enumerate().any(|(position, index)| *index as usize != position) {
+        return Err("Upload is missing one or more chunks".to_string());
+    }
+    
+    let mut hasher = Sha256::new();
+    for index in &indexes {
+        if let Some(chunk) = store::get_upload_chunk(upload_id, *index) {
+            hasher.update(&chunk);
+        }
+    }
+    let digest = hasher.finalize().to_vec();
+    
+    if digest != sha256 {
+        return Err("SHA-256 hash does not match the uploaded content".to_string());
+    }
+    
+    Ok(upload)
+}
+
+// Turn a completed attachment upload into an attachment that can be sent with a message
+#[ic_cdk::update]
+fn finalize_attachment_upload(upload_id: u64, sha256: Vec<u8>) -> Result<u64, String> {
+    let upload = completed_upload(upload_id, UploadPurpose::Attachment, &sha256)?;
+    let indexes = store::get_upload_chunk_indexes(upload_id);
+    
+    let attachment = Attachment {
+        id: 0, // Will be assigned by create_attachment
+        report_id: upload.report_id,
+        uploader: upload.uploader,
+        name: upload.name,
+        file_type: upload.file_type,
+        size: upload.total_size,
+        chunk_count: indexes.len() as u32,
+        sha256,
+        uploaded_at: api::time(),
+        message_id: None,
+        evidence_file_id: None,
+    };
+    
+    let attachment_id = store::create_attachment(&attachment);
+    store::move_upload_chunks_to_attachment(upload_id, attachment_id);
+    store::remove_evidence_upload(upload_id);
+    
+    audit(
+        "finalize_attachment_upload",
+        vec![format!("report:{}", upload.report_id), format!("attachment:{}", attachment_id)],
+        None,
+        Some(store::digest(&Attachment { id: attachment_id, ..attachment })),
+    );
+    
+    Ok(attachment_id)
+}
+
+// Download one chunk of a message attachment (for the report submitter and authorities)
 #[ic_cdk::query]
-fn get_authority_statistics() -> Result<AuthorityStats, String> {
-    ensure_authority()?;
-    Ok(store::get_authority_stats())
+fn get_attachment_chunk(attachment_id: u64, index: u32) -> Result<Vec<u8>, String> {
+    let attachment = match store::get_attachment(attachment_id) {
+        Some(attachment) => attachment,
+        None => return Err("Attachment not found".to_string()),
+    };
+    
+    ensure_can_view_report(attachment.report_id)?;
+    
+    // Unsent attachments are only visible to their uploader
+    if attachment.message_id.is_none() && attachment.uploader != caller() {
+        return Err("Attachment not found".to_string());
+    }
+    
+    match store::get_attachment_chunk(attachment_id, index) {
+        Some(chunk) => Ok(chunk),
+        None => Err(format!("Chunk {} not found (attachment has {} chunks)", index, attachment.chunk_count)),
+    }
 }
 
-// Add a new authority (only for existing authorities)
+// Add a message's attachment to the report's evidence, keeping a link to the message.
+// Encrypted attachments pass wrapped content keys as for finalize_evidence_upload.
 #[ic_cdk::update]
-fn add_new_authority(id: Principal) -> Result<(), String> {
-    ensure_authority()?;
+fn promote_attachment_to_evidence(
+    message_id: u64,
+    encryption: Option<EvidenceEncryptionInput>,
+) -> Result<u64, String> {
+    require_permission(Permission::ReviewReports)?;
+    
+    let message = match store::get_message(message_id) {
+        Some(message) => message,
+        None => return Err("Message not found".to_string()),
+    };
+    
+    let attachment_id = match &message.attachment {
+        Some(attachment) => attachment.attachment_id,
+        None => return Err("Message has no attachment".to_string()),
+    };
+    
+    let mut attachment = match store::get_attachment(attachment_id) {
+        Some(attachment) => attachment,
+        None => return Err("Attachment not found".to_string()),
+    };
     
-    if store::is_authority(id) {
-        return Err("Principal is already an authority".to_string());
+    if let Some(file_id) = attachment.evidence_file_id {
+        return Err(format!("Attachment is already evidence file {}", file_id));
     }
     
-    let authority = Authority {
-        id,
-        reports_reviewed: Vec::new(),
-        approval_rate: 0.0,
+    let mut report = match store::get_report(message.report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    
+    let encryption = match encryption {
+        Some(input) => Some(resolve_evidence_encryption(message.report_id, input)?),
+        None => None,
+    };
+    
+    let file = EvidenceFile {
+        id: 0, // Will be assigned by add_evidence_file
+        report_id: message.report_id,
+        name: attachment.name.clone(),
+        file_type: attachment.file_type.clone(),
+        size: attachment.size,
+        chunk_count: attachment.chunk_count,
+        sha256: attachment.sha256.clone(),
+        upload_date: api::time(),
+        encryption,
+        source_message_id: Some(message_id),
+    };
+    
+    let file_id = store::add_evidence_file(&file);
+    store::copy_attachment_chunks_to_evidence(attachment_id, attachment.chunk_count, file_id);
+    queue_evidence_rewraps(&EvidenceFile { id: file_id, ..file });
+    
+    attachment.evidence_file_id = Some(file_id);
+    store::update_attachment(attachment);
+    
+    let before_digest = store::digest(&report);
+    report.evidence_files.push(file_id);
+    report.evidence_count = report.evidence_files.len() as u32;
+    audit(
+        "promote_attachment_to_evidence",
+        vec![
+            format!("report:{}", report.id),
+            format!("message:{}", message_id),
+            format!("evidence:{}", file_id),
+        ],
+        Some(before_digest),
+        Some(store::digest(&report)),
+    );
+    store::update_report(report)?;
+    
+    store::create_message(&Message {
+        id: 0,
+        report_id: message.report_id,
+        sender: MessageSender::System,
+        content: "A message attachment was added to this report's evidence.".to_string(),
+        timestamp: api::time(),
+        attachment: None,
+        encrypted: None,
+    });
+    
+    Ok(file_id)
+}
+
+// Abandon an upload and free its chunks
+#[ic_cdk::update]
+fn cancel_evidence_upload(upload_id: u64) -> Result<(), String> {
+    let caller = caller();
+    
+    let upload = match store::get_evidence_upload(upload_id) {
+        Some(upload) => upload,
+        None => return Err("Upload not found".to_string()),
     };
     
-    store::add_authority(authority);
+    if upload.uploader != caller {
+        return Err("You can only cancel your own uploads".to_string());
+    }
+    
+    store::remove_evidence_upload(upload_id);
     
     Ok(())
 }
 
-// For development: Reset to initial state with mock data
+// Get evidence file metadata (for the report submitter and authorities)
+#[ic_cdk::query]
+fn get_evidence_info(file_id: u64) -> Result<EvidenceFile, String> {
+    let file = match store::get_evidence_file(file_id) {
+        Some(file) => file,
+        None => return Err("Evidence file not found".to_string()),
+    };
+    
+    ensure_can_read_evidence(&file)?;
+    
+    Ok(file)
+}
+
+// Get the content keys wrapped for the caller on an encrypted evidence file
+// (one per registered key they had when it was wrapped)
+#[ic_cdk::query]
+fn get_evidence_keys(file_id: u64) -> Result<Vec<WrappedKey>, String> {
+    let file = match store::get_evidence_file(file_id) {
+        Some(file) => file,
+        None => return Err("Evidence file not found".to_string()),
+    };
+    
+    ensure_can_read_evidence(&file)?;
+    
+    let encryption = match file.encryption {
+        Some(encryption) => encryption,
+        None => return Err("Evidence file is not encrypted".to_string()),
+    };
+    
+    let caller = caller();
+    let mut keys: Vec<WrappedKey> = encryption.wrapped_keys
+        .into_iter()
+        .filter(|(reader, _)| *reader == caller)
+        .map(|(_, wrapped)| wrapped)
+        .collect();
+    
+    if let Some(report) = store::get_report(file.report_id) {
+        if report.submitter_id == caller {
+            keys.extend(encryption.reporter_key);
+        }
+    }
+    
+    Ok(keys)
+}
+
+// Re-wraps the caller can perform: pending requests on files they hold a content key for
+#[ic_cdk::query]
+fn get_pending_evidence_rewraps() -> Result<Vec<RewrapRequest>, String> {
+    let caller = require_permission(Permission::ViewReports)?;
+    
+    let mut requests = Vec::new();
+    for (key, requested_at) in store::get_rewrap_requests() {
+        let file = match store::get_evidence_file(key.file_id) {
+            Some(file) => file,
+            None => continue,
+        };
+        
+        let readable = file.encryption.as_ref().is_some_and(|encryption| encryption.has_reader(caller));
+        let current_key = store::get_current_key(&KeyOwner::Authority(key.authority));
+        
+        if let (true, Some(current_key)) = (readable, current_key) {
+            requests.push(RewrapRequest {
+                file_id: key.file_id,
+                report_id: file.report_id,
+                authority: key.authority,
+                key_id: current_key.id,
+                requested_at,
+            });
+        }
+    }
+    
+    Ok(requests)
+}
+
+// Supply an evidence content key wrapped for another authority's current key
 #[ic_cdk::update]
-fn reset_to_mock_data() -> Result<(), String> {
-    ensure_authority()?;
+fn provide_evidence_key(file_id: u64, authority_id: Principal, wrapped: WrappedKey) -> Result<(), String> {
+    let caller = require_permission(Permission::ViewReports)?;
     
-    // This would be implemented to clear existing data and reinitialize mock data
-    // For brevity, we'll just call initialize again
-    store::initialize_mock_data();
+    let mut file = match store::get_evidence_file(file_id) {
+        Some(file) => file,
+        None => return Err("Evidence file not found".to_string()),
+    };
+    let before_digest = store::digest(&file);
+    
+    let encryption = match file.encryption.as_mut() {
+        Some(encryption) => encryption,
+        None => return Err("Evidence file is not encrypted".to_string()),
+    };
+    
+    if !encryption.has_reader(caller) {
+        return Err("You can only re-wrap keys for evidence you can read".to_string());
+    }
+    
+    if !has_permission(authority_id, Permission::ViewReports) {
+        return Err("That authority is not allowed to view reports".to_string());
+    }
+    
+    match store::get_current_key(&KeyOwner::Authority(authority_id)) {
+        Some(key) if key.id == wrapped.key_id => {}
+        Some(_) => return Err("The key must be wrapped for the authority's current key".to_string()),
+        None => return Err("That authority has not registered a key".to_string()),
+    }
+    
+    if wrapped.wrapped_key.is_empty() || wrapped.wrapped_key.len() > MAX_PUBLIC_KEY_SIZE {
+        return Err(format!("Wrapped key must be 1 to {} bytes", MAX_PUBLIC_KEY_SIZE));
+    }
+    
+    encryption.wrapped_keys.retain(|(reader, existing)| !(*reader == authority_id && existing.key_id == wrapped.key_id));
+    encryption.wrapped_keys.push((authority_id, wrapped));
+    
+    audit(
+        "provide_evidence_key",
+        vec![format!("evidence:{}", file_id), format!("authority:{}", authority_id)],
+        Some(before_digest),
+        Some(store::digest(&file)),
+    );
+    store::update_evidence_file(file);
+    store::remove_rewrap_request(file_id, authority_id);
     
     Ok(())
-}
\ No newline at end of file
+}
+
+// Download one chunk of an evidence file (for the report submitter and authorities)
+#[ic_cdk::query]
+fn get_evidence_chunk(file_id: u64, index: u32) -> Result<Vec<u8>, String> {
+    let file = match store::get_evidence_file(file_id) {
+        Some(file) => file,
+        None => return Err("Evidence file not found".to_string()),
+    };
+    
+    ensure_can_read_evidence(&file)?;
+    
+    match store::get_evidence_chunk(file_id, index) {
+        Some(chunk) => Ok(chunk),
+        None => Err(format!("Chunk {} not found (file has {} chunks)", index, file.chunk_count)),
+    }
+}
+
+// Hide reporter principals in an audit entry. Callers that are users but not
+// authorities are reporters, and user targets always name a reporter account.
+// The stored entry is untouched, so verify_audit_chain still covers it.
+fn redact_reporter_identity(mut entry: AuditEntry) -> AuditEntry {
+    if store::get_user(entry.caller).is_some() && !store::is_authority(entry.caller) {
+        entry.caller = Principal::anonymous();
+    }
+    
+    for target in entry.targets.iter_mut() {
+        if target.starts_with("user:") {
+            *target = "user:redacted".to_string();
+        }
+    }
+    
+    entry
+}
+
+// Page through the audit log (for authority)
+#[ic_cdk::query]
+fn get_audit_log(start_id: u64, limit: u64) -> Result<Vec<AuditEntry>, String> {
+    require_permission(Permission::ViewAuditLog)?;
+    Ok(store::get_audit_entries(start_id, limit.min(MAX_AUDIT_PAGE_SIZE) as usize)
+        .into_iter()
+        .map(redact_reporter_identity)
+        .collect())
+}
+
+// Recompute the audit hash chain and report the first broken link (for authority)
+#[ic_cdk::query]
+fn verify_audit_chain() -> Result<AuditChainStatus, String> {
+    require_permission(Permission::ViewAuditLog)?;
+    Ok(store::verify_audit_chain())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+// Message attachments: upload, sending by reference and promotion to evidence
+
+use super::*;
+
+fn setup() -> (u64, u64, u64) {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    let report_id = submit(REPORTER, 10).unwrap();
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    (report_id, reporter_key, authority_key)
+}
+
+fn begin(report_id: u64, file_type: &str, total_size: u64) -> Result<u64, String> {
+    begin_attachment_upload(report_id, "photo".to_string(), file_type.to_string(), total_size)
+}
+
+// Upload a two-chunk attachment as `uploader`
+fn upload_attachment(report_id: u64, uploader: Principal) -> u64 {
+    call_as(uploader);
+    let upload_id = begin(report_id, "image/png", 4).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![1, 2]).unwrap();
+    upload_evidence_chunk(upload_id, 1, vec![3, 4]).unwrap();
+    finalize_attachment_upload(upload_id, Sha256::digest([1, 2, 3, 4]).to_vec()).unwrap()
+}
+
+fn message_with_attachment(report_id: u64, attachment_id: u64) -> u64 {
+    call_as(AUTHORITY);
+    get_messages(report_id)
+        .iter()
+        .find(|message| message.attachment.as_ref().is_some_and(|attachment| attachment.attachment_id == attachment_id))
+        .expect("message with the attachment")
+        .id
+}
+
+#[test]
+fn attachments_are_sent_by_reference_and_can_become_evidence() {
+    let (report_id, reporter_key, authority_key) = setup();
+    let attachment_id = upload_attachment(report_id, REPORTER);
+    
+    // Nobody else sees the attachment until it is sent
+    call_as(AUTHORITY);
+    assert_eq!(get_attachment_chunk(attachment_id, 0).unwrap_err(), "Attachment not found");
+    
+    call_as(REPORTER);
+    send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), Some(attachment_id)).unwrap();
+    let error = send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), Some(attachment_id)).unwrap_err();
+    assert!(error.contains("already been sent"), "{}", error);
+    
+    call_as(AUTHORITY);
+    assert_eq!(get_attachment_chunk(attachment_id, 1), Ok(vec![3, 4]));
+    let message_id = message_with_attachment(report_id, attachment_id);
+    
+    // Only reviewers promote attachments
+    call_as(REPORTER);
+    assert!(promote_attachment_to_evidence(message_id, None).is_err());
+    
+    call_as(AUTHORITY);
+    let file_id = promote_attachment_to_evidence(message_id, None).unwrap();
+    let file = get_evidence_info(file_id).unwrap();
+    assert_eq!(file.source_message_id, Some(message_id));
+    assert_eq!((file.size, file.chunk_count), (4, 2));
+    assert_eq!(get_evidence_chunk(file_id, 0), Ok(vec![1, 2]));
+    assert_eq!(store::get_report(report_id).unwrap().evidence_files, vec![file_id]);
+    
+    let error = promote_attachment_to_evidence(message_id, None).unwrap_err();
+    assert!(error.contains("already evidence file"), "{}", error);
+    assert!(get_attachment_chunk(attachment_id, 0).is_ok());
+}
+
+#[test]
+fn attachments_are_checked() {
+    let (report_id, reporter_key, authority_key) = setup();
+    let other_report_id = submit(REPORTER, 10).unwrap();
+    
+    call_as(REPORTER);
+    let error = begin(report_id, "application/x-msdownload", 4).unwrap_err();
+    assert!(error.contains("must be one of"), "{}", error);
+    assert!(begin(report_id, "image/png", 0).is_err());
+    assert!(begin(report_id, "image/png", MAX_ATTACHMENT_SIZE + 1).is_err());
+    call_as(OTHER_REPORTER);
+    assert!(begin(report_id, "image/png", 4).is_err());
+    
+    // An attachment upload cannot be finalized as evidence
+    call_as(REPORTER);
+    let upload_id = begin(report_id, "image/png", 1).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![1]).unwrap();
+    let error = finalize_evidence_upload(upload_id, Sha256::digest([1]).to_vec(), None).unwrap_err();
+    assert!(error.contains("not started as"), "{}", error);
+    
+    // Attachments only go out on their own report, from their uploader
+    let attachment_id = upload_attachment(report_id, REPORTER);
+    let other_key = register_reporter_key(other_report_id, "x25519".to_string(), vec![1; 32]).unwrap();
+    let error = send_message_as_reporter(other_report_id, encrypted(other_key, &[authority_key]), Some(attachment_id)).unwrap_err();
+    assert!(error.contains("different report"), "{}", error);
+    call_as(AUTHORITY);
+    let error = send_message_as_authority(report_id, encrypted(authority_key, &[reporter_key]), Some(attachment_id)).unwrap_err();
+    assert!(error.contains("your own attachments"), "{}", error);
+    
+    // Messages without an attachment cannot be promoted
+    send_message_as_authority(report_id, encrypted(authority_key, &[reporter_key]), None).unwrap();
+    let message_id = get_messages(report_id).last().unwrap().id;
+    assert_eq!(promote_attachment_to_evidence(message_id, None).unwrap_err(), "Message has no attachment");
+}
@@ -0,0 +1,109 @@
+// Managed report categories: validation, parent chains, retirement and stake limits
+
+use super::*;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+}
+
+fn category(id: &str, parent_id: Option<&str>) -> Category {
+    Category {
+        id: id.to_string(),
+        name: format!("Category {}", id),
+        description: String::new(),
+        parent_id: parent_id.map(str::to_string),
+        min_stake_amount: None,
+        max_stake_amount: None,
+        active: true,
+    }
+}
+
+fn submit_in(category: &str, stake_amount: u64) -> Result<u64, String> {
+    call_as(REPORTER);
+    block_on(file_report(
+        "Report title".to_string(),
+        "Report description".to_string(),
+        category.to_string(),
+        None,
+        None,
+        stake_amount,
+    ))
+}
+
+#[test]
+fn defaults_are_seeded_once() {
+    setup();
+    let count = get_categories().len();
+    assert!(get_categories().iter().any(|category| category.id == "fraud"));
+    
+    call_as(AUTHORITY);
+    create_category(category("wildlife", None)).unwrap();
+    store::seed_default_categories();
+    assert_eq!(get_categories().len(), count + 1);
+}
+
+#[test]
+fn categories_are_validated() {
+    setup();
+    call_as(AUTHORITY);
+    
+    assert!(create_category(category("", None)).is_err());
+    assert!(create_category(category("Upper Case", None)).is_err());
+    assert!(create_category(category(&"a".repeat(65), None)).is_err());
+    assert!(create_category(Category { name: " ".to_string(), ..category("blank", None) }).is_err());
+    let error = create_category(Category { min_stake_amount: Some(20), max_stake_amount: Some(10), ..category("limits", None) }).unwrap_err();
+    assert!(error.contains("cannot exceed"), "{}", error);
+    let error = create_category(category("orphan", Some("missing"))).unwrap_err();
+    assert!(error.contains("not found"), "{}", error);
+    let error = create_category(category("fraud", None)).unwrap_err();
+    assert!(error.contains("already exists"), "{}", error);
+    
+    // Editing a parent chain cannot make it loop
+    create_category(category("online-fraud", Some("fraud"))).unwrap();
+    create_category(category("phishing", Some("online-fraud"))).unwrap();
+    let error = update_category(Category { parent_id: Some("phishing".to_string()), ..store::get_category("fraud").unwrap() }).unwrap_err();
+    assert!(error.contains("own ancestor"), "{}", error);
+    assert!(update_category(category("unknown", None)).is_err());
+    
+    call_as(REPORTER);
+    assert!(create_category(category("reporters-own", None)).is_err());
+}
+
+#[test]
+fn retired_categories_keep_their_reports_but_take_no_new_ones() {
+    setup();
+    let report_id = submit_in("theft", 10).unwrap();
+    assert_eq!(submit_in("unknown", 10).unwrap_err(), "Unknown report category 'unknown'");
+    
+    call_as(AUTHORITY);
+    retire_category("theft".to_string()).unwrap();
+    assert!(!store::get_category("theft").unwrap().active);
+    
+    let error = submit_in("theft", 10).unwrap_err();
+    assert!(error.contains("no longer accepting"), "{}", error);
+    call_as(AUTHORITY);
+    let listed: Vec<u64> = get_reports_by_category("theft".to_string()).unwrap().iter().map(|report| report.id).collect();
+    assert_eq!(listed, vec![report_id]);
+    assert!(get_reports_by_category("unknown".to_string()).is_err());
+}
+
+#[test]
+fn category_limits_narrow_the_reward_config() {
+    setup();
+    call_as(AUTHORITY);
+    let limited = Category { min_stake_amount: Some(20), max_stake_amount: Some(1_000), ..category("limited", None) };
+    create_category(limited).unwrap();
+    
+    // The reward config still caps stakes at 100
+    assert_eq!(submit_in("limited", 19).unwrap_err(), "Minimum stake amount is 20 tokens");
+    assert_eq!(submit_in("limited", 101).unwrap_err(), "Maximum stake amount is 100 tokens");
+    assert!(submit_in("limited", 20).is_ok());
+    
+    // Limits below the config's minimum do not lower it
+    call_as(AUTHORITY);
+    update_category(Category { min_stake_amount: Some(1), max_stake_amount: Some(30), ..category("limited", None) }).unwrap();
+    assert_eq!(submit_in("limited", 4).unwrap_err(), "Minimum stake amount is 5 tokens");
+    assert_eq!(submit_in("limited", 31).unwrap_err(), "Maximum stake amount is 30 tokens");
+}
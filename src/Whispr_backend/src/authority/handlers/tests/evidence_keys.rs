@@ -0,0 +1,119 @@
+// Envelope-encrypted evidence: wrapped content keys, re-wrap requests and key rotation
+
+use super::*;
+
+const SECOND_ADMIN: Principal = Principal::from_slice(&[40]);
+
+fn setup() -> (u64, u64, u64, u64) {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    add_admin(SECOND_ADMIN);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    let report_id = submit(REPORTER, 10).unwrap();
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    call_as(SECOND_ADMIN);
+    let second_key = register_authority_key("x25519".to_string(), vec![3; 32]).unwrap();
+    (report_id, reporter_key, authority_key, second_key)
+}
+
+fn wrapped(key_id: u64) -> WrappedKey {
+    WrappedKey { key_id, wrapped_key: vec![key_id as u8; 32] }
+}
+
+// Upload a one-chunk encrypted evidence file as REPORTER, wrapped for `key_ids`
+fn upload_encrypted(report_id: u64, key_ids: &[u64]) -> Result<u64, String> {
+    call_as(REPORTER);
+    let upload_id = begin_evidence_upload(report_id, "photo.enc".to_string(), "application/octet-stream".to_string(), 3).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![9, 9, 9]).unwrap();
+    let encryption = EvidenceEncryptionInput {
+        algorithm: "x25519-aes-gcm".to_string(),
+        nonce: vec![0; 12],
+        wrapped_keys: key_ids.iter().map(|key_id| wrapped(*key_id)).collect(),
+    };
+    finalize_evidence_upload(upload_id, Sha256::digest([9, 9, 9]).to_vec(), Some(encryption))
+}
+
+// IDs of the keys the caller's content keys for a file are wrapped for
+fn evidence_key_ids(file_id: u64) -> Vec<u64> {
+    get_evidence_keys(file_id).unwrap().iter().map(|wrapped| wrapped.key_id).collect()
+}
+
+fn pending_rewraps(caller: Principal) -> Vec<(u64, Principal, u64)> {
+    call_as(caller);
+    get_pending_evidence_rewraps()
+        .unwrap()
+        .iter()
+        .map(|request| (request.file_id, request.authority, request.key_id))
+        .collect()
+}
+
+#[test]
+fn authorities_left_out_get_a_rewrap_from_a_reader() {
+    let (report_id, reporter_key, authority_key, second_key) = setup();
+    let file_id = upload_encrypted(report_id, &[reporter_key, authority_key]).unwrap();
+    
+    // Only authorities who can already read the file see the request
+    assert_eq!(pending_rewraps(AUTHORITY), vec![(file_id, SECOND_ADMIN, second_key)]);
+    assert!(pending_rewraps(SECOND_ADMIN).is_empty());
+    let error = get_evidence_chunk(file_id, 0).unwrap_err();
+    assert!(error.contains("No content key"), "{}", error);
+    assert!(provide_evidence_key(file_id, SECOND_ADMIN, wrapped(second_key)).is_err());
+    
+    call_as(AUTHORITY);
+    provide_evidence_key(file_id, SECOND_ADMIN, wrapped(second_key)).unwrap();
+    
+    assert!(pending_rewraps(AUTHORITY).is_empty());
+    call_as(SECOND_ADMIN);
+    assert_eq!(evidence_key_ids(file_id), vec![second_key]);
+    assert_eq!(get_evidence_chunk(file_id, 0), Ok(vec![9, 9, 9]));
+    
+    // The reporter gets their own wrapped key
+    call_as(REPORTER);
+    assert_eq!(evidence_key_ids(file_id), vec![reporter_key]);
+}
+
+#[test]
+fn rotating_a_key_keeps_the_old_wrap_and_queues_a_new_one() {
+    let (report_id, reporter_key, authority_key, second_key) = setup();
+    let file_id = upload_encrypted(report_id, &[reporter_key, authority_key, second_key]).unwrap();
+    assert!(pending_rewraps(AUTHORITY).is_empty());
+    
+    call_as(SECOND_ADMIN);
+    let rotated_key = register_authority_key("x25519".to_string(), vec![4; 32]).unwrap();
+    
+    // The old content key still opens the file until the new one arrives
+    assert_eq!(evidence_key_ids(file_id), vec![second_key]);
+    assert!(get_evidence_chunk(file_id, 0).is_ok());
+    assert_eq!(pending_rewraps(AUTHORITY), vec![(file_id, SECOND_ADMIN, rotated_key)]);
+    
+    // Re-wraps must target the current key
+    call_as(AUTHORITY);
+    let error = provide_evidence_key(file_id, SECOND_ADMIN, wrapped(second_key)).unwrap_err();
+    assert!(error.contains("current key"), "{}", error);
+    provide_evidence_key(file_id, SECOND_ADMIN, wrapped(rotated_key)).unwrap();
+    
+    call_as(SECOND_ADMIN);
+    assert_eq!(evidence_key_ids(file_id), vec![second_key, rotated_key]);
+    assert!(pending_rewraps(AUTHORITY).is_empty());
+}
+
+#[test]
+fn encrypted_uploads_need_an_authority_reader() {
+    let (report_id, reporter_key, authority_key, _) = setup();
+    
+    let error = upload_encrypted(report_id, &[reporter_key]).unwrap_err();
+    assert!(error.contains("at least one authority key"), "{}", error);
+    let error = upload_encrypted(report_id, &[authority_key, authority_key]).unwrap_err();
+    assert!(error.contains("more than once"), "{}", error);
+    let error = upload_encrypted(report_id, &[authority_key, 99]).unwrap_err();
+    assert!(error.contains("fetch the keys again"), "{}", error);
+    
+    // Plaintext evidence has no keys to hand out
+    call_as(REPORTER);
+    let upload_id = begin_evidence_upload(report_id, "note.txt".to_string(), "text/plain".to_string(), 1).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![1]).unwrap();
+    let file_id = finalize_evidence_upload(upload_id, Sha256::digest([1]).to_vec(), None).unwrap();
+    assert!(get_evidence_keys(file_id).is_err());
+    call_as(AUTHORITY);
+    assert!(provide_evidence_key(file_id, SECOND_ADMIN, wrapped(1)).is_err());
+}
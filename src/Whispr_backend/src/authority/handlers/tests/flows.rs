@@ -0,0 +1,267 @@
+// Stake and payout flows on internal balances, checking the books after every step
+
+use super::*;
+use candid::CandidType;
+use ic_stable_structures::Storable;
+use std::borrow::Cow;
+
+const STARTING_BALANCE: u64 = 500;
+const TREASURY_FUNDS: u64 = 10_000;
+
+// A principal with no user record (a single 0x04 byte would be the anonymous principal)
+const UNREGISTERED: Principal = Principal::from_slice(&[5]);
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", STARTING_BALANCE);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", STARTING_BALANCE);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, TREASURY_FUNDS, None);
+    assert_books_balance();
+}
+
+fn split_slashes() {
+    store::set_slashing_policy(SlashingPolicy {
+        burn_percent: 20,
+        treasury_percent: 50,
+        reviewer_pool_percent: 30,
+        soft_reject_refund_percent: 40,
+        withdrawal_refund_percent: 50,
+    });
+}
+
+fn balance(id: Principal) -> u64 {
+    store::get_user(id).map_or(0, |user| user.token_balance)
+}
+
+fn system_balance(account: BookAccount) -> u64 {
+    store::get_system_balance(&account)
+}
+
+fn total_supply() -> u64 {
+    system_balance(BookAccount::Minting)
+}
+
+#[test]
+fn stake_is_held_in_escrow() {
+    setup();
+
+    let report_id = submit(REPORTER, 50).unwrap();
+    assert_books_balance();
+
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 50);
+    assert_eq!(store::check_ledger_invariants().escrow_balances, 50);
+    assert_eq!(store::get_user(REPORTER).unwrap().stakes_active, 50);
+    assert_eq!(report_status(report_id), ReportStatus::Pending);
+
+    // Stakes over the limit and unregistered reporters are refused without touching the books
+    let error = submit(REPORTER, 500).unwrap_err();
+    assert!(error.contains("Maximum stake"), "{}", error);
+    let error = submit(UNREGISTERED, 50).unwrap_err();
+    assert!(error.contains("Register"), "{}", error);
+    assert_books_balance();
+}
+
+#[test]
+fn verify_returns_the_stake_and_pays_the_reward() {
+    setup();
+    let report_id = submit(REPORTER, 50).unwrap();
+    assert_books_balance();
+
+    call_as(AUTHORITY);
+    claim_report_for_review(report_id).unwrap();
+    assert_books_balance();
+    assert!(matches!(verify_report(report_id, None), Ok(ActionOutcome::Executed)));
+    assert_books_balance();
+
+    assert_eq!(balance(REPORTER), STARTING_BALANCE + 500);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS - 500);
+    assert_eq!(store::check_ledger_invariants().escrow_balances, 0);
+    let reporter = store::get_user(REPORTER).unwrap();
+    assert_eq!((reporter.stakes_active, reporter.rewards_earned), (0, 500));
+}
+
+#[test]
+fn reward_needs_a_funded_treasury() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", STARTING_BALANCE);
+    let report_id = submit(REPORTER, 50).unwrap();
+
+    call_as(AUTHORITY);
+    let error = verify_report(report_id, None).unwrap_err();
+    assert!(error.contains("not enough"), "{}", error);
+    assert_eq!(report_status(report_id), ReportStatus::Pending);
+    assert_books_balance();
+
+    call_as(OTHER_REPORTER);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", STARTING_BALANCE);
+    assert_eq!(fund_treasury(STARTING_BALANCE), Ok(STARTING_BALANCE));
+    assert_books_balance();
+
+    call_as(AUTHORITY);
+    verify_report(report_id, None).unwrap();
+    assert_books_balance();
+    assert_eq!(system_balance(BookAccount::Treasury), 0);
+    assert_eq!(balance(REPORTER), STARTING_BALANCE + 500);
+}
+
+#[test]
+fn reject_slashes_the_stake_per_the_slashing_policy() {
+    setup();
+    split_slashes();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let supply = total_supply();
+
+    call_as(AUTHORITY);
+    reject_report(report_id, None).unwrap();
+    assert_books_balance();
+
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 50);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS + 25);
+    assert_eq!(system_balance(BookAccount::ReviewerPool), 15);
+    assert_eq!(total_supply(), supply - 10);
+    let reporter = store::get_user(REPORTER).unwrap();
+    assert_eq!((reporter.stakes_active, reporter.stakes_lost), (0, 50));
+}
+
+#[test]
+fn soft_reject_refunds_part_of_the_stake() {
+    setup();
+    split_slashes();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let supply = total_supply();
+
+    call_as(AUTHORITY);
+    assert_eq!(soft_reject_report(report_id, SoftRejectReason::Duplicate, None), Ok(20));
+    assert_books_balance();
+
+    // 30 slashed: 15 to the treasury, 9 to reviewers and 6 burned
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 30);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS + 15);
+    assert_eq!(system_balance(BookAccount::ReviewerPool), 9);
+    assert_eq!(total_supply(), supply - 6);
+    assert_eq!(store::get_user(REPORTER).unwrap().stakes_lost, 30);
+
+    let error = reopen_report(report_id, None).unwrap_err();
+    assert!(error.contains("Soft-rejected"), "{}", error);
+    assert_books_balance();
+}
+
+#[test]
+fn withdrawal_refunds_half_the_stake() {
+    setup();
+    let report_id = submit(REPORTER, 50).unwrap();
+
+    call_as(OTHER_REPORTER);
+    assert!(withdraw_report(report_id, None).is_err());
+    call_as(REPORTER);
+    assert_eq!(withdraw_report(report_id, None), Ok(25));
+    assert_books_balance();
+
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 25);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS + 25);
+    assert_eq!(report_status(report_id), ReportStatus::Withdrawn);
+
+    call_as(AUTHORITY);
+    assert!(verify_report(report_id, None).is_err());
+    assert_books_balance();
+}
+
+#[test]
+fn withdrawal_refund_follows_the_slashing_policy() {
+    setup();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let policy = store::get_slashing_policy();
+
+    call_as(AUTHORITY);
+    let error = update_slashing_policy(SlashingPolicy { withdrawal_refund_percent: 101, ..policy.clone() }).unwrap_err();
+    assert!(error.contains("Withdrawal refund"), "{}", error);
+    update_slashing_policy(SlashingPolicy { withdrawal_refund_percent: 80, ..policy.clone() }).unwrap();
+    assert_eq!(get_slashing_policy().unwrap().withdrawal_refund_percent, 80);
+
+    // Only authorities who manage rewards can change it
+    call_as(REPORTER);
+    assert!(update_slashing_policy(SlashingPolicy { withdrawal_refund_percent: 100, ..policy }).is_err());
+
+    assert_eq!(withdraw_report(report_id, None), Ok(40));
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 10);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS + 10);
+    assert_books_balance();
+}
+
+// Same shape as a slashing policy written before the withdrawal refund was configurable
+#[derive(CandidType)]
+struct FixedRefundPolicy {
+    burn_percent: u64,
+    treasury_percent: u64,
+    reviewer_pool_percent: u64,
+    soft_reject_refund_percent: u64,
+}
+
+#[test]
+fn policy_stored_without_a_withdrawal_refund_keeps_the_old_half() {
+    let stored = FixedRefundPolicy { burn_percent: 20, treasury_percent: 50, reviewer_pool_percent: 30, soft_reject_refund_percent: 40 };
+    let bytes = candid::encode_one(&stored).unwrap();
+
+    let policy = SlashingPolicy::from_bytes(Cow::Owned(bytes));
+
+    assert_eq!((policy.burn_percent, policy.treasury_percent, policy.reviewer_pool_percent), (20, 50, 30));
+    assert_eq!(policy.soft_reject_refund_percent, 40);
+    assert_eq!(policy.withdrawal_refund_percent, 50);
+
+    // Policies that set it keep it
+    let generous = SlashingPolicy { withdrawal_refund_percent: 90, ..policy };
+    assert_eq!(SlashingPolicy::from_bytes(generous.to_bytes()).withdrawal_refund_percent, 90);
+}
+
+#[test]
+fn reopen_reinstates_the_slashed_stake() {
+    setup();
+    split_slashes();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let supply = total_supply();
+
+    call_as(AUTHORITY);
+    reject_report(report_id, None).unwrap();
+    assert_books_balance();
+    reopen_report(report_id, None).unwrap();
+    assert_books_balance();
+
+    // The burned share comes back out of the treasury
+    assert_eq!(store::check_ledger_invariants().escrow_balances, 50);
+    assert_eq!(system_balance(BookAccount::Treasury), TREASURY_FUNDS - 10);
+    assert_eq!(system_balance(BookAccount::ReviewerPool), 0);
+    assert_eq!(total_supply(), supply - 10);
+    assert_eq!(report_status(report_id), ReportStatus::Reopened);
+
+    verify_report(report_id, None).unwrap();
+    assert_books_balance();
+    assert_eq!(balance(REPORTER), STARTING_BALANCE + 500);
+    let reporter = store::get_user(REPORTER).unwrap();
+    assert_eq!((reporter.stakes_active, reporter.stakes_lost), (0, 0));
+}
+
+#[test]
+fn transfer_moves_tokens_between_users() {
+    setup();
+    submit(REPORTER, 50).unwrap();
+    call_as(REPORTER);
+
+    let entry = transfer(OTHER_REPORTER, 100, Some("thanks".to_string()), "first".to_string()).unwrap();
+    assert_books_balance();
+    assert_eq!(balance(REPORTER), STARTING_BALANCE - 50 - 100);
+    assert_eq!(balance(OTHER_REPORTER), STARTING_BALANCE + 100);
+
+    // Retrying with the same key returns the original transfer
+    let retried = transfer(OTHER_REPORTER, 100, Some("thanks".to_string()), "first".to_string()).unwrap();
+    assert_eq!(retried.id, entry.id);
+    assert_books_balance();
+    assert_eq!(balance(OTHER_REPORTER), STARTING_BALANCE + 100);
+
+    // Staked tokens stay in escrow
+    let error = transfer(OTHER_REPORTER, 400, None, "second".to_string()).unwrap_err();
+    assert!(error.contains("Insufficient balance"), "{}", error);
+    assert!(transfer(UNREGISTERED, 10, None, "third".to_string()).is_err());
+    assert_books_balance();
+}
@@ -0,0 +1,178 @@
+// Multi-signature proposals for large rewards and authority changes
+
+use super::*;
+
+const SECOND_ADMIN: Principal = Principal::from_slice(&[10]);
+const THIRD_ADMIN: Principal = Principal::from_slice(&[11]);
+const NEWCOMER: Principal = Principal::from_slice(&[12]);
+const REVIEWER: Principal = Principal::from_slice(&[13]);
+
+// A report whose reward (10x the stake) is above the default approval threshold of 500
+const LARGE_STAKE: u64 = 60;
+
+fn setup(admins: &[Principal]) {
+    store::seed_default_categories();
+    for admin in admins {
+        add_admin(*admin);
+    }
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+}
+
+fn add_reviewer(id: Principal) {
+    store::add_authority(Authority {
+        id,
+        reports_reviewed: Vec::new(),
+        approval_rate: 0.0,
+        roles: vec![AuthorityRole::Reviewer],
+    });
+}
+
+fn pending_proposal(outcome: Result<ActionOutcome, String>) -> u64 {
+    match outcome {
+        Ok(ActionOutcome::PendingApproval { proposal_id }) => proposal_id,
+        other => panic!("expected a pending proposal, got {:?}", other),
+    }
+}
+
+fn proposal_status(proposal_id: u64) -> ProposalStatus {
+    store::get_proposal(proposal_id).expect("proposal exists").status
+}
+
+#[test]
+fn lone_admin_cannot_add_an_authority() {
+    setup(&[AUTHORITY]);
+    call_as(AUTHORITY);
+
+    let error = add_new_authority(NEWCOMER).unwrap_err();
+
+    assert!(error.contains("needs 2 approvals"), "{}", error);
+    assert!(!store::is_authority(NEWCOMER));
+    assert!(store::get_all_proposals().is_empty());
+}
+
+#[test]
+fn lone_admin_cannot_pay_a_large_reward() {
+    setup(&[AUTHORITY]);
+    let report_id = submit(REPORTER, LARGE_STAKE).unwrap();
+    call_as(AUTHORITY);
+
+    let error = verify_report(report_id, None).unwrap_err();
+
+    assert!(error.contains("needs 2 approvals"), "{}", error);
+    assert_eq!(report_status(report_id), ReportStatus::Pending);
+    assert_eq!(store::get_user(REPORTER).unwrap().rewards_earned, 0);
+}
+
+#[test]
+fn adding_an_authority_needs_a_second_admin() {
+    setup(&[AUTHORITY, SECOND_ADMIN]);
+    call_as(AUTHORITY);
+
+    let proposal_id = pending_proposal(add_new_authority(NEWCOMER));
+    assert!(!store::is_authority(NEWCOMER));
+
+    // The proposer's approval is already counted and cannot be given twice
+    let error = approve_proposal(proposal_id).unwrap_err();
+    assert!(error.contains("already voted"), "{}", error);
+
+    // Authorities without ManageAuthorities cannot vote
+    add_reviewer(REVIEWER);
+    call_as(REVIEWER);
+    assert!(approve_proposal(proposal_id).is_err());
+
+    call_as(SECOND_ADMIN);
+    let proposal = approve_proposal(proposal_id).unwrap();
+    assert_eq!(proposal.status, ProposalStatus::Executed);
+    assert_eq!(proposal.approvals, vec![AUTHORITY, SECOND_ADMIN]);
+    let newcomer = store::get_authority(NEWCOMER).unwrap();
+    assert_eq!(newcomer.roles, vec![AuthorityRole::Reviewer]);
+
+    // A resolved proposal takes no more votes
+    call_as(AUTHORITY);
+    assert!(approve_proposal(proposal_id).is_err());
+}
+
+#[test]
+fn large_rewards_are_routed_to_a_proposal() {
+    setup(&[AUTHORITY, SECOND_ADMIN]);
+    let small = submit(REPORTER, 50).unwrap();
+    let large = submit(REPORTER, LARGE_STAKE).unwrap();
+    call_as(AUTHORITY);
+
+    // A reward at the threshold is paid straight away
+    assert!(matches!(verify_report(small, None), Ok(ActionOutcome::Executed)));
+
+    let proposal_id = pending_proposal(verify_report(large, None));
+    assert_eq!(report_status(large), ReportStatus::Pending);
+    let error = verify_report(large, None).unwrap_err();
+    assert!(error.contains("already open"), "{}", error);
+
+    call_as(SECOND_ADMIN);
+    assert_eq!(approve_proposal(proposal_id).unwrap().status, ProposalStatus::Executed);
+    assert_eq!(report_status(large), ReportStatus::Approved);
+    assert_eq!(store::get_user(REPORTER).unwrap().rewards_earned, 500 + 600);
+    assert_books_balance();
+}
+
+#[test]
+fn proposals_expire() {
+    setup(&[AUTHORITY, SECOND_ADMIN]);
+    call_as(AUTHORITY);
+    let proposal_id = pending_proposal(add_new_authority(NEWCOMER));
+
+    runtime::advance_time(Duration::from_nanos(store::get_governance_config().proposal_ttl_ns + 1));
+    assert_eq!(get_proposal(proposal_id).unwrap().status, ProposalStatus::Expired);
+
+    call_as(SECOND_ADMIN);
+    let error = approve_proposal(proposal_id).unwrap_err();
+    assert!(error.contains("expired"), "{}", error);
+    assert_eq!(proposal_status(proposal_id), ProposalStatus::Expired);
+    assert!(!store::is_authority(NEWCOMER));
+}
+
+#[test]
+fn proposals_close_once_they_cannot_pass() {
+    setup(&[AUTHORITY, SECOND_ADMIN, THIRD_ADMIN]);
+    call_as(AUTHORITY);
+    let proposal_id = pending_proposal(add_new_authority(NEWCOMER));
+
+    // The proposer and the third admin could still approve it
+    call_as(SECOND_ADMIN);
+    assert_eq!(reject_proposal(proposal_id).unwrap().status, ProposalStatus::Open);
+    assert!(approve_proposal(proposal_id).is_err());
+
+    call_as(THIRD_ADMIN);
+    assert_eq!(reject_proposal(proposal_id).unwrap().status, ProposalStatus::Rejected);
+    assert!(!store::is_authority(NEWCOMER));
+}
+
+#[test]
+fn governance_keeps_enough_admins_to_approve() {
+    setup(&[AUTHORITY, SECOND_ADMIN]);
+    call_as(AUTHORITY);
+
+    let error = remove_authority(SECOND_ADMIN).unwrap_err();
+    assert!(error.contains("at least 2 admins"), "{}", error);
+    let error = revoke_role(SECOND_ADMIN, AuthorityRole::Admin).unwrap_err();
+    assert!(error.contains("at least 2 admins"), "{}", error);
+
+    let config = GovernanceConfig { required_approvals: 3, ..store::get_governance_config() };
+    let error = update_governance_config(config).unwrap_err();
+    assert!(error.contains("only 2 admins"), "{}", error);
+}
+
+#[test]
+fn init_installs_the_admins_it_is_given() {
+    init(Some(InitArgs { ledger_canister_id: None, ledger_fee: None, admins: Some(vec![AUTHORITY, SECOND_ADMIN]) }));
+
+    for admin in [AUTHORITY, SECOND_ADMIN] {
+        assert!(store::get_authority(admin).unwrap().has_role(AuthorityRole::Admin));
+    }
+
+    call_as(AUTHORITY);
+    let proposal_id = pending_proposal(add_new_authority(NEWCOMER));
+    call_as(SECOND_ADMIN);
+    approve_proposal(proposal_id).unwrap();
+    assert!(store::is_authority(NEWCOMER));
+}
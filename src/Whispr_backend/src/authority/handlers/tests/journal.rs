@@ -0,0 +1,85 @@
+// Double-entry journal: per-party transaction history and opening balances
+
+use super::*;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+}
+
+fn history(id: Principal) -> Vec<JournalEntry> {
+    call_as(id);
+    get_my_transactions(0, 100)
+}
+
+fn kinds(entries: &[JournalEntry]) -> Vec<EntryKind> {
+    entries.iter().map(|entry| entry.kind).collect()
+}
+
+#[test]
+fn history_covers_the_callers_tokens_and_report_stakes() {
+    setup();
+    let verified = submit(REPORTER, 10).unwrap();
+    let rejected = submit(REPORTER, 20).unwrap();
+    call_as(AUTHORITY);
+    verify_report(verified, None).unwrap();
+    reject_report(rejected, None).unwrap();
+    
+    // The slash moves tokens between canister books, but is listed for the reporter
+    let entries = history(REPORTER);
+    assert_eq!(kinds(&entries), vec![
+        EntryKind::Grant,
+        EntryKind::StakeLock,
+        EntryKind::StakeLock,
+        EntryKind::StakeRefund,
+        EntryKind::RewardPayout,
+        EntryKind::StakeSlash,
+    ]);
+    let slash = entries.last().unwrap();
+    assert_eq!((slash.debit.clone(), slash.credit.clone()), (BookAccount::Escrow { report_id: rejected }, BookAccount::Treasury));
+    assert_eq!(slash.report_id, Some(rejected));
+    
+    // Report IDs never reach memos, which end up in the public block log
+    assert!(entries.iter().all(|entry| entry.memo.is_none()));
+    
+    assert_eq!(kinds(&history(OTHER_REPORTER)), vec![EntryKind::Grant]);
+    assert!(history(AUTHORITY).is_empty());
+    assert_books_balance();
+}
+
+#[test]
+fn history_pages_forward_from_the_last_entry_seen() {
+    setup();
+    for _ in 0..3 {
+        submit(REPORTER, 10).unwrap();
+    }
+    let all: Vec<u64> = history(REPORTER).iter().map(|entry| entry.id).collect();
+    assert_eq!(all.len(), 4);
+    
+    call_as(REPORTER);
+    let first_page: Vec<u64> = get_my_transactions(0, 2).iter().map(|entry| entry.id).collect();
+    let second_page: Vec<u64> = get_my_transactions(first_page[1], 2).iter().map(|entry| entry.id).collect();
+    assert_eq!([first_page, second_page].concat(), all);
+    assert!(get_my_transactions(u64::MAX, 2).is_empty());
+    
+    call_as(Principal::anonymous());
+    assert!(get_my_transactions(0, 10).is_empty());
+}
+
+#[test]
+fn balances_from_before_the_journal_get_opening_entries() {
+    store::create_or_update_user(User { token_balance: 70, ..User::new(REPORTER) });
+    assert!(history(REPORTER).is_empty());
+    
+    post_upgrade();
+    post_upgrade();
+    
+    let entries = history(REPORTER);
+    assert_eq!(kinds(&entries), vec![EntryKind::OpeningBalance]);
+    assert_eq!((entries[0].credit.clone(), entries[0].amount), (BookAccount::user(REPORTER), 70));
+    assert_eq!(store::get_system_balance(&BookAccount::Minting), 70);
+    assert_books_balance();
+}
@@ -0,0 +1,267 @@
+// Ledger-backed staking against the stand-in ledger
+
+use super::*;
+use mock_ledger::{with_ledger, ApproveArgs};
+
+const LEDGER: Principal = Principal::from_slice(&[9]);
+const FEE: u64 = 1;
+const STARTING_BALANCE: u64 = 1_000;
+
+fn account(owner: Principal, subaccount: Option<Vec<u8>>) -> mock_ledger::Account {
+    mock_ledger::Account { owner, subaccount }
+}
+
+fn balance(owner: Principal, subaccount: Option<Vec<u8>>) -> u64 {
+    with_ledger(|ledger| ledger.balance_of(&account(owner, subaccount)))
+}
+
+fn escrow_balance(report_id: u64) -> u64 {
+    balance(api::id(), Some(ledger::escrow_subaccount(report_id)))
+}
+
+fn treasury_balance() -> u64 {
+    balance(api::id(), None)
+}
+
+fn reviewer_pool_balance() -> u64 {
+    balance(api::id(), Some(ledger::reviewer_pool_subaccount()))
+}
+
+fn mint(owner: Principal, subaccount: Option<Vec<u8>>, amount: u64) {
+    with_ledger(|ledger| ledger.mint(&account(owner, subaccount), amount));
+}
+
+// Register a reporter with ledger tokens and an allowance for this canister
+fn add_reporter(reporter: Principal, allowance: u64) {
+    call_as(reporter);
+    register().expect("register");
+    mint(reporter, None, STARTING_BALANCE);
+
+    let args = ApproveArgs {
+        from_subaccount: None,
+        spender: account(api::id(), None),
+        amount: Nat::from(allowance),
+        expected_allowance: None,
+        expires_at: None,
+        fee: None,
+        memo: None,
+        created_at_time: None,
+    };
+    with_ledger(|ledger| ledger.approve(reporter, args)).expect("approve");
+}
+
+fn setup() {
+    store::set_ledger_config(LedgerConfig { ledger_canister_id: Some(LEDGER), fee: FEE, treasury_balance: None });
+    with_ledger(|ledger| *ledger = mock_ledger::Ledger::new(account(LEDGER, None), FEE));
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    add_reporter(REPORTER, STARTING_BALANCE);
+}
+
+// Send everything queued and check the treasury balance, as the timer would
+fn run_ledger_transfers() {
+    block_on(process_ledger_transfers());
+}
+
+fn transfer_statuses(report_id: u64) -> Vec<(LedgerTransferKind, LedgerTransferStatus)> {
+    store::get_ledger_transfers()
+        .into_iter()
+        .filter(|transfer| transfer.report_id == report_id)
+        .map(|transfer| (transfer.kind, transfer.status))
+        .collect()
+}
+
+fn assert_all_completed(report_id: u64) {
+    for (kind, status) in transfer_statuses(report_id) {
+        assert!(matches!(status, LedgerTransferStatus::Completed { .. }), "{:?} is {:?}", kind, status);
+    }
+}
+
+#[test]
+fn stake_moves_into_the_report_escrow() {
+    setup();
+
+    let report_id = submit(REPORTER, 50).unwrap();
+
+    assert_eq!(escrow_balance(report_id), 50);
+    // The approval and the stake transfer each cost the reporter a fee
+    assert_eq!(balance(REPORTER, None), STARTING_BALANCE - 2 * FEE - 50);
+    assert_eq!(store::get_user(REPORTER).unwrap().stakes_active, 50);
+    assert_books_balance();
+}
+
+#[test]
+fn stake_needs_an_allowance() {
+    setup();
+    add_reporter(OTHER_REPORTER, 10);
+
+    let error = submit(OTHER_REPORTER, 50).unwrap_err();
+
+    assert!(error.contains("Approve this canister"), "{}", error);
+    assert!(store::get_user_reports(OTHER_REPORTER).is_empty());
+    assert_eq!(balance(OTHER_REPORTER, None), STARTING_BALANCE - FEE);
+}
+
+#[test]
+fn verify_refunds_the_stake_and_pays_the_reward_from_the_treasury() {
+    setup();
+    mint(api::id(), None, 1_000);
+    run_ledger_transfers();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let before = balance(REPORTER, None);
+
+    call_as(AUTHORITY);
+    assert!(matches!(verify_report(report_id, None), Ok(ActionOutcome::Executed)));
+    // Nothing moves until the queued transfers are sent
+    assert_eq!(escrow_balance(report_id), 50);
+    run_ledger_transfers();
+
+    assert_all_completed(report_id);
+    assert_eq!(escrow_balance(report_id), 0);
+    // The refund pays its fee out of the escrowed stake; the reward's fee comes from the treasury
+    assert_eq!(balance(REPORTER, None), before + 50 - FEE + 500);
+    assert_eq!(treasury_balance(), 1_000 - 500 - FEE);
+    assert_eq!(store::get_ledger_config().treasury_balance, Some(1_000 - 500 - FEE));
+    assert_books_balance();
+}
+
+#[test]
+fn verify_checks_the_ledger_treasury() {
+    setup();
+    let first = submit(REPORTER, 50).unwrap();
+    let second = submit(REPORTER, 50).unwrap();
+    call_as(AUTHORITY);
+
+    let error = verify_report(first, None).unwrap_err();
+    assert!(error.contains("has not been checked"), "{}", error);
+
+    mint(api::id(), None, 100);
+    run_ledger_transfers();
+    let error = verify_report(first, None).unwrap_err();
+    assert!(error.contains("not enough"), "{}", error);
+
+    // A queued reward and its fee are spoken for until they are sent
+    mint(api::id(), None, 600);
+    run_ledger_transfers();
+    verify_report(first, None).unwrap();
+    let error = verify_report(second, None).unwrap_err();
+    assert!(error.contains("not enough"), "{}", error);
+
+    assert_eq!(report_status(first), ReportStatus::Approved);
+    assert_eq!(report_status(second), ReportStatus::Pending);
+}
+
+#[test]
+fn reject_splits_the_stake_per_the_slashing_policy() {
+    setup();
+    store::set_slashing_policy(SlashingPolicy {
+        burn_percent: 20,
+        treasury_percent: 50,
+        reviewer_pool_percent: 30,
+        soft_reject_refund_percent: 50,
+        withdrawal_refund_percent: 50,
+    });
+    let report_id = submit(REPORTER, 50).unwrap();
+    let supply = with_ledger(|ledger| ledger.total_supply());
+
+    call_as(AUTHORITY);
+    reject_report(report_id, None).unwrap();
+    run_ledger_transfers();
+
+    assert_all_completed(report_id);
+    assert_eq!(escrow_balance(report_id), 0);
+    // Shares sent between accounts pay their fee out of the escrow; the burn is free
+    assert_eq!(treasury_balance(), 25 - FEE);
+    assert_eq!(reviewer_pool_balance(), 15 - FEE);
+    assert_eq!(with_ledger(|ledger| ledger.total_supply()), supply - 10 - 2 * FEE);
+    assert_eq!(store::get_user(REPORTER).unwrap().stakes_lost, 50);
+    assert_books_balance();
+}
+
+#[test]
+fn reopen_puts_the_slashed_stake_back_in_escrow() {
+    setup();
+    store::set_slashing_policy(SlashingPolicy {
+        burn_percent: 20,
+        treasury_percent: 50,
+        reviewer_pool_percent: 30,
+        soft_reject_refund_percent: 50,
+        withdrawal_refund_percent: 50,
+    });
+    let report_id = submit(REPORTER, 50).unwrap();
+    call_as(AUTHORITY);
+    reject_report(report_id, None).unwrap();
+
+    let error = reopen_report(report_id, None).unwrap_err();
+    assert!(error.contains("still being moved"), "{}", error);
+
+    run_ledger_transfers();
+    mint(api::id(), None, 1_000);
+    reopen_report(report_id, None).unwrap();
+    run_ledger_transfers();
+
+    assert_all_completed(report_id);
+    assert_eq!(escrow_balance(report_id), 50);
+    assert_eq!(reviewer_pool_balance(), 0);
+    assert_eq!(report_status(report_id), ReportStatus::Reopened);
+    let reporter = store::get_user(REPORTER).unwrap();
+    assert_eq!((reporter.stakes_active, reporter.stakes_lost), (50, 0));
+
+    // The reinstated stake can be refunded in full once the report is verified
+    run_ledger_transfers();
+    verify_report(report_id, None).unwrap();
+    run_ledger_transfers();
+    assert_all_completed(report_id);
+    assert_eq!(escrow_balance(report_id), 0);
+    assert_books_balance();
+}
+
+#[test]
+fn withdrawal_refunds_half_the_stake() {
+    setup();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let before = balance(REPORTER, None);
+
+    assert_eq!(withdraw_report(report_id, None), Ok(25));
+    run_ledger_transfers();
+
+    assert_all_completed(report_id);
+    assert_eq!(escrow_balance(report_id), 0);
+    assert_eq!(balance(REPORTER, None), before + 25 - FEE);
+    assert_eq!(treasury_balance(), 25 - FEE);
+    assert_books_balance();
+}
+
+#[test]
+fn ledger_transfers_show_reporters_by_pseudonym() {
+    setup();
+    store::set_pseudonym_secret(vec![7; 32]);
+    mint(api::id(), None, 1_000);
+    run_ledger_transfers();
+    let report_id = submit(REPORTER, 50).unwrap();
+    call_as(AUTHORITY);
+    verify_report(report_id, None).unwrap();
+
+    let views = get_ledger_transfers().unwrap();
+
+    assert_eq!(views.len(), 2);
+    for view in views {
+        let pseudonym = store::reporter_pseudonym(report_id);
+        assert!(matches!(view.recipient, Participant::Reporter(ref shown) if *shown == pseudonym));
+        assert_hides_reporter(&view);
+    }
+
+    call_as(REPORTER);
+    assert!(get_ledger_transfers().is_err());
+}
+
+#[test]
+fn peer_transfers_and_grants_stay_on_the_ledger() {
+    setup();
+    add_reporter(OTHER_REPORTER, 0);
+    call_as(REPORTER);
+
+    assert!(transfer(OTHER_REPORTER, 10, None, "key".to_string()).is_err());
+    assert!(claim_onboarding_grant().is_err());
+    assert!(fund_treasury(10).is_err());
+}
@@ -0,0 +1,221 @@
+// Paged, filtered and sorted report listing over the secondary indexes
+
+use super::*;
+
+const CATEGORIES: [&str; 3] = ["fraud", "theft", "drugs"];
+const REPORT_COUNT: u64 = 12;
+
+// What a query's filters should select, checked against every stored report
+type ReportFilter = Box<dyn Fn(&Report) -> bool>;
+
+// File REPORT_COUNT reports a second apart, cycling through the categories with
+// stakes out of date order, then review and reject a few of them
+fn setup() -> Vec<Report> {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 10_000);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", 10_000);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+    
+    for index in 0..REPORT_COUNT {
+        call_as(if index % 4 == 0 { OTHER_REPORTER } else { REPORTER });
+        block_on(file_report(
+            format!("Report {}", index),
+            "Report description".to_string(),
+            CATEGORIES[index as usize % CATEGORIES.len()].to_string(),
+            None,
+            None,
+            5 + (index * 37) % 50,
+        ))
+        .unwrap();
+        runtime::advance_time(Duration::from_secs(1));
+    }
+    
+    call_as(AUTHORITY);
+    for report_id in [2, 5, 8] {
+        claim_report_for_review(report_id).unwrap();
+    }
+    reject_report(8, None).unwrap();
+    
+    // One report carries evidence
+    let mut with_evidence = store::get_report(4).unwrap();
+    with_evidence.evidence_files.push(1);
+    store::update_report(with_evidence).unwrap();
+    
+    store::get_all_reports()
+}
+
+fn query(page_size: u32) -> ReportQuery {
+    ReportQuery {
+        status: None,
+        category: None,
+        submitted_from: None,
+        submitted_to: None,
+        reviewer: None,
+        min_stake: None,
+        max_stake: None,
+        has_evidence: None,
+        sort_by: ReportSortKey::DateSubmitted,
+        direction: SortDirection::Ascending,
+        cursor: None,
+        page_size,
+    }
+}
+
+// Follow next_cursor to the end, returning every listed ID and the number of pages
+fn list_all(mut query: ReportQuery) -> (Vec<u64>, usize) {
+    let mut ids = Vec::new();
+    let mut pages = 0;
+    
+    loop {
+        let page = list_reports(query.clone()).unwrap();
+        pages += 1;
+        assert!(page.reports.len() <= query.page_size as usize);
+        ids.extend(page.reports.iter().map(|report| report.id));
+        
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => break,
+        }
+        assert!(pages <= REPORT_COUNT as usize + 1, "listing does not end");
+    }
+    
+    (ids, pages)
+}
+
+// IDs of the reports matching `filter`, in the order `key` sorts them
+fn expected(reports: &[Report], filter: impl Fn(&Report) -> bool, key: impl Fn(&Report) -> u64, descending: bool) -> Vec<u64> {
+    let mut matching: Vec<&Report> = reports.iter().filter(|report| filter(report)).collect();
+    matching.sort_by_key(|report| (key(report), report.id));
+    if descending {
+        matching.reverse();
+    }
+    matching.iter().map(|report| report.id).collect()
+}
+
+fn by_date(report: &Report) -> u64 {
+    report.date_submitted
+}
+
+#[test]
+fn paging_returns_every_report_exactly_once() {
+    let reports = setup();
+    
+    let (ids, pages) = list_all(query(5));
+    
+    assert_eq!(pages, 3);
+    assert_eq!(ids, expected(&reports, |_| true, by_date, false));
+    let mut unique = ids.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len() as u64, REPORT_COUNT);
+    
+    // A page size that divides the total still ends the listing
+    assert_eq!(list_all(query(4)).0, ids);
+    assert_eq!(list_all(query(REPORT_COUNT as u32)).0, ids);
+}
+
+#[test]
+fn listing_sorts_by_date_and_stake_in_both_directions() {
+    let reports = setup();
+    
+    for direction in [SortDirection::Ascending, SortDirection::Descending] {
+        let descending = direction == SortDirection::Descending;
+        
+        let date_order = ReportQuery { direction, ..query(5) };
+        assert_eq!(list_all(date_order).0, expected(&reports, |_| true, by_date, descending));
+        
+        let stake_order = ReportQuery { sort_by: ReportSortKey::StakeAmount, direction, ..query(5) };
+        assert_eq!(list_all(stake_order).0, expected(&reports, |_| true, |report| report.stake_amount, descending));
+    }
+}
+
+#[test]
+fn filters_combine() {
+    let reports = setup();
+    let start = reports[3].date_submitted;
+    let end = reports[9].date_submitted;
+    
+    let cases: Vec<(ReportQuery, ReportFilter)> = vec![
+        (
+            ReportQuery { status: Some(ReportStatus::Pending), ..query(2) },
+            Box::new(|report| report.status == ReportStatus::Pending),
+        ),
+        (
+            ReportQuery { category: Some("theft".to_string()), ..query(2) },
+            Box::new(|report| report.category == "theft"),
+        ),
+        (
+            ReportQuery { status: Some(ReportStatus::UnderReview), category: Some("theft".to_string()), ..query(2) },
+            Box::new(|report| report.status == ReportStatus::UnderReview && report.category == "theft"),
+        ),
+        (
+            ReportQuery { reviewer: Some(AUTHORITY), ..query(2) },
+            Box::new(|report| report.reviewer == Some(AUTHORITY)),
+        ),
+        (
+            ReportQuery { submitted_from: Some(start), submitted_to: Some(end), category: Some("fraud".to_string()), ..query(2) },
+            Box::new(move |report| (start..=end).contains(&report.date_submitted) && report.category == "fraud"),
+        ),
+        (
+            ReportQuery { min_stake: Some(20), max_stake: Some(40), status: Some(ReportStatus::Pending), ..query(2) },
+            Box::new(|report| (20..=40).contains(&report.stake_amount) && report.status == ReportStatus::Pending),
+        ),
+        (
+            ReportQuery { has_evidence: Some(true), ..query(2) },
+            Box::new(|report| !report.evidence_files.is_empty()),
+        ),
+        (
+            ReportQuery { has_evidence: Some(false), submitted_from: Some(start), ..query(2) },
+            Box::new(move |report| report.evidence_files.is_empty() && report.date_submitted >= start),
+        ),
+    ];
+    
+    for (filters, filter) in cases {
+        let matching = expected(&reports, &filter, by_date, false);
+        assert!(!matching.is_empty(), "{:?} matches nothing", filters);
+        
+        for direction in [SortDirection::Ascending, SortDirection::Descending] {
+            let listed = list_all(ReportQuery { direction, ..filters.clone() }).0;
+            assert_eq!(listed, expected(&reports, &filter, by_date, direction == SortDirection::Descending), "{:?}", filters);
+        }
+        
+        let stake_order = ReportQuery { sort_by: ReportSortKey::StakeAmount, ..filters.clone() };
+        assert_eq!(list_all(stake_order).0, expected(&reports, &filter, |report| report.stake_amount, false), "{:?}", filters);
+    }
+}
+
+#[test]
+fn listing_follows_status_changes() {
+    setup();
+    let rejected = ReportQuery { status: Some(ReportStatus::Rejected), ..query(5) };
+    assert_eq!(list_all(rejected.clone()).0, vec![8]);
+    
+    reopen_report(8, None).unwrap();
+    
+    assert!(list_all(rejected).0.is_empty());
+    assert_eq!(list_all(ReportQuery { status: Some(ReportStatus::Reopened), ..query(5) }).0, vec![8]);
+}
+
+#[test]
+fn listing_needs_view_permission() {
+    setup();
+    
+    call_as(REPORTER);
+    assert!(list_reports(query(5)).is_err());
+}
+
+#[test]
+fn upgrade_rebuilds_indexes_from_an_older_schema() {
+    let reports = setup();
+    store::set_index_schema_version(0);
+    
+    post_upgrade();
+    
+    assert_eq!(store::get_index_schema_version(), store::INDEX_SCHEMA_VERSION);
+    assert_eq!(list_all(query(5)).0, expected(&reports, |_| true, by_date, false));
+    let pending = ReportQuery { status: Some(ReportStatus::Pending), ..query(5) };
+    let expected_pending = expected(&reports, |report| report.status == ReportStatus::Pending, by_date, false);
+    assert_eq!(list_all(pending).0, expected_pending);
+    assert_eq!(store::get_user_reports(OTHER_REPORTER).len(), 3);
+}
@@ -0,0 +1,120 @@
+// End-to-end encrypted messages: key registration and rotation, and recipient checks
+
+use super::*;
+
+fn setup() -> u64 {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    submit(REPORTER, 10).unwrap()
+}
+
+fn current_key_ids(report_id: u64) -> Vec<u64> {
+    call_as(AUTHORITY);
+    get_message_keys(report_id).unwrap().iter().map(|key| key.id).collect()
+}
+
+#[test]
+fn rotated_keys_stay_readable_but_cannot_be_used_for_new_messages() {
+    let report_id = setup();
+    let (old_reporter_key, old_authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    
+    call_as(REPORTER);
+    send_message_as_reporter(report_id, encrypted(old_reporter_key, &[old_authority_key]), None).unwrap();
+    
+    // Both sides rotate
+    runtime::advance_time(Duration::from_secs(60));
+    let (new_reporter_key, new_authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    assert_eq!(current_key_ids(report_id), vec![new_reporter_key, new_authority_key]);
+    
+    // Old keys can still be looked up to decrypt earlier messages, marked as superseded
+    for key_id in [old_reporter_key, old_authority_key] {
+        let key = get_public_key(key_id).unwrap();
+        assert_eq!(key.superseded_at, Some(api::time()));
+    }
+    assert_eq!(get_public_key(new_reporter_key).unwrap().superseded_at, None);
+    let messages = get_messages(report_id);
+    let earlier_content = messages.iter().find_map(|message| message.encrypted.as_ref()).unwrap();
+    assert_eq!(earlier_content.sender_key_id, old_reporter_key);
+    assert_eq!(earlier_content.wrapped_keys[0].key_id, old_authority_key);
+    
+    // New messages must use the current keys
+    call_as(REPORTER);
+    let error = send_message_as_reporter(report_id, encrypted(old_reporter_key, &[new_authority_key]), None).unwrap_err();
+    assert!(error.contains("not your current messaging key"), "{}", error);
+    let error = send_message_as_reporter(report_id, encrypted(new_reporter_key, &[old_authority_key]), None).unwrap_err();
+    assert!(error.contains("fetch the keys again"), "{}", error);
+    send_message_as_reporter(report_id, encrypted(new_reporter_key, &[new_authority_key]), None).unwrap();
+    
+    call_as(AUTHORITY);
+    let error = send_message_as_authority(report_id, encrypted(new_authority_key, &[old_reporter_key]), None).unwrap_err();
+    assert!(error.contains("fetch the keys again"), "{}", error);
+    send_message_as_authority(report_id, encrypted(new_authority_key, &[new_reporter_key]), None).unwrap();
+    assert_eq!(get_messages(report_id).iter().filter(|message| message.encrypted.is_some()).count(), 3);
+}
+
+#[test]
+fn messages_must_be_readable_by_the_other_side() {
+    let report_id = setup();
+    
+    call_as(REPORTER);
+    let error = send_message_as_reporter(report_id, encrypted(1, &[1]), None).unwrap_err();
+    assert!(error.contains("Register a messaging key"), "{}", error);
+    
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    
+    call_as(REPORTER);
+    let error = send_message_as_reporter(report_id, encrypted(reporter_key, &[reporter_key]), None).unwrap_err();
+    assert!(error.contains("at least one authority key"), "{}", error);
+    let error = send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key, authority_key]), None).unwrap_err();
+    assert!(error.contains("more than once"), "{}", error);
+    assert!(send_message_as_reporter(report_id, encrypted(reporter_key, &[]), None).is_err());
+    let oversized = EncryptedContent { ciphertext: vec![0; MAX_MESSAGE_CIPHERTEXT_SIZE + 1], ..encrypted(reporter_key, &[authority_key]) };
+    assert!(send_message_as_reporter(report_id, oversized, None).is_err());
+    
+    call_as(AUTHORITY);
+    let error = send_message_as_authority(report_id, encrypted(authority_key, &[authority_key]), None).unwrap_err();
+    assert!(error.contains("reporter's current key"), "{}", error);
+    
+    // Only the submitter registers keys and sends messages as the reporter
+    call_as(OTHER_REPORTER);
+    assert!(register_reporter_key(report_id, "x25519".to_string(), vec![1; 32]).is_err());
+    assert!(send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).is_err());
+    assert!(get_messages(report_id).is_empty());
+}
+
+#[test]
+fn keys_are_validated() {
+    let report_id = setup();
+    
+    call_as(REPORTER);
+    assert!(register_reporter_key(report_id, " ".to_string(), vec![1; 32]).is_err());
+    assert!(register_reporter_key(report_id, "x25519".to_string(), Vec::new()).is_err());
+    assert!(register_reporter_key(report_id, "x25519".to_string(), vec![1; MAX_PUBLIC_KEY_SIZE + 1]).is_err());
+    
+    // Authorities need a role that can read reports
+    assert!(register_authority_key("x25519".to_string(), vec![2; 32]).is_err());
+    assert!(current_key_ids(report_id).is_empty());
+}
+
+#[test]
+fn polling_returns_messages_after_a_cursor() {
+    let report_id = setup();
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    
+    call_as(REPORTER);
+    for _ in 0..5 {
+        send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).unwrap();
+    }
+    let all: Vec<u64> = get_messages(report_id).iter().map(|message| message.id).collect();
+    
+    let ids = |after_id, limit| -> Vec<u64> {
+        get_messages_since(report_id, after_id, limit).unwrap().iter().map(|message| message.id).collect()
+    };
+    assert_eq!(ids(0, 2), all[..2]);
+    assert_eq!(ids(all[1], 100), all[2..]);
+    assert!(ids(*all.last().unwrap(), 10).is_empty());
+    
+    call_as(OTHER_REPORTER);
+    assert!(get_messages_since(report_id, 0, 10).is_err());
+}
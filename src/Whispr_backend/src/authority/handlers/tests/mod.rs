@@ -0,0 +1,95 @@
+// Handler tests. Each test runs on its own thread, so it starts from empty stable
+// memory and a fresh stand-in ledger.
+
+use super::*;
+use futures::executor::block_on;
+
+mod attachments;
+mod categories;
+mod evidence_keys;
+mod flows;
+mod governance;
+mod journal;
+mod ledger_mode;
+mod listing;
+mod messaging;
+mod notifications;
+mod onboarding;
+mod permissions;
+mod privacy;
+mod read_cursors;
+mod reward_config;
+mod transfers;
+mod treasury;
+mod uploads;
+
+const AUTHORITY: Principal = Principal::from_slice(&[1]);
+const REPORTER: Principal = Principal::from_slice(&[2]);
+const OTHER_REPORTER: Principal = Principal::from_slice(&[3]);
+
+fn add_admin(id: Principal) {
+    store::add_authority(Authority {
+        id,
+        reports_reviewed: Vec::new(),
+        approval_rate: 0.0,
+        roles: vec![AuthorityRole::Admin],
+    });
+}
+
+// Make the next handler call as `id`
+fn call_as(id: Principal) {
+    runtime::set_caller(id);
+}
+
+fn submit(reporter: Principal, stake_amount: u64) -> Result<u64, String> {
+    call_as(reporter);
+    block_on(file_report(
+        "Report title".to_string(),
+        "Report description".to_string(),
+        "fraud".to_string(),
+        None,
+        None,
+        stake_amount,
+    ))
+}
+
+fn report_status(report_id: u64) -> ReportStatus {
+    store::get_report(report_id).expect("report exists").status
+}
+
+fn assert_books_balance() {
+    let invariants = store::check_ledger_invariants();
+    assert!(invariants.violations.is_empty(), "{:?}", invariants.violations);
+}
+
+// Whether the Debug form of `value` holds REPORTER's principal
+fn names_reporter(value: &impl std::fmt::Debug) -> bool {
+    format!("{:?}", value).contains(&format!("{:?}", REPORTER))
+}
+
+fn assert_hides_reporter(value: &impl std::fmt::Debug) {
+    assert!(!names_reporter(value), "{:?}", value);
+}
+
+// Register messaging keys for a report's reporter and for an authority
+fn register_keys(report_id: u64, reporter: Principal, authority: Principal) -> (u64, u64) {
+    call_as(reporter);
+    let reporter_key = register_reporter_key(report_id, "x25519".to_string(), vec![1; 32]).unwrap();
+    call_as(authority);
+    let authority_key = register_authority_key("x25519".to_string(), vec![2; 32]).unwrap();
+    (reporter_key, authority_key)
+}
+
+// A message body from `sender_key_id`, wrapped for each of `recipient_key_ids`
+fn encrypted(sender_key_id: u64, recipient_key_ids: &[u64]) -> EncryptedContent {
+    EncryptedContent {
+        algorithm: "x25519-aes-gcm".to_string(),
+        sender_key_id,
+        nonce: vec![0; 12],
+        ciphertext: vec![3; 16],
+        wrapped_keys: recipient_key_ids
+            .iter()
+            .map(|key_id| WrappedKey { key_id: *key_id, wrapped_key: vec![4; 32] })
+            .collect(),
+    }
+}
@@ -0,0 +1,115 @@
+// Notification inboxes: lifecycle events, muted kinds, read flags and capacity
+
+use super::*;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+}
+
+fn inbox(id: Principal, unread_only: bool) -> Vec<Notification> {
+    call_as(id);
+    get_notifications(0, unread_only, 100)
+}
+
+fn kinds(id: Principal) -> Vec<NotificationKind> {
+    inbox(id, false).iter().map(|notification| notification.event.kind()).collect()
+}
+
+fn mute(id: Principal, muted: Vec<NotificationKind>) {
+    call_as(id);
+    set_notification_preferences(NotificationPreferences { muted }).unwrap();
+}
+
+#[test]
+fn lifecycle_events_reach_both_sides() {
+    setup();
+    let report_id = submit(REPORTER, 10).unwrap();
+    assert_eq!(kinds(AUTHORITY), vec![NotificationKind::NewReport]);
+    assert!(kinds(REPORTER).is_empty());
+    
+    call_as(AUTHORITY);
+    verify_report(report_id, None).unwrap();
+    
+    assert_eq!(kinds(REPORTER), vec![NotificationKind::StatusChanged, NotificationKind::RewardCredited]);
+    match &inbox(REPORTER, false)[1].event {
+        NotificationEvent::RewardCredited { report_id: credited, amount } => assert_eq!((*credited, *amount), (report_id, 100)),
+        other => panic!("expected a reward, got {:?}", other),
+    }
+    
+    // The acting authority is not told about their own action
+    assert_eq!(kinds(AUTHORITY), vec![NotificationKind::NewReport]);
+}
+
+#[test]
+fn muted_kinds_are_not_delivered() {
+    setup();
+    mute(REPORTER, vec![NotificationKind::StatusChanged, NotificationKind::StatusChanged]);
+    call_as(REPORTER);
+    assert_eq!(get_notification_preferences().muted, vec![NotificationKind::StatusChanged]);
+    
+    let first = submit(REPORTER, 10).unwrap();
+    call_as(AUTHORITY);
+    verify_report(first, None).unwrap();
+    assert_eq!(kinds(REPORTER), vec![NotificationKind::RewardCredited]);
+    
+    // Preferences are per principal, and unmuting applies to later events only
+    mute(AUTHORITY, vec![NotificationKind::NewReport]);
+    mute(REPORTER, Vec::new());
+    let second = submit(REPORTER, 10).unwrap();
+    assert_eq!(kinds(AUTHORITY), vec![NotificationKind::NewReport]);
+    call_as(AUTHORITY);
+    reject_report(second, None).unwrap();
+    assert_eq!(kinds(REPORTER), vec![NotificationKind::RewardCredited, NotificationKind::StatusChanged, NotificationKind::StakeLost]);
+    
+    call_as(Principal::anonymous());
+    assert!(set_notification_preferences(NotificationPreferences { muted: Vec::new() }).is_err());
+}
+
+#[test]
+fn notifications_are_marked_read_by_their_recipient() {
+    setup();
+    submit(REPORTER, 10).unwrap();
+    submit(REPORTER, 10).unwrap();
+    let notifications = inbox(AUTHORITY, true);
+    assert_eq!(notifications.len(), 2);
+    
+    call_as(REPORTER);
+    assert_eq!(mark_notification_read(notifications[0].id).unwrap_err(), "Notification not found");
+    
+    call_as(AUTHORITY);
+    mark_notification_read(notifications[0].id).unwrap();
+    mark_notification_read(notifications[0].id).unwrap();
+    let unread: Vec<u64> = inbox(AUTHORITY, true).iter().map(|notification| notification.id).collect();
+    assert_eq!(unread, vec![notifications[1].id]);
+    assert_eq!(inbox(AUTHORITY, false).len(), 2);
+    
+    // Paging continues after the last ID seen
+    call_as(AUTHORITY);
+    assert_eq!(get_notifications(notifications[0].id, false, 10).len(), 1);
+}
+
+#[test]
+fn full_inboxes_drop_the_oldest_entries() {
+    setup();
+    for report_id in 1..=5 {
+        let notification = Notification {
+            id: 0,
+            event: NotificationEvent::NewReport { report_id, category: "fraud".to_string() },
+            created_at: 0,
+            read: false,
+        };
+        store::add_notification(AUTHORITY, &notification, 3);
+    }
+    
+    let kept: Vec<u64> = inbox(AUTHORITY, false)
+        .iter()
+        .map(|notification| match notification.event {
+            NotificationEvent::NewReport { report_id, .. } => report_id,
+            _ => 0,
+        })
+        .collect();
+    assert_eq!(kept, vec![3, 4, 5]);
+}
@@ -0,0 +1,187 @@
+// Onboarding grants: single-use invite codes, open grants, the daily budget and the cooldown
+
+use super::*;
+
+const FIRST: Principal = Principal::from_slice(&[30]);
+const SECOND: Principal = Principal::from_slice(&[31]);
+const THIRD: Principal = Principal::from_slice(&[32]);
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn setup(config: OnboardingConfig) {
+    add_admin(AUTHORITY);
+    store::set_onboarding_config(config);
+}
+
+fn config(open_grants: bool, daily_grant_budget: u64, grant_cooldown_ns: Option<u64>) -> OnboardingConfig {
+    OnboardingConfig {
+        grant_amount: 100,
+        open_grants,
+        daily_grant_budget,
+        grant_cooldown_ns,
+        max_voucher_amount: 100,
+    }
+}
+
+fn issue(count: u32, amount: u64, expires_at: Option<u64>) -> Vec<String> {
+    call_as(AUTHORITY);
+    block_on(issue_vouchers(count, amount, expires_at)).unwrap()
+}
+
+fn redeem_as(id: Principal, code: &str) -> Result<u64, String> {
+    call_as(id);
+    redeem_voucher(code.to_string())
+}
+
+fn claim_as(id: Principal) -> Result<u64, String> {
+    call_as(id);
+    claim_onboarding_grant()
+}
+
+fn balance(id: Principal) -> u64 {
+    store::get_user(id).map_or(0, |user| user.token_balance)
+}
+
+#[test]
+fn invite_codes_redeem_once() {
+    setup(config(false, 10_000, None));
+    let codes = issue(2, 50, None);
+    assert_ne!(codes[0], codes[1]);
+    
+    assert_eq!(redeem_as(FIRST, &codes[0]), Ok(50));
+    
+    // Neither the redeemer nor anyone else can use the code again
+    let error = redeem_as(FIRST, &codes[0]).unwrap_err();
+    assert!(error.contains("already been used"), "{}", error);
+    let error = redeem_as(SECOND, &codes[0]).unwrap_err();
+    assert!(error.contains("already been used"), "{}", error);
+    assert_eq!((balance(FIRST), balance(SECOND)), (50, 0));
+    
+    // Codes are matched without dashes, spaces or case
+    let loose = codes[1].replace('-', " ").to_lowercase();
+    assert_eq!(redeem_as(SECOND, &loose), Ok(50));
+    
+    let vouchers = {
+        call_as(AUTHORITY);
+        get_my_vouchers().unwrap()
+    };
+    assert_eq!(vouchers.iter().map(|voucher| voucher.redeemed_by).collect::<Vec<_>>(), vec![Some(FIRST), Some(SECOND)]);
+    assert_books_balance();
+}
+
+#[test]
+fn unknown_codes_and_anonymous_callers_are_refused() {
+    setup(config(false, 10_000, None));
+    issue(1, 50, None);
+    
+    let error = redeem_as(FIRST, "0000-0000-0000-0000").unwrap_err();
+    assert_eq!(error, "Invalid invite code");
+    assert!(redeem_as(Principal::anonymous(), "0000-0000-0000-0000").is_err());
+    
+    // Grants need an invite code unless open grants are on
+    let error = claim_as(FIRST).unwrap_err();
+    assert!(error.contains("invite code"), "{}", error);
+    assert!(store::get_user(FIRST).is_none());
+}
+
+#[test]
+fn invite_codes_expire() {
+    setup(config(false, 10_000, None));
+    let expires_at = api::time() + HOUR.as_nanos() as u64;
+    let codes = issue(2, 50, Some(expires_at));
+    
+    assert_eq!(redeem_as(FIRST, &codes[0]), Ok(50));
+    runtime::advance_time(HOUR);
+    let error = redeem_as(SECOND, &codes[1]).unwrap_err();
+    assert!(error.contains("expired"), "{}", error);
+    assert_eq!(balance(SECOND), 0);
+    
+    // Codes cannot be issued already expired
+    call_as(AUTHORITY);
+    let error = block_on(issue_vouchers(1, 50, Some(api::time()))).unwrap_err();
+    assert!(error.contains("future"), "{}", error);
+}
+
+#[test]
+fn issuing_is_bounded() {
+    setup(config(false, 10_000, None));
+    call_as(AUTHORITY);
+    
+    assert!(block_on(issue_vouchers(0, 50, None)).is_err());
+    assert!(block_on(issue_vouchers(MAX_VOUCHERS_PER_CALL + 1, 50, None)).is_err());
+    let error = block_on(issue_vouchers(1, 101, None)).unwrap_err();
+    assert!(error.contains("between 1 and 100"), "{}", error);
+    assert!(block_on(issue_vouchers(1, 0, None)).is_err());
+    assert!(get_my_vouchers().unwrap().is_empty());
+}
+
+#[test]
+fn grants_stop_at_the_daily_budget() {
+    setup(config(true, 150, None));
+    let codes = issue(1, 60, None);
+    
+    assert_eq!(claim_as(FIRST), Ok(100));
+    let error = claim_as(SECOND).unwrap_err();
+    assert!(error.contains("budget"), "{}", error);
+    
+    // Invite codes draw on the same budget, and a refused code stays usable
+    let error = redeem_as(THIRD, &codes[0]).unwrap_err();
+    assert!(error.contains("budget"), "{}", error);
+    assert!(store::get_user(SECOND).is_none() && store::get_user(THIRD).is_none());
+    
+    // The budget resets with the UTC day
+    runtime::advance_time(Duration::from_nanos(store::DAY_NS));
+    assert_eq!(claim_as(SECOND), Ok(100));
+    assert_eq!(redeem_as(THIRD, &codes[0]), Err("Today's onboarding budget has been used up; try again tomorrow".to_string()));
+    runtime::advance_time(Duration::from_nanos(store::DAY_NS));
+    assert_eq!(redeem_as(THIRD, &codes[0]), Ok(60));
+    assert_books_balance();
+}
+
+#[test]
+fn open_grants_are_given_once_without_a_cooldown() {
+    setup(config(true, 10_000, None));
+    
+    assert_eq!(claim_as(FIRST), Ok(100));
+    runtime::advance_time(Duration::from_nanos(30 * store::DAY_NS));
+    let error = claim_as(FIRST).unwrap_err();
+    assert!(error.contains("already received"), "{}", error);
+    assert_eq!(balance(FIRST), 100);
+}
+
+#[test]
+fn open_grants_wait_out_the_cooldown() {
+    setup(config(true, 10_000, Some(HOUR.as_nanos() as u64)));
+    let codes = issue(1, 50, None);
+    
+    assert_eq!(claim_as(FIRST), Ok(100));
+    runtime::advance_time(HOUR / 2);
+    let error = claim_as(FIRST).unwrap_err();
+    assert!(error.contains("recently"), "{}", error);
+    
+    // Invite codes are single-use, so they skip the cooldown, but they still restart it
+    assert_eq!(redeem_as(FIRST, &codes[0]), Ok(150));
+    runtime::advance_time(HOUR / 2);
+    assert!(claim_as(FIRST).is_err());
+    
+    runtime::advance_time(HOUR / 2);
+    assert_eq!(claim_as(FIRST), Ok(250));
+    assert_books_balance();
+}
+
+#[test]
+fn onboarding_config_is_validated() {
+    setup(config(false, 10_000, None));
+    call_as(AUTHORITY);
+    
+    let error = update_onboarding_config(config(true, 50, None)).unwrap_err();
+    assert!(error.contains("daily grant budget"), "{}", error);
+    let error = update_onboarding_config(OnboardingConfig { grant_amount: 0, ..config(true, 10_000, None) }).unwrap_err();
+    assert!(error.contains("at least 1"), "{}", error);
+    
+    let updated = update_onboarding_config(config(true, 500, Some(1))).unwrap();
+    assert_eq!(store::get_onboarding_config().daily_grant_budget, updated.daily_grant_budget);
+    
+    call_as(FIRST);
+    assert!(update_onboarding_config(config(true, 10_000, None)).is_err());
+}
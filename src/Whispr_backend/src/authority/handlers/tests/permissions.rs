@@ -0,0 +1,179 @@
+// The role permission matrix, checked through the handlers at each role boundary
+
+use super::*;
+use candid::CandidType;
+use ic_stable_structures::Storable;
+use std::borrow::Cow;
+
+const MEMBER: Principal = Principal::from_slice(&[20]);
+const NEWCOMER: Principal = Principal::from_slice(&[21]);
+
+fn setup(roles: Vec<AuthorityRole>) -> u64 {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+    store::add_authority(Authority { id: MEMBER, reports_reviewed: Vec::new(), approval_rate: 0.0, roles });
+    
+    let report_id = submit(REPORTER, 50).unwrap();
+    call_as(MEMBER);
+    report_id
+}
+
+fn assert_lacks(result: Result<impl std::fmt::Debug, String>, permission: Permission) {
+    let error = result.unwrap_err();
+    assert_eq!(error, format!("Caller lacks the {:?} permission", permission));
+}
+
+fn category() -> Category {
+    Category {
+        id: "wildlife".to_string(),
+        name: "Wildlife".to_string(),
+        description: "Poaching and trafficking".to_string(),
+        parent_id: None,
+        min_stake_amount: None,
+        max_stake_amount: None,
+        active: true,
+    }
+}
+
+#[test]
+fn reviewer_reviews_and_verifies_but_cannot_administer() {
+    let report_id = setup(vec![AuthorityRole::Reviewer]);
+    
+    claim_report_for_review(report_id).unwrap();
+    assert!(matches!(verify_report(report_id, None), Ok(ActionOutcome::Executed)));
+    
+    assert_lacks(add_new_authority(NEWCOMER), Permission::ManageAuthorities);
+    assert_lacks(update_reward_config(store::get_reward_config()), Permission::ManageRewardConfig);
+    assert_lacks(create_category(category()), Permission::ManageCategories);
+    assert_lacks(get_audit_log(0, 10), Permission::ViewAuditLog);
+    assert_lacks(get_treasury_report(api::time(), api::time()), Permission::ViewFinances);
+    assert_lacks(reset_to_mock_data(), Permission::ResetData);
+    assert_lacks(get_my_vouchers(), Permission::IssueVouchers);
+}
+
+#[test]
+fn investigator_cannot_verify() {
+    let report_id = setup(vec![AuthorityRole::Investigator]);
+    
+    assert_eq!(get_report(report_id).len(), 1);
+    claim_report_for_review(report_id).unwrap();
+    escalate_report(report_id, None).unwrap();
+    
+    assert_lacks(verify_report(report_id, None), Permission::VerifyReports);
+    assert_lacks(get_authority_statistics(), Permission::ViewStatistics);
+    assert_eq!(report_status(report_id), ReportStatus::Escalated);
+    
+    // Rejecting is part of review, so investigators can still turn a report down
+    reject_report(report_id, None).unwrap();
+}
+
+#[test]
+fn auditor_reads_but_cannot_act() {
+    let report_id = setup(vec![AuthorityRole::Auditor]);
+    
+    assert_eq!(get_report(report_id).len(), 1);
+    assert!(!get_audit_log(0, 10).unwrap().is_empty());
+    assert!(verify_audit_chain().unwrap().valid);
+    get_treasury_report(api::time(), api::time()).unwrap();
+    get_authority_statistics().unwrap();
+    
+    assert_lacks(claim_report_for_review(report_id), Permission::ReviewReports);
+    assert_lacks(verify_report(report_id, None), Permission::VerifyReports);
+    assert_lacks(update_reward_config(store::get_reward_config()), Permission::ManageRewardConfig);
+    assert_lacks(send_message_as_authority(report_id, encrypted(1, &[1]), None), Permission::MessageReporters);
+}
+
+#[test]
+fn finance_officer_manages_rewards_but_cannot_review() {
+    let report_id = setup(vec![AuthorityRole::FinanceOfficer]);
+    
+    update_reward_config(store::get_reward_config()).unwrap();
+    update_slashing_policy(store::get_slashing_policy()).unwrap();
+    get_treasury_report(api::time(), api::time()).unwrap();
+    
+    assert_lacks(claim_report_for_review(report_id), Permission::ReviewReports);
+    assert_lacks(get_audit_log(0, 10), Permission::ViewAuditLog);
+    assert_lacks(add_new_authority(NEWCOMER), Permission::ManageAuthorities);
+    assert_lacks(create_category(category()), Permission::ManageCategories);
+}
+
+#[test]
+fn partner_can_only_issue_vouchers() {
+    let report_id = setup(vec![AuthorityRole::Partner]);
+    
+    let codes = block_on(issue_vouchers(2, 10, None)).unwrap();
+    assert_eq!(codes.len(), 2);
+    assert_eq!(get_my_vouchers().unwrap().len(), 2);
+    
+    assert_lacks(get_all_reports(), Permission::ViewReports);
+    assert!(get_report(report_id).is_empty());
+    assert!(get_messages(report_id).is_empty());
+    assert_eq!(get_report_history(report_id).unwrap_err(), "You are not allowed to view this report");
+    assert_lacks(claim_report_for_review(report_id), Permission::ReviewReports);
+    assert_lacks(get_authority_statistics(), Permission::ViewStatistics);
+    assert_lacks(get_audit_log(0, 10), Permission::ViewAuditLog);
+    assert_lacks(get_treasury_report(api::time(), api::time()), Permission::ViewFinances);
+    assert_lacks(update_reward_config(store::get_reward_config()), Permission::ManageRewardConfig);
+    assert_lacks(add_new_authority(NEWCOMER), Permission::ManageAuthorities);
+}
+
+#[test]
+fn admin_holds_every_permission() {
+    let report_id = setup(vec![AuthorityRole::Admin]);
+    
+    create_category(category()).unwrap();
+    update_reward_config(store::get_reward_config()).unwrap();
+    get_audit_log(0, 10).unwrap();
+    get_treasury_report(api::time(), api::time()).unwrap();
+    block_on(issue_vouchers(1, 10, None)).unwrap();
+    claim_report_for_review(report_id).unwrap();
+    assert!(matches!(verify_report(report_id, None), Ok(ActionOutcome::Executed)));
+}
+
+#[test]
+fn roles_combine_their_permissions() {
+    let report_id = setup(vec![AuthorityRole::Investigator, AuthorityRole::Partner]);
+    
+    claim_report_for_review(report_id).unwrap();
+    block_on(issue_vouchers(1, 10, None)).unwrap();
+    assert_lacks(verify_report(report_id, None), Permission::VerifyReports);
+}
+
+#[test]
+fn non_authorities_hold_no_permissions() {
+    let report_id = setup(Vec::new());
+    
+    assert_lacks(get_all_reports(), Permission::ViewReports);
+    call_as(REPORTER);
+    assert_eq!(get_all_reports().unwrap_err(), "Caller is not an authorized authority");
+    assert_eq!(block_on(issue_vouchers(1, 10, None)).unwrap_err(), "Caller is not an authorized authority");
+    call_as(Principal::anonymous());
+    assert_eq!(verify_report(report_id, None).unwrap_err(), "Anonymous callers are not allowed");
+}
+
+// Same shape as an authority record written before roles existed
+#[derive(CandidType)]
+struct RolelessAuthority {
+    id: Principal,
+    reports_reviewed: Vec<u64>,
+    approval_rate: f64,
+}
+
+#[test]
+fn authority_stored_before_roles_decodes_as_admin() {
+    let stored = RolelessAuthority { id: MEMBER, reports_reviewed: vec![3, 4], approval_rate: 0.5 };
+    let bytes = candid::encode_one(&stored).unwrap();
+    
+    let authority = Authority::from_bytes(Cow::Owned(bytes));
+    
+    assert_eq!(authority.id, MEMBER);
+    assert_eq!(authority.reports_reviewed, vec![3, 4]);
+    assert_eq!(authority.approval_rate, 0.5);
+    assert_eq!(authority.roles, vec![AuthorityRole::Admin]);
+    
+    // Records with roles keep them
+    let reviewer = Authority { roles: vec![AuthorityRole::Reviewer], ..authority };
+    assert_eq!(Authority::from_bytes(reviewer.to_bytes()).roles, vec![AuthorityRole::Reviewer]);
+}
@@ -0,0 +1,74 @@
+// Authority-facing views show reporters by per-report pseudonym, never by principal
+
+use super::*;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+    store::set_pseudonym_secret(vec![7; 32]);
+}
+
+#[test]
+fn authority_views_never_show_the_reporter() {
+    setup();
+    let report_id = submit(REPORTER, 50).unwrap();
+    let withdrawn = submit(REPORTER, 50).unwrap();
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    
+    call_as(REPORTER);
+    send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).unwrap();
+    withdraw_report(withdrawn, Some("changed my mind".to_string())).unwrap();
+    call_as(AUTHORITY);
+    send_message_as_authority(report_id, encrypted(authority_key, &[reporter_key]), None).unwrap();
+    claim_report_for_review(report_id).unwrap();
+    verify_report(report_id, Some("confirmed".to_string())).unwrap();
+    
+    // The stored records name the reporter, so the checks below would catch a leak
+    assert!(names_reporter(&store::get_report_messages(report_id)));
+    
+    for id in [report_id, withdrawn] {
+        let views = get_report(id);
+        assert_eq!(views.len(), 1);
+        assert_hides_reporter(&views);
+        assert_hides_reporter(&get_messages(id));
+        assert_hides_reporter(&get_report_history(id).unwrap());
+    }
+    
+    // The reporter's own messages and status changes are attributed to the pseudonym
+    let pseudonym = store::reporter_pseudonym(report_id);
+    let messages = get_messages(report_id);
+    assert!(messages.iter().any(|message| matches!(message.sender, Participant::Reporter(ref shown) if *shown == pseudonym)));
+    let history = get_report_history(withdrawn).unwrap();
+    let withdrawn_pseudonym = store::reporter_pseudonym(withdrawn);
+    assert!(history.iter().any(|change| matches!(change.actor, Participant::Reporter(ref shown) if *shown == withdrawn_pseudonym)));
+    
+    // The audit log holds the reporter's principal but never hands it out
+    let stored = store::get_audit_entries(0, 1_000);
+    assert!(stored.iter().any(|entry| entry.caller == REPORTER));
+    let log = get_audit_log(0, 1_000).unwrap();
+    assert_eq!(log.len(), stored.len());
+    assert_hides_reporter(&log);
+}
+
+#[test]
+fn pseudonyms_are_stable_per_report_and_differ_across_reports() {
+    setup();
+    let first = submit(REPORTER, 50).unwrap();
+    let second = submit(REPORTER, 50).unwrap();
+    call_as(AUTHORITY);
+    
+    let pseudonym = get_report(first)[0].reporter.clone();
+    assert_eq!(get_report(first)[0].reporter, pseudonym);
+    assert_eq!(store::reporter_pseudonym(first), pseudonym);
+    claim_report_for_review(first).unwrap();
+    assert_eq!(get_report(first)[0].reporter, pseudonym);
+    
+    // Two reports by the same person cannot be linked by their pseudonyms
+    assert_ne!(get_report(second)[0].reporter, pseudonym);
+    
+    // The pseudonym depends on the secret, so it cannot be recomputed from the report ID alone
+    store::set_pseudonym_secret(vec![8; 32]);
+    assert_ne!(store::reporter_pseudonym(first), pseudonym);
+}
@@ -0,0 +1,84 @@
+// Per-participant read cursors, unread counts and read receipts
+
+use super::*;
+
+fn setup() -> (u64, u64, u64) {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    let report_id = submit(REPORTER, 10).unwrap();
+    let (reporter_key, authority_key) = register_keys(report_id, REPORTER, AUTHORITY);
+    (report_id, reporter_key, authority_key)
+}
+
+fn reporter_unread(report_id: u64) -> u64 {
+    call_as(REPORTER);
+    get_my_report(report_id)[0].unread_messages
+}
+
+fn latest_message_id(report_id: u64) -> u64 {
+    store::get_last_message_id(report_id).unwrap()
+}
+
+#[test]
+fn unread_counts_skip_own_messages_and_follow_the_cursor() {
+    let (report_id, reporter_key, authority_key) = setup();
+    let reporter_before = reporter_unread(report_id);
+    let authority_before = store::count_unread_messages(report_id, AUTHORITY);
+    
+    call_as(REPORTER);
+    send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).unwrap();
+    send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).unwrap();
+    assert_eq!(reporter_unread(report_id), reporter_before);
+    assert_eq!(store::count_unread_messages(report_id, AUTHORITY), authority_before + 2);
+    
+    call_as(AUTHORITY);
+    let latest = latest_message_id(report_id);
+    assert_eq!(mark_messages_read(report_id, latest - 1), Ok(latest - 1));
+    assert_eq!(store::count_unread_messages(report_id, AUTHORITY), 1);
+    assert_eq!(mark_messages_read(report_id, latest), Ok(latest));
+    assert_eq!(store::count_unread_messages(report_id, AUTHORITY), 0);
+    
+    // A reply is unread for the reporter only
+    send_message_as_authority(report_id, encrypted(authority_key, &[reporter_key]), None).unwrap();
+    assert_eq!(reporter_unread(report_id), reporter_before + 1);
+    assert_eq!(store::count_unread_messages(report_id, AUTHORITY), 0);
+    
+    call_as(REPORTER);
+    mark_messages_read(report_id, latest_message_id(report_id)).unwrap();
+    assert_eq!(reporter_unread(report_id), 0);
+}
+
+#[test]
+fn cursors_never_move_backwards_or_past_the_latest_message() {
+    let (report_id, reporter_key, authority_key) = setup();
+    call_as(REPORTER);
+    send_message_as_reporter(report_id, encrypted(reporter_key, &[authority_key]), None).unwrap();
+    let latest = latest_message_id(report_id);
+    
+    call_as(AUTHORITY);
+    assert_eq!(mark_messages_read(report_id, u64::MAX), Ok(latest));
+    assert_eq!(mark_messages_read(report_id, 0), Ok(latest));
+    
+    // Only participants have cursors
+    call_as(OTHER_REPORTER);
+    assert!(mark_messages_read(report_id, latest).is_err());
+    assert!(get_read_receipts(report_id).is_err());
+}
+
+#[test]
+fn read_receipts_show_each_side() {
+    let (report_id, _, _) = setup();
+    let latest = latest_message_id(report_id);
+    
+    call_as(AUTHORITY);
+    let receipts = get_read_receipts(report_id).unwrap();
+    assert_eq!((receipts.reporter_read_up_to, receipts.authorities.len()), (0, 0));
+    mark_messages_read(report_id, latest).unwrap();
+    
+    call_as(REPORTER);
+    mark_messages_read(report_id, latest).unwrap();
+    let receipts = get_read_receipts(report_id).unwrap();
+    assert_eq!(receipts.reporter_read_up_to, latest);
+    assert_eq!(receipts.authorities, vec![(AUTHORITY, latest)]);
+}
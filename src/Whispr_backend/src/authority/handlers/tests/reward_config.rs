@@ -0,0 +1,78 @@
+// Versioned reward config: reports keep the terms they were submitted under
+
+use super::*;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, 10_000, None);
+}
+
+fn rewards_earned(id: Principal) -> u64 {
+    store::get_user(id).map_or(0, |user| user.rewards_earned)
+}
+
+#[test]
+fn reports_are_rewarded_under_the_config_they_were_submitted_with() {
+    setup();
+    let before = submit(REPORTER, 20).unwrap();
+    
+    call_as(AUTHORITY);
+    let current = get_reward_config().unwrap();
+    let updated = update_reward_config(RewardConfig { reward_multiplier: 20, min_stake_amount: 10, max_stake_amount: 25, ..current }).unwrap();
+    assert_eq!(updated.version, 2);
+    assert_eq!(store::get_reward_config_version(1).unwrap().reward_multiplier, 10);
+    
+    let after = submit(REPORTER, 20).unwrap();
+    assert_eq!(store::get_report(before).unwrap().reward_config_version, Some(1));
+    assert_eq!(store::get_report(after).unwrap().reward_config_version, Some(2));
+    
+    call_as(AUTHORITY);
+    verify_report(before, None).unwrap();
+    assert_eq!(rewards_earned(REPORTER), 200);
+    verify_report(after, None).unwrap();
+    assert_eq!(rewards_earned(REPORTER), 200 + 400);
+    assert_books_balance();
+}
+
+#[test]
+fn stakes_are_checked_against_the_current_config() {
+    setup();
+    call_as(AUTHORITY);
+    let current = get_reward_config().unwrap();
+    update_reward_config(RewardConfig { min_stake_amount: 10, max_stake_amount: 25, ..current }).unwrap();
+    
+    assert_eq!(submit(REPORTER, 9).unwrap_err(), "Minimum stake amount is 10 tokens");
+    assert_eq!(submit(REPORTER, 26).unwrap_err(), "Maximum stake amount is 25 tokens");
+    assert!(submit(REPORTER, 10).is_ok());
+}
+
+#[test]
+fn stale_or_invalid_configs_are_refused() {
+    setup();
+    call_as(AUTHORITY);
+    let current = get_reward_config().unwrap();
+    update_reward_config(current.clone()).unwrap();
+    
+    // The edit was based on version 1, which is no longer current
+    let error = update_reward_config(current.clone()).unwrap_err();
+    assert!(error.contains("current version is 2"), "{}", error);
+    
+    let current = get_reward_config().unwrap();
+    let invalid = [
+        RewardConfig { reward_multiplier: 0, ..current.clone() },
+        RewardConfig { reward_multiplier: 101, ..current.clone() },
+        RewardConfig { min_stake_amount: 0, ..current.clone() },
+        RewardConfig { min_stake_amount: 50, max_stake_amount: 40, ..current.clone() },
+        RewardConfig { max_stake_amount: u64::MAX, ..current.clone() },
+    ];
+    for config in invalid {
+        assert!(update_reward_config(config.clone()).is_err(), "{:?}", config);
+    }
+    assert_eq!(store::get_reward_config().version, 2);
+    
+    call_as(REPORTER);
+    assert!(get_reward_config().is_err());
+    assert!(update_reward_config(current).is_err());
+}
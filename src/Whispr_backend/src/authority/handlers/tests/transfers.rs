@@ -0,0 +1,89 @@
+// Peer-to-peer transfers: daily limits and idempotency keys
+
+use super::*;
+
+const STARTING_BALANCE: u64 = 500;
+
+fn setup() {
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", STARTING_BALANCE);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", STARTING_BALANCE);
+}
+
+fn set_limits(daily_amount_limit: Option<u64>, daily_count_limit: Option<u64>) {
+    call_as(AUTHORITY);
+    update_transfer_config(TransferConfig { daily_amount_limit, daily_count_limit }).unwrap();
+}
+
+fn send(amount: u64, idempotency_key: &str) -> Result<JournalEntry, String> {
+    call_as(REPORTER);
+    transfer(OTHER_REPORTER, amount, None, idempotency_key.to_string())
+}
+
+fn balance(id: Principal) -> u64 {
+    store::get_user(id).map_or(0, |user| user.token_balance)
+}
+
+#[test]
+fn daily_limits_cap_the_amount_and_number_of_transfers() {
+    setup();
+    set_limits(Some(150), Some(2));
+    
+    send(100, "a").unwrap();
+    let error = send(60, "b").unwrap_err();
+    assert_eq!(error, "Transfers are limited to 150 tokens per day and 100 have been sent today");
+    send(50, "c").unwrap();
+    let error = send(1, "d").unwrap_err();
+    assert!(error.contains("150 tokens per day"), "{}", error);
+    
+    // The count limit applies on its own
+    set_limits(None, Some(2));
+    let error = send(1, "e").unwrap_err();
+    assert_eq!(error, "Transfers are limited to 2 per day");
+    
+    // Limits are per sender and reset with the UTC day
+    call_as(OTHER_REPORTER);
+    transfer(REPORTER, 10, None, "a".to_string()).unwrap();
+    runtime::advance_time(Duration::from_nanos(store::DAY_NS));
+    send(1, "f").unwrap();
+    assert_eq!(balance(OTHER_REPORTER), STARTING_BALANCE + 151 - 10);
+    assert_books_balance();
+}
+
+#[test]
+fn idempotency_keys_return_the_original_transfer() {
+    setup();
+    set_limits(None, Some(1));
+    let entry = send(100, "rent").unwrap();
+    
+    // A retry is answered even once the daily limit is used up, and moves nothing
+    assert_eq!(send(100, "rent").unwrap().id, entry.id);
+    assert_eq!(balance(OTHER_REPORTER), STARTING_BALANCE + 100);
+    
+    let error = send(99, "rent").unwrap_err();
+    assert!(error.contains("different transfer"), "{}", error);
+    call_as(REPORTER);
+    let error = transfer(OTHER_REPORTER, 100, Some("memo".to_string()), "rent".to_string()).unwrap_err();
+    assert!(error.contains("different transfer"), "{}", error);
+    
+    // Keys belong to their sender
+    call_as(OTHER_REPORTER);
+    let reply = transfer(REPORTER, 100, None, "rent".to_string()).unwrap();
+    assert_ne!(reply.id, entry.id);
+    
+    assert!(send(1, "").is_err());
+    assert!(send(1, &"k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1)).is_err());
+    assert_books_balance();
+}
+
+#[test]
+fn transfer_config_is_validated() {
+    setup();
+    call_as(AUTHORITY);
+    assert!(update_transfer_config(TransferConfig { daily_amount_limit: Some(0), daily_count_limit: None }).is_err());
+    assert!(update_transfer_config(TransferConfig { daily_amount_limit: None, daily_count_limit: Some(0) }).is_err());
+    
+    call_as(REPORTER);
+    assert!(update_transfer_config(TransferConfig { daily_amount_limit: None, daily_count_limit: None }).is_err());
+    assert_eq!(get_transfer_config().daily_count_limit, store::get_transfer_config().daily_count_limit);
+}
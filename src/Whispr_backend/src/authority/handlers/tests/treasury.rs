@@ -0,0 +1,89 @@
+// Reward treasury: payout caps per period, funding and the treasury report
+
+use super::*;
+
+const TREASURY_FUNDS: u64 = 10_000;
+
+fn setup() {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    post_entry(EntryKind::Grant, BookAccount::Minting, BookAccount::Treasury, TREASURY_FUNDS, None);
+}
+
+fn set_cap(payout_cap: Option<u64>, cap_period: PayoutPeriod) {
+    call_as(AUTHORITY);
+    update_treasury_config(TreasuryConfig { payout_cap, cap_period }).unwrap();
+}
+
+// File a report with a 100 token reward and try to verify it
+fn verify_new_report() -> Result<u64, String> {
+    let report_id = submit(REPORTER, 10).unwrap();
+    call_as(AUTHORITY);
+    verify_report(report_id, None).map(|_| report_id)
+}
+
+#[test]
+fn rewards_stop_at_the_daily_payout_cap() {
+    setup();
+    set_cap(Some(250), PayoutPeriod::Daily);
+    
+    verify_new_report().unwrap();
+    verify_new_report().unwrap();
+    let error = verify_new_report().unwrap_err();
+    assert!(error.contains("50 tokens left"), "{}", error);
+    assert_eq!(report_status(3), ReportStatus::Pending);
+    
+    // The cap resets with the next day
+    runtime::advance_time(Duration::from_nanos(store::DAY_NS));
+    call_as(AUTHORITY);
+    verify_report(3, None).unwrap();
+    assert_eq!(store::get_user(REPORTER).unwrap().rewards_earned, 300);
+    
+    // Lifting the cap lets rewards through again
+    verify_new_report().unwrap();
+    verify_new_report().unwrap_err();
+    set_cap(None, PayoutPeriod::Daily);
+    verify_new_report().unwrap();
+    assert_books_balance();
+}
+
+#[test]
+fn monthly_periods_start_on_the_first_of_the_month() {
+    // 1970-01-01, 1970-02-01 and 1970-03-01
+    assert_eq!([0, 31, 59].map(day_of_month), [1, 1, 1]);
+    // 2000-02-29 and 2000-12-31
+    assert_eq!([11_016, 11_322].map(day_of_month), [29, 31]);
+    
+    assert_eq!(payout_period_start(11_016, PayoutPeriod::Monthly), 10_988);
+    assert_eq!(payout_period_start(11_016, PayoutPeriod::Daily), 11_016);
+}
+
+#[test]
+fn treasury_report_totals_flows_and_the_cap() {
+    setup();
+    set_cap(Some(1_000), PayoutPeriod::Monthly);
+    verify_new_report().unwrap();
+    
+    call_as(REPORTER);
+    fund_treasury(40).unwrap();
+    assert!(fund_treasury(0).is_err());
+    
+    call_as(AUTHORITY);
+    let now = api::time();
+    let report = get_treasury_report(now, now).unwrap();
+    assert_eq!(report.balance, TREASURY_FUNDS - 100 + 40);
+    assert_eq!((report.total_inflow, report.total_outflow), (TREASURY_FUNDS + 40, 100));
+    assert_eq!((report.total_rewards_paid, report.period_rewards_paid), (100, 100));
+    assert_eq!(report.period_remaining, Some(900));
+    assert_eq!(report.days.len(), 1);
+    assert_eq!(report.days[0].0, now / store::DAY_NS * store::DAY_NS);
+    
+    assert!(get_treasury_report(now, now - store::DAY_NS).is_err());
+    assert!(get_treasury_report(now, now + 366 * store::DAY_NS).is_err());
+    assert!(update_treasury_config(TreasuryConfig { payout_cap: Some(0), cap_period: PayoutPeriod::Daily }).is_err());
+    
+    call_as(REPORTER);
+    assert!(get_treasury_report(now, now).is_err());
+    assert!(update_treasury_config(TreasuryConfig { payout_cap: None, cap_period: PayoutPeriod::Daily }).is_err());
+}
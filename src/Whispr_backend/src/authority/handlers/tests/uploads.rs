@@ -0,0 +1,103 @@
+// Chunked evidence uploads: size limits, hash checks and the per-uploader open-upload limit
+
+use super::*;
+
+fn setup() -> u64 {
+    store::seed_default_categories();
+    add_admin(AUTHORITY);
+    grant_onboarding_tokens(REPORTER, "test", 500);
+    grant_onboarding_tokens(OTHER_REPORTER, "test", 500);
+    submit(REPORTER, 10).unwrap()
+}
+
+fn begin(report_id: u64, total_size: u64) -> Result<u64, String> {
+    begin_evidence_upload(report_id, "evidence.bin".to_string(), "application/octet-stream".to_string(), total_size)
+}
+
+fn sha256(chunks: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    hasher.finalize().to_vec()
+}
+
+#[test]
+fn finalizing_checks_the_hash_of_the_chunks() {
+    let report_id = setup();
+    call_as(REPORTER);
+    let upload_id = begin(report_id, 6).unwrap();
+    
+    // Chunks may arrive out of order
+    upload_evidence_chunk(upload_id, 1, vec![4, 5, 6]).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![1, 2, 3]).unwrap();
+    
+    // A wrong hash leaves the upload open so it can be retried
+    let error = finalize_evidence_upload(upload_id, sha256(&[&[4, 5, 6], &[1, 2, 3]]), None).unwrap_err();
+    assert!(error.contains("does not match"), "{}", error);
+    assert_eq!(store::count_open_uploads(REPORTER), 1);
+    
+    let file_id = finalize_evidence_upload(upload_id, sha256(&[&[1, 2, 3], &[4, 5, 6]]), None).unwrap();
+    
+    assert_eq!(store::get_report(report_id).unwrap().evidence_files, vec![file_id]);
+    assert_eq!(get_evidence_chunk(file_id, 1), Ok(vec![4, 5, 6]));
+    assert_eq!(store::count_open_uploads(REPORTER), 0);
+    assert!(store::get_evidence_upload(upload_id).is_none());
+}
+
+#[test]
+fn uploads_are_held_to_their_size() {
+    let report_id = setup();
+    call_as(REPORTER);
+    
+    assert!(begin(report_id, 0).is_err());
+    let error = begin(report_id, MAX_EVIDENCE_FILE_SIZE + 1).unwrap_err();
+    assert!(error.contains("limited to"), "{}", error);
+    assert!(begin(report_id, MAX_EVIDENCE_FILE_SIZE).is_ok());
+    
+    let upload_id = begin(report_id, 4).unwrap();
+    let error = upload_evidence_chunk(upload_id, 0, vec![0; 5]).unwrap_err();
+    assert!(error.contains("declared file size"), "{}", error);
+    let error = upload_evidence_chunk(upload_id, 0, vec![0; MAX_EVIDENCE_CHUNK_SIZE + 1]).unwrap_err();
+    assert!(error.contains("Chunks are limited"), "{}", error);
+    
+    // A retried chunk replaces the earlier one instead of adding to the size
+    upload_evidence_chunk(upload_id, 0, vec![1, 2]).unwrap();
+    upload_evidence_chunk(upload_id, 0, vec![1, 2]).unwrap();
+    let error = finalize_evidence_upload(upload_id, sha256(&[&[1, 2]]), None).unwrap_err();
+    assert!(error.contains("received 2 of 4"), "{}", error);
+    
+    // Only the uploader can add chunks
+    call_as(OTHER_REPORTER);
+    assert!(upload_evidence_chunk(upload_id, 1, vec![3, 4]).is_err());
+}
+
+#[test]
+fn open_upload_limit_counts_each_uploader_separately() {
+    let report_id = setup();
+    let other_report_id = submit(OTHER_REPORTER, 10).unwrap();
+    
+    call_as(REPORTER);
+    let upload_ids: Vec<u64> = (0..MAX_OPEN_UPLOADS_PER_CALLER).map(|_| begin(report_id, 1).unwrap()).collect();
+    let error = begin(report_id, 1).unwrap_err();
+    assert!(error.contains("uploads can be open"), "{}", error);
+    
+    // Another principal's uploads are counted on their own
+    call_as(OTHER_REPORTER);
+    assert!(begin(other_report_id, 1).is_ok());
+    assert_eq!(store::count_open_uploads(OTHER_REPORTER), 1);
+    
+    // Finishing an upload frees a slot
+    call_as(REPORTER);
+    upload_evidence_chunk(upload_ids[0], 0, vec![7]).unwrap();
+    finalize_evidence_upload(upload_ids[0], sha256(&[&[7]]), None).unwrap();
+    assert!(begin(report_id, 1).is_ok());
+    assert!(begin(report_id, 1).is_err());
+    
+    // So does the sweep of expired uploads
+    runtime::advance_time(Duration::from_nanos(UPLOAD_EXPIRY_NS + 1));
+    expire_stale_uploads();
+    assert_eq!(store::count_open_uploads(REPORTER), 0);
+    assert_eq!(store::count_open_uploads(OTHER_REPORTER), 0);
+    assert!(begin(report_id, 1).is_ok());
+}
@@ -0,0 +1,241 @@
+use crate::authority::runtime;
+use candid::{CandidType, Deserialize, Int, Nat, Principal};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+// ICRC-1/ICRC-2/ICRC-3 wire types. They are used both to call an external ledger in
+// ledger-backed staking mode and to serve the canister's own token interface.
+
+// Marks a subaccount as a report escrow; the report ID fills the last 8 bytes
+const ESCROW_SUBACCOUNT_TAG: u8 = 1;
+
+// Marks the subaccount holding the reviewers' share of slashed stakes
+const REVIEWER_POOL_SUBACCOUNT_TAG: u8 = 2;
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Vec<u8>>,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum TransferError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    TemporarilyUnavailable,
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TransferFromArgs {
+    pub spender_subaccount: Option<Vec<u8>>,
+    pub from: Account,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum TransferFromError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    InsufficientAllowance { allowance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+// Metadata entry value returned by icrc1_metadata
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum MetadataValue {
+    Nat(Nat),
+    Int(Int),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SupportedStandard {
+    pub name: String,
+    pub url: String,
+}
+
+// Generic ICRC-3 value, used to represent blocks
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum Value {
+    Blob(Vec<u8>),
+    Text(String),
+    Nat(Nat),
+    Int(Int),
+    Array(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    // ICRC-3 representation-independent hash
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            Value::Blob(bytes) => Sha256::digest(bytes).into(),
+            Value::Text(text) => Sha256::digest(text.as_bytes()).into(),
+            Value::Nat(nat) => {
+                let mut leb = Vec::new();
+                nat.encode(&mut leb).expect("Failed to encode nat");
+                Sha256::digest(&leb).into()
+            }
+            Value::Int(int) => {
+                let mut sleb = Vec::new();
+                int.encode(&mut sleb).expect("Failed to encode int");
+                Sha256::digest(&sleb).into()
+            }
+            Value::Array(values) => {
+                let mut hasher = Sha256::new();
+                for value in values {
+                    hasher.update(value.hash());
+                }
+                hasher.finalize().into()
+            }
+            Value::Map(entries) => {
+                let mut pairs: Vec<Vec<u8>> = entries
+                    .iter()
+                    .map(|(key, value)| {
+                        let mut pair = Sha256::digest(key.as_bytes()).to_vec();
+                        pair.extend_from_slice(&value.hash());
+                        pair
+                    })
+                    .collect();
+                pairs.sort();
+                
+                let mut hasher = Sha256::new();
+                for pair in pairs {
+                    hasher.update(pair);
+                }
+                hasher.finalize().into()
+            }
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetBlocksArgs {
+    pub start: Nat,
+    pub length: Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BlockWithId {
+    pub id: Nat,
+    pub block: Value,
+}
+
+candid::define_function!(pub GetBlocksCallback : (Vec<GetBlocksArgs>) -> (GetBlocksResult) query);
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedBlocks {
+    pub args: Vec<GetBlocksArgs>,
+    pub callback: GetBlocksCallback,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetBlocksResult {
+    pub log_length: Nat,
+    pub blocks: Vec<BlockWithId>,
+    pub archived_blocks: Vec<ArchivedBlocks>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SupportedBlockType {
+    pub block_type: String,
+    pub url: String,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct GetArchivesArgs {
+    pub from: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchiveInfo {
+    pub canister_id: Principal,
+    pub start: Nat,
+    pub end: Nat,
+}
+
+// Escrow subaccount holding the stake of one report
+pub fn escrow_subaccount(report_id: u64) -> Vec<u8> {
+    let mut subaccount = vec![0u8; 32];
+    subaccount[0] = ESCROW_SUBACCOUNT_TAG;
+    subaccount[24..].copy_from_slice(&report_id.to_be_bytes());
+    subaccount
+}
+
+// Subaccount holding the reviewers' share of slashed stakes
+pub fn reviewer_pool_subaccount() -> Vec<u8> {
+    let mut subaccount = vec![0u8; 32];
+    subaccount[0] = REVIEWER_POOL_SUBACCOUNT_TAG;
+    subaccount
+}
+
+// Memo tying a ledger transaction to a report
+pub fn report_memo(report_id: u64) -> Vec<u8> {
+    report_id.to_be_bytes().to_vec()
+}
+
+// Amounts and block indexes handled here always fit in a u64
+pub fn nat_to_u64(value: &Nat) -> u64 {
+    u64::try_from(&value.0).unwrap_or(u64::MAX)
+}
+
+// Call icrc1_transfer. The outer error means the call itself failed, in which case
+// the transfer may or may not have happened.
+pub async fn icrc1_transfer(ledger: Principal, arg: TransferArg) -> Result<Result<Nat, TransferError>, String> {
+    match runtime::call::<_, (Result<Nat, TransferError>,)>(ledger, "icrc1_transfer", (arg,)).await {
+        Ok((result,)) => Ok(result),
+        Err((code, message)) => Err(format!("icrc1_transfer call failed ({:?}): {}", code, message)),
+    }
+}
+
+// Call icrc1_balance_of
+pub async fn icrc1_balance_of(ledger: Principal, account: Account) -> Result<Nat, String> {
+    match runtime::call::<_, (Nat,)>(ledger, "icrc1_balance_of", (account,)).await {
+        Ok((balance,)) => Ok(balance),
+        Err((code, message)) => Err(format!("icrc1_balance_of call failed ({:?}): {}", code, message)),
+    }
+}
+
+// Call icrc1_minting_account; transfers to the minting account burn tokens
+pub async fn icrc1_minting_account(ledger: Principal) -> Result<Option<Account>, String> {
+    match runtime::call::<_, (Option<Account>,)>(ledger, "icrc1_minting_account", ()).await {
+        Ok((account,)) => Ok(account),
+        Err((code, message)) => Err(format!("icrc1_minting_account call failed ({:?}): {}", code, message)),
+    }
+}
+
+// Call icrc2_transfer_from, with the same error convention as icrc1_transfer
+pub async fn icrc2_transfer_from(
+    ledger: Principal,
+    args: TransferFromArgs,
+) -> Result<Result<Nat, TransferFromError>, String> {
+    match runtime::call::<_, (Result<Nat, TransferFromError>,)>(ledger, "icrc2_transfer_from", (args,)).await {
+        Ok((result,)) => Ok(result),
+        Err((code, message)) => Err(format!("icrc2_transfer_from call failed ({:?}): {}", code, message)),
+    }
+}
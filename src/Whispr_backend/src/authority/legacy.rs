@@ -0,0 +1,213 @@
+use crate::authority::handlers::{file_report, MAX_EVIDENCE_CHUNK_SIZE, MAX_EVIDENCE_FILE_SIZE};
+use crate::authority::store;
+use crate::authority::types::*;
+use candid::{CandidType, Deserialize, Principal};
+use crate::authority::runtime::{api, caller};
+use sha2::{Digest, Sha256};
+
+// Compatibility layer for the original string-ID report API. The endpoints here are
+// thin adapters over the authority store; the legacy types only exist at the edge.
+
+// Location as sent by the original frontend
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LocationData {
+    pub address: String,
+    pub coordinates: Option<Coordinates>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+// Evidence with inline content, as sent by the original frontend
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InlineEvidenceFile {
+    pub name: String,
+    pub file_type: String,
+    pub size: u64,
+    pub content: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReportSubmission {
+    pub title: String,
+    pub description: String,
+    pub location: LocationData,
+    pub date: Option<String>,
+    pub time: Option<String>,
+    pub category: String,
+    pub evidence_files: Vec<InlineEvidenceFile>,
+    pub stake_amount: u64,
+}
+
+// Dashboard row returned by get_my_reports
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReportSummary {
+    pub id: String,
+    pub title: String,
+    pub category: String,
+    pub date: String,
+    pub status: String,
+    pub stake: u64,
+    pub reward: u64,
+    pub has_messages: bool,
+}
+
+// Status names understood by the original frontend
+fn legacy_status_name(status: &ReportStatus) -> String {
+    match status {
+        ReportStatus::Pending
+        | ReportStatus::UnderReview
+        | ReportStatus::Escalated
+        | ReportStatus::Reopened => "pending".to_string(),
+        ReportStatus::Approved => "verified".to_string(),
+        ReportStatus::Rejected => "rejected".to_string(),
+        ReportStatus::Withdrawn => "withdrawn".to_string(),
+    }
+}
+
+fn to_location(location: LocationData) -> Option<Location> {
+    let address = Some(location.address).filter(|address| !address.trim().is_empty());
+    
+    match (location.coordinates, address) {
+        (Some(coordinates), address) => Some(Location {
+            address,
+            latitude: coordinates.lat,
+            longitude: coordinates.lng,
+        }),
+        (None, Some(address)) => Some(Location {
+            address: Some(address),
+            latitude: 0.0,
+            longitude: 0.0,
+        }),
+        (None, None) => None,
+    }
+}
+
+fn to_incident_date(date: Option<String>, time: Option<String>) -> Option<String> {
+    match (date, time) {
+        (Some(date), Some(time)) => Some(format!("{} {}", date, time)),
+        (date, _) => date,
+    }
+}
+
+fn to_summary(report: &Report) -> ReportSummary {
+    let has_messages = store::get_report_messages(report.id)
+        .iter()
+        .any(|message| !matches!(message.sender, MessageSender::System));
+    
+    ReportSummary {
+        id: report.id.to_string(),
+        title: report.title.clone(),
+        category: report.category.clone(),
+        date: report.incident_date.clone().unwrap_or_else(|| "N/A".to_string()),
+        status: legacy_status_name(&report.status),
+        stake: report.stake_amount,
+        reward: report.reward_amount,
+        has_messages,
+    }
+}
+
+// Store inline evidence as chunked evidence files and link them to the report,
+// auditing the change as `actor`
+fn attach_inline_evidence(actor: Principal, method: &str, report_id: u64, files: Vec<InlineEvidenceFile>) -> Result<(), String> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    
+    let mut report = match store::get_report(report_id) {
+        Some(report) => report,
+        None => return Err("Report not found".to_string()),
+    };
+    let before_digest = store::digest(&report);
+    let mut targets = vec![format!("report:{}", report_id)];
+    
+    for file in files {
+        if file.content.len() as u64 > MAX_EVIDENCE_FILE_SIZE {
+            return Err(format!("Evidence file '{}' exceeds {} bytes", file.name, MAX_EVIDENCE_FILE_SIZE));
+        }
+        
+        let chunks: Vec<&[u8]> = file.content.chunks(MAX_EVIDENCE_CHUNK_SIZE).collect();
+        let evidence = EvidenceFile {
+            id: 0, // Will be assigned by add_evidence_file
+            report_id,
+            name: file.name,
+            file_type: file.file_type,
+            size: file.content.len() as u64,
+            chunk_count: chunks.len() as u32,
+            sha256: Sha256::digest(&file.content).to_vec(),
+            upload_date: api::time(),
+            encryption: None,
+            source_message_id: None,
+        };
+        
+        let file_id = store::add_evidence_file(&evidence);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            store::put_evidence_chunk(file_id, index as u32, chunk.to_vec());
+        }
+        
+        report.evidence_files.push(file_id);
+        targets.push(format!("evidence:{}", file_id));
+    }
+    
+    report.evidence_count = report.evidence_files.len() as u32;
+    store::append_audit_entry(actor, method, targets, Some(before_digest), Some(store::digest(&report)));
+    store::update_report(report)
+}
+
+// Get the caller's reports in the original dashboard format
+#[ic_cdk::query]
+fn get_my_reports() -> Vec<ReportSummary> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return vec![];
+    }
+    
+    store::get_user_reports(caller).iter().map(to_summary).collect()
+}
+
+// Submit a report in the original format; the returned ID is the report's numeric ID as text
+#[ic_cdk::update]
+async fn submit_legacy_report(submission: ReportSubmission) -> Result<String, String> {
+    let caller = caller();
+    
+    if let Some(file) = submission.evidence_files.iter().find(|file| file.content.len() as u64 > MAX_EVIDENCE_FILE_SIZE) {
+        return Err(format!("Evidence file '{}' exceeds {} bytes", file.name, MAX_EVIDENCE_FILE_SIZE));
+    }
+    
+    let report_id = file_report(
+        submission.title,
+        submission.description,
+        submission.category,
+        to_location(submission.location),
+        to_incident_date(submission.date, submission.time),
+        submission.stake_amount,
+    ).await?;
+    
+    attach_inline_evidence(caller, "submit_legacy_report", report_id, submission.evidence_files)?;
+    
+    Ok(report_id.to_string())
+}
+
+// Get the caller's token balance (unknown users have no balance)
+#[ic_cdk::query]
+fn get_token_balance() -> u64 {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return 0;
+    }
+    
+    store::get_user(caller).map_or(0, |user| user.token_balance)
+}
+
+#[ic_cdk::query]
+fn greet(name: String) -> String {
+    format!("Hello, {}!", name)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,136 @@
+// Adapters for the original string-ID report API
+
+use super::*;
+use crate::authority::runtime;
+use futures::executor::block_on;
+
+const REPORTER: Principal = Principal::from_slice(&[2]);
+const OTHER_REPORTER: Principal = Principal::from_slice(&[3]);
+
+fn setup() {
+    store::seed_default_categories();
+    for reporter in [REPORTER, OTHER_REPORTER] {
+        store::create_or_update_user(User::new(reporter));
+        store::post_entry_now(EntryKind::Grant, BookAccount::Minting, BookAccount::user(reporter), 500, None).unwrap();
+    }
+}
+
+fn submission(evidence_files: Vec<InlineEvidenceFile>) -> ReportSubmission {
+    ReportSubmission {
+        title: "Report title".to_string(),
+        description: "Report description".to_string(),
+        location: LocationData {
+            address: "Main Street".to_string(),
+            coordinates: Some(Coordinates { lat: 1.5, lng: -2.5 }),
+        },
+        date: Some("2024-01-02".to_string()),
+        time: Some("10:30".to_string()),
+        category: "fraud".to_string(),
+        evidence_files,
+        stake_amount: 50,
+    }
+}
+
+fn set_status(report_id: u64, status: ReportStatus) {
+    let mut report = store::get_report(report_id).unwrap();
+    report.status = status;
+    store::update_report(report).unwrap();
+}
+
+#[test]
+fn statuses_map_to_the_original_names() {
+    let cases = [
+        (ReportStatus::Pending, "pending"),
+        (ReportStatus::UnderReview, "pending"),
+        (ReportStatus::Escalated, "pending"),
+        (ReportStatus::Reopened, "pending"),
+        (ReportStatus::Approved, "verified"),
+        (ReportStatus::Rejected, "rejected"),
+        (ReportStatus::Withdrawn, "withdrawn"),
+    ];
+    
+    for (status, name) in cases {
+        assert_eq!(legacy_status_name(&status), name, "{:?}", status);
+    }
+}
+
+#[test]
+fn legacy_submission_files_a_report_with_its_evidence() {
+    setup();
+    runtime::set_caller(REPORTER);
+    let content = vec![7u8; MAX_EVIDENCE_CHUNK_SIZE + 10];
+    let file = InlineEvidenceFile {
+        name: "photo.jpg".to_string(),
+        file_type: "image/jpeg".to_string(),
+        size: content.len() as u64,
+        content: content.clone(),
+    };
+    
+    let report_id: u64 = block_on(submit_legacy_report(submission(vec![file]))).unwrap().parse().unwrap();
+    
+    let report = store::get_report(report_id).unwrap();
+    assert_eq!(report.submitter_id, REPORTER);
+    assert_eq!(report.stake_amount, 50);
+    assert_eq!(report.incident_date.as_deref(), Some("2024-01-02 10:30"));
+    let location = report.location.unwrap();
+    assert_eq!((location.address.as_deref(), location.latitude, location.longitude), (Some("Main Street"), 1.5, -2.5));
+    
+    // Inline content is split into chunks and linked to the report
+    assert_eq!((report.evidence_count, report.evidence_files.len()), (1, 1));
+    let evidence = store::get_evidence_file(report.evidence_files[0]).unwrap();
+    assert_eq!((evidence.report_id, evidence.size, evidence.chunk_count), (report_id, content.len() as u64, 2));
+    assert_eq!(evidence.sha256, Sha256::digest(&content).to_vec());
+    let mut stored = store::get_evidence_chunk(evidence.id, 0).unwrap();
+    stored.extend(store::get_evidence_chunk(evidence.id, 1).unwrap());
+    assert_eq!(stored, content);
+}
+
+#[test]
+fn legacy_submission_follows_the_staking_rules() {
+    setup();
+    runtime::set_caller(REPORTER);
+    
+    let error = block_on(submit_legacy_report(ReportSubmission { stake_amount: 500, ..submission(Vec::new()) })).unwrap_err();
+    assert!(error.contains("Maximum stake"), "{}", error);
+    
+    let error = block_on(submit_legacy_report(ReportSubmission { category: "unknown".to_string(), ..submission(Vec::new()) })).unwrap_err();
+    assert!(error.contains("Unknown report category"), "{}", error);
+    assert!(store::get_user_reports(REPORTER).is_empty());
+}
+
+#[test]
+fn my_reports_lists_only_the_callers_reports() {
+    setup();
+    runtime::set_caller(REPORTER);
+    let first: u64 = block_on(submit_legacy_report(submission(Vec::new()))).unwrap().parse().unwrap();
+    let second: u64 = block_on(submit_legacy_report(ReportSubmission { date: None, ..submission(Vec::new()) })).unwrap().parse().unwrap();
+    runtime::set_caller(OTHER_REPORTER);
+    block_on(submit_legacy_report(submission(Vec::new()))).unwrap();
+    set_status(second, ReportStatus::Approved);
+    store::create_message(&Message {
+        id: 0,
+        report_id: first,
+        sender: MessageSender::Authority(Principal::from_slice(&[1])),
+        content: String::new(),
+        timestamp: 0,
+        attachment: None,
+        encrypted: None,
+    });
+    
+    runtime::set_caller(REPORTER);
+    let mut summaries = get_my_reports();
+    summaries.sort_by_key(|summary| summary.id.parse::<u64>().unwrap());
+    
+    let rows: Vec<(String, &str, &str, bool)> = summaries
+        .iter()
+        .map(|summary| (summary.id.clone(), summary.date.as_str(), summary.status.as_str(), summary.has_messages))
+        .collect();
+    assert_eq!(rows, vec![
+        (first.to_string(), "2024-01-02 10:30", "pending", true),
+        (second.to_string(), "N/A", "verified", false),
+    ]);
+    assert!(summaries.iter().all(|summary| summary.stake == 50 && summary.category == "fraud"));
+    
+    runtime::set_caller(Principal::anonymous());
+    assert!(get_my_reports().is_empty());
+}
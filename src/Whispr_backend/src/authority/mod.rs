@@ -1,6 +1,7 @@
 pub mod handlers;
+pub mod ledger;
+pub mod legacy;
+pub mod runtime;
 pub mod store;
+pub mod token;
 pub mod types;
-
-// Re-export all the public functions
-pub use handlers::*;
\ No newline at end of file
@@ -0,0 +1,102 @@
+// System API used by the handlers. Canister builds use ic_cdk directly; test builds
+// swap in a settable clock, caller and canister ID, timers that never fire,
+// counter-based randomness and ledger calls answered in process by the stand-in ledger.
+
+#[cfg(not(test))]
+pub use ic_cdk::{call, caller, spawn, trap};
+#[cfg(not(test))]
+pub use ic_cdk_timers::{set_timer, set_timer_interval};
+
+#[cfg(not(test))]
+pub mod api {
+    pub use ic_cdk::api::{id, management_canister, time};
+}
+
+#[cfg(test)]
+pub use self::test_runtime::*;
+
+#[cfg(test)]
+mod test_runtime {
+    use candid::utils::{ArgumentDecoder, ArgumentEncoder};
+    use candid::Principal;
+    use ic_cdk::api::call::{CallResult, RejectionCode};
+    use ic_cdk_timers::TimerId;
+    use std::cell::{Cell, RefCell};
+    use std::future::Future;
+    use std::time::Duration;
+
+    const CANISTER_ID: Principal = Principal::from_slice(&[0xAA; 10]);
+
+    thread_local! {
+        static TIME: Cell<u64> = const { Cell::new(1_700_000_000_000_000_000) };
+        static CALLER: RefCell<Principal> = const { RefCell::new(Principal::anonymous()) };
+        static RAND_CALLS: Cell<u64> = const { Cell::new(0) };
+    }
+
+    pub fn advance_time(duration: Duration) {
+        TIME.with(|time| time.set(time.get() + duration.as_nanos() as u64));
+    }
+
+    pub fn set_caller(principal: Principal) {
+        CALLER.with(|caller| *caller.borrow_mut() = principal);
+    }
+
+    pub fn caller() -> Principal {
+        CALLER.with(|caller| *caller.borrow())
+    }
+
+    pub fn trap(message: &str) -> ! {
+        panic!("{}", message)
+    }
+
+    // Timers never fire in tests; tests drive the timer bodies themselves
+    pub fn set_timer(_delay: Duration, _func: impl FnOnce() + 'static) -> TimerId {
+        TimerId::default()
+    }
+
+    pub fn set_timer_interval(_interval: Duration, _func: impl FnMut() + 'static) -> TimerId {
+        TimerId::default()
+    }
+
+    pub fn spawn<F: Future<Output = ()> + 'static>(_future: F) {}
+
+    // Every inter-canister call in tests goes to the stand-in ledger, with the
+    // arguments and reply passed through Candid as they would be on the wire
+    pub async fn call<T: ArgumentEncoder, R: for<'a> ArgumentDecoder<'a>>(
+        _id: Principal,
+        method: &str,
+        args: T,
+    ) -> CallResult<R> {
+        let rejected = |message: String| (RejectionCode::CanisterError, message);
+        let bytes = candid::encode_args(args).map_err(|error| rejected(error.to_string()))?;
+        let reply = mock_ledger::handle(api::id(), method, &bytes).map_err(rejected)?;
+        candid::decode_args(&reply).map_err(|error| rejected(error.to_string()))
+    }
+
+    pub mod api {
+        use super::*;
+
+        // Only raw_rand is used; each call returns different, reproducible bytes
+        pub mod management_canister {
+            pub mod main {
+                use super::super::super::*;
+
+                pub async fn raw_rand() -> CallResult<(Vec<u8>,)> {
+                    let call = RAND_CALLS.with(|calls| {
+                        calls.set(calls.get() + 1);
+                        calls.get()
+                    });
+                    Ok((call.to_be_bytes().repeat(4),))
+                }
+            }
+        }
+
+        pub fn time() -> u64 {
+            TIME.with(|time| time.get())
+        }
+
+        pub fn id() -> Principal {
+            CANISTER_ID
+        }
+    }
+}
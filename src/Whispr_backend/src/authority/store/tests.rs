@@ -0,0 +1,246 @@
+// Store-level tests that reach into the stable maps directly
+
+use super::*;
+
+const ACTOR: Principal = Principal::from_slice(&[1]);
+
+// Rewrite an audit entry in place, as someone with raw access to stable memory could
+fn tamper_with_audit_entry(id: u64, change: impl FnOnce(&mut AuditEntry)) {
+    AUDIT_LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let mut entry = log.get(&id).expect("entry exists");
+        change(&mut entry);
+        log.insert(id, entry);
+    });
+}
+
+fn append_audit_entries(count: u64) {
+    for index in 0..count {
+        append_audit_entry(ACTOR, "test", vec![format!("report:{}", index)], None, Some(vec![index as u8]));
+    }
+}
+
+#[test]
+fn untouched_audit_chain_is_valid() {
+    append_audit_entries(5);
+    
+    let status = verify_audit_chain();
+    
+    assert!(status.valid);
+    assert_eq!((status.entries_checked, status.first_invalid_id), (5, None));
+}
+
+#[test]
+fn edited_audit_entry_is_the_first_invalid_one() {
+    append_audit_entries(5);
+    
+    tamper_with_audit_entry(3, |entry| entry.targets = vec!["report:99".to_string()]);
+    
+    let status = verify_audit_chain();
+    assert!(!status.valid);
+    assert_eq!((status.entries_checked, status.first_invalid_id), (3, Some(3)));
+}
+
+#[test]
+fn rehashed_audit_entry_breaks_the_next_link() {
+    append_audit_entries(5);
+    
+    // Fixing up the edited entry's own hash still leaves its successor pointing at the old one
+    tamper_with_audit_entry(2, |entry| {
+        entry.method = "forged".to_string();
+        entry.hash = entry.compute_hash();
+    });
+    
+    assert_eq!(verify_audit_chain().first_invalid_id, Some(3));
+}
+
+#[test]
+fn removed_audit_entry_is_detected() {
+    append_audit_entries(5);
+    
+    AUDIT_LOG.with(|log| log.borrow_mut().remove(&4));
+    
+    assert_eq!(verify_audit_chain().first_invalid_id, Some(5));
+}
+
+#[test]
+fn audit_entry_moved_to_another_id_is_detected() {
+    append_audit_entries(3);
+    
+    AUDIT_LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let (first, second) = (log.get(&1).unwrap(), log.get(&2).unwrap());
+        log.insert(1, second);
+        log.insert(2, first);
+    });
+    
+    assert_eq!(verify_audit_chain().first_invalid_id, Some(1));
+}
+
+fn report(id: u64, category: &str, status: ReportStatus, stake_amount: u64) -> Report {
+    Report {
+        id,
+        title: format!("Report {}", id),
+        description: String::new(),
+        category: category.to_string(),
+        date_submitted: 1_000 + id,
+        incident_date: None,
+        location: None,
+        submitter_id: ACTOR,
+        evidence_count: 0,
+        evidence_files: Vec::new(),
+        stake_amount,
+        reward_amount: 0,
+        status,
+        reviewer: None,
+        review_date: None,
+        review_notes: None,
+        reward_config_version: None,
+    }
+}
+
+fn report_ids(index: ReportIndex) -> Vec<u64> {
+    scan_report_index(&index, None, false, usize::MAX).into_iter().map(|(_, id)| id).collect()
+}
+
+#[test]
+fn rebuild_indexes_covers_reports_stored_without_them() {
+    // Reports as written by a version without secondary indexes, plus an entry
+    // for a report that no longer exists
+    REPORTS.with(|reports| {
+        let mut reports = reports.borrow_mut();
+        reports.insert(1, report(1, "fraud", ReportStatus::Pending, 30));
+        reports.insert(2, report(2, "legacy-category", ReportStatus::Approved, 10));
+        reports.insert(3, report(3, "fraud", ReportStatus::Pending, 20));
+    });
+    REPORT_INDEX.with(|index| {
+        index.borrow_mut().insert(ReportIndex::Date.key(&report(9, "fraud", ReportStatus::Pending, 5)), ());
+    });
+    assert_eq!(report_ids(ReportIndex::Date), vec![9]);
+    
+    rebuild_indexes();
+    
+    assert_eq!(report_ids(ReportIndex::Date), vec![1, 2, 3]);
+    assert_eq!(report_ids(ReportIndex::Stake), vec![2, 3, 1]);
+    assert_eq!(report_ids(ReportIndex::StatusDate(ReportStatus::Pending)), vec![1, 3]);
+    assert_eq!(report_ids(ReportIndex::CategoryDate("fraud".to_string())), vec![1, 3]);
+    assert_eq!(get_user_reports(ACTOR).len(), 3);
+    
+    // Categories only found on reports are registered, and the next report gets a fresh ID
+    assert!(get_category("legacy-category").is_some());
+    assert_eq!(reserve_report_id(), 4);
+    
+    // Rebuilding again changes nothing
+    rebuild_indexes();
+    assert_eq!(report_ids(ReportIndex::Date), vec![1, 2, 3]);
+}
+
+fn message(report_id: u64) -> Message {
+    Message {
+        id: 0,
+        report_id,
+        sender: MessageSender::System,
+        content: "note".to_string(),
+        timestamp: 0,
+        attachment: None,
+        encrypted: None,
+    }
+}
+
+#[test]
+fn counters_and_message_index_live_in_stable_memory() {
+    let first_report = create_report(&report(0, "fraud", ReportStatus::Pending, 10));
+    let second_report = create_report(&report(0, "fraud", ReportStatus::Pending, 10));
+    create_message(&message(first_report));
+    create_message(&message(second_report));
+    create_message(&message(first_report));
+    assert_eq!((first_report, second_report), (1, 2));
+    
+    // Reopening the memories, as the canister does after an upgrade, finds the
+    // counters where they were and the messages still indexed by report
+    let report_counter: StableCell<u64, Memory> =
+        StableCell::init(MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(5))), 1).unwrap();
+    let message_counter: StableCell<u64, Memory> =
+        StableCell::init(MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(6))), 1).unwrap();
+    assert_eq!((*report_counter.get(), *message_counter.get()), (3, 4));
+    
+    let report_messages: StableBTreeMap<ReportItemKey, (), Memory> =
+        StableBTreeMap::init(MEMORY_MANAGER.with(|mm| mm.borrow().get(MemoryId::new(10))));
+    let indexed: Vec<u64> = report_messages.range(ReportItemKey::range(first_report)).map(|(key, _)| key.item_id).collect();
+    assert_eq!(indexed, vec![1, 3]);
+}
+
+#[test]
+fn rebuild_indexes_moves_counters_past_stored_ids() {
+    create_report(&report(0, "fraud", ReportStatus::Pending, 10));
+    let report_id = create_report(&report(0, "fraud", ReportStatus::Pending, 10));
+    create_message(&message(report_id));
+    create_message(&message(report_id));
+    
+    // Counters left behind the data, as after an upgrade from a version that kept them on the heap
+    NEXT_REPORT_ID.with(|counter| counter.borrow_mut().set(1).unwrap());
+    NEXT_MESSAGE_ID.with(|counter| counter.borrow_mut().set(1).unwrap());
+    REPORT_MESSAGES.with(|index| {
+        let mut index = index.borrow_mut();
+        let keys: Vec<ReportItemKey> = index.iter().map(|(key, _)| key).collect();
+        for key in keys {
+            index.remove(&key);
+        }
+    });
+    assert!(get_report_messages(report_id).is_empty());
+    
+    rebuild_indexes();
+    
+    assert_eq!(get_report_messages(report_id).len(), 2);
+    assert_eq!(create_report(&report(0, "fraud", ReportStatus::Pending, 10)), 3);
+    assert_eq!(create_message(&message(report_id)), 3);
+    
+    // Counters already ahead of the data are left alone
+    NEXT_REPORT_ID.with(|counter| counter.borrow_mut().set(10).unwrap());
+    rebuild_indexes();
+    assert_eq!(reserve_report_id(), 10);
+}
+
+fn upload(uploader: Principal) -> EvidenceUpload {
+    EvidenceUpload {
+        id: 0,
+        report_id: 1,
+        uploader,
+        name: "evidence.bin".to_string(),
+        file_type: "application/octet-stream".to_string(),
+        total_size: 1,
+        received_size: 0,
+        started_at: 0,
+        purpose: None,
+    }
+}
+
+#[test]
+fn rebuild_indexes_reindexes_open_uploads_by_uploader() {
+    let other = Principal::from_slice(&[2]);
+    // A principal whose bytes start with ACTOR's must not be counted as ACTOR
+    let longer = Principal::from_slice(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 7]);
+    let first = create_evidence_upload(&upload(ACTOR));
+    create_evidence_upload(&upload(ACTOR));
+    create_evidence_upload(&upload(other));
+    create_evidence_upload(&upload(longer));
+    assert_eq!((count_open_uploads(ACTOR), count_open_uploads(other), count_open_uploads(longer)), (2, 1, 1));
+    
+    remove_evidence_upload(first);
+    assert_eq!(count_open_uploads(ACTOR), 1);
+    
+    // Uploads stored before the index existed, and an entry for an upload that is gone
+    UPLOADER_UPLOADS.with(|index| {
+        let mut index = index.borrow_mut();
+        let keys: Vec<UploaderUploadKey> = index.iter().map(|(key, _)| key).collect();
+        for key in keys {
+            index.remove(&key);
+        }
+        index.insert(UploaderUploadKey { uploader: other, upload_id: 99 }, ());
+    });
+    assert_eq!((count_open_uploads(ACTOR), count_open_uploads(other)), (0, 1));
+    
+    rebuild_indexes();
+    
+    assert_eq!((count_open_uploads(ACTOR), count_open_uploads(other), count_open_uploads(longer)), (1, 1, 1));
+}
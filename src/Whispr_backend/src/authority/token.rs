@@ -0,0 +1,287 @@
+use crate::authority::ledger::{
+    nat_to_u64, Account, ArchiveInfo, BlockWithId, GetArchivesArgs, GetBlocksArgs, GetBlocksResult, MetadataValue,
+    SupportedBlockType, SupportedStandard, TransferArg, TransferError,
+};
+use crate::authority::handlers;
+use crate::authority::store;
+use crate::authority::types::*;
+use candid::{Nat, Principal};
+use crate::authority::runtime::{api, caller};
+use sha2::{Digest, Sha256};
+
+// ICRC-1 interface over the internal token balances, with an ICRC-3 block log.
+// The minting account is the canister's default account; transfers to it burn.
+
+const TOKEN_NAME: &str = "Whispr Token";
+const TOKEN_SYMBOL: &str = "WSPR";
+
+// Stakes and rewards are whole tokens
+const TOKEN_DECIMALS: u8 = 0;
+
+// Transfers are free, so the only accepted fee is zero
+const TOKEN_FEE: u64 = 0;
+
+// Longest memo accepted by icrc1_transfer
+const MAX_MEMO_SIZE: usize = 32;
+
+// How long a transfer with created_at_time can be submitted, and deduplicated
+const TRANSFER_WINDOW_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+// How far created_at_time may be ahead of the canister's clock
+const PERMITTED_DRIFT_NS: u64 = 60 * 1_000_000_000;
+
+// Most blocks returned by one icrc3_get_blocks call
+const MAX_BLOCKS_PER_REQUEST: usize = 1_000;
+
+const ICRC1_URL: &str = "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-1";
+const ICRC3_URL: &str = "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-3";
+
+fn generic_error(message: &str) -> TransferError {
+    TransferError::GenericError {
+        error_code: Nat::from(0u64),
+        message: message.to_string(),
+    }
+}
+
+// Parse an optional subaccount; None is the default subaccount
+fn parse_subaccount(subaccount: &Option<Vec<u8>>) -> Option<Subaccount> {
+    match subaccount {
+        Some(bytes) => bytes.as_slice().try_into().ok(),
+        None => Some(DEFAULT_SUBACCOUNT),
+    }
+}
+
+// Account as written to blocks, with the default subaccount left out
+fn block_account(owner: Principal, subaccount: Subaccount) -> Account {
+    Account {
+        owner,
+        subaccount: Some(subaccount.to_vec()).filter(|_| subaccount != DEFAULT_SUBACCOUNT),
+    }
+}
+
+// Identifies a transfer for deduplication: the caller plus every argument
+fn transaction_hash(caller: Principal, arg: &TransferArg) -> [u8; 32] {
+    let bytes = candid::encode_args((caller, arg.clone())).expect("Failed to encode transfer");
+    Sha256::digest(bytes).into()
+}
+
+#[ic_cdk::query]
+fn icrc1_name() -> String {
+    TOKEN_NAME.to_string()
+}
+
+#[ic_cdk::query]
+fn icrc1_symbol() -> String {
+    TOKEN_SYMBOL.to_string()
+}
+
+#[ic_cdk::query]
+fn icrc1_decimals() -> u8 {
+    TOKEN_DECIMALS
+}
+
+#[ic_cdk::query]
+fn icrc1_fee() -> Nat {
+    Nat::from(TOKEN_FEE)
+}
+
+#[ic_cdk::query]
+fn icrc1_metadata() -> Vec<(String, MetadataValue)> {
+    vec![
+        ("icrc1:name".to_string(), MetadataValue::Text(TOKEN_NAME.to_string())),
+        ("icrc1:symbol".to_string(), MetadataValue::Text(TOKEN_SYMBOL.to_string())),
+        ("icrc1:decimals".to_string(), MetadataValue::Nat(Nat::from(TOKEN_DECIMALS))),
+        ("icrc1:fee".to_string(), MetadataValue::Nat(Nat::from(TOKEN_FEE))),
+    ]
+}
+
+#[ic_cdk::query]
+fn icrc1_total_supply() -> Nat {
+    Nat::from(store::get_token_supply())
+}
+
+#[ic_cdk::query]
+fn icrc1_minting_account() -> Option<Account> {
+    Some(Account { owner: api::id(), subaccount: None })
+}
+
+#[ic_cdk::query]
+fn icrc1_balance_of(account: Account) -> Nat {
+    match parse_subaccount(&account.subaccount) {
+        Some(subaccount) => Nat::from(store::get_account_balance(account.owner, &subaccount)),
+        None => Nat::from(0u64),
+    }
+}
+
+#[ic_cdk::query]
+fn icrc1_supported_standards() -> Vec<SupportedStandard> {
+    vec![
+        SupportedStandard { name: "ICRC-1".to_string(), url: ICRC1_URL.to_string() },
+        SupportedStandard { name: "ICRC-3".to_string(), url: ICRC3_URL.to_string() },
+    ]
+}
+
+// Move tokens out of one of the caller's accounts. A transfer carrying
+// created_at_time is rejected as a duplicate if resubmitted within the window.
+// Sending to another principal goes through the same checks as `transfer`, and
+// must carry created_at_time so retries cannot send twice.
+#[ic_cdk::update]
+fn icrc1_transfer(arg: TransferArg) -> Result<Nat, TransferError> {
+    let caller = caller();
+    
+    if caller == Principal::anonymous() {
+        return Err(generic_error("Anonymous callers cannot transfer tokens"));
+    }
+    
+    let (from_subaccount, to_subaccount) = match (parse_subaccount(&arg.from_subaccount), parse_subaccount(&arg.to.subaccount)) {
+        (Some(from_subaccount), Some(to_subaccount)) => (from_subaccount, to_subaccount),
+        _ => return Err(generic_error("Subaccounts must be 32 bytes")),
+    };
+    
+    if arg.fee.as_ref().is_some_and(|fee| fee.0 != TOKEN_FEE.into()) {
+        return Err(TransferError::BadFee { expected_fee: Nat::from(TOKEN_FEE) });
+    }
+    
+    if arg.memo.as_ref().is_some_and(|memo| memo.len() > MAX_MEMO_SIZE) {
+        return Err(generic_error(&format!("Memos are limited to {} bytes", MAX_MEMO_SIZE)));
+    }
+    
+    let amount = match u64::try_from(&arg.amount.0) {
+        Ok(amount) => amount,
+        Err(_) => return Err(generic_error("Amount is too large")),
+    };
+    
+    let is_burn = arg.to.owner == api::id() && to_subaccount == DEFAULT_SUBACCOUNT;
+    if is_burn && amount == 0 {
+        return Err(TransferError::BadBurn { min_burn_amount: Nat::from(1u64) });
+    }
+    
+    // Check the transaction window and deduplicate
+    let now = api::time();
+    let dedup = match arg.created_at_time {
+        Some(created_at_time) => {
+            if created_at_time.saturating_add(TRANSFER_WINDOW_NS + PERMITTED_DRIFT_NS) < now {
+                return Err(TransferError::TooOld);
+            }
+            
+            if created_at_time > now.saturating_add(PERMITTED_DRIFT_NS) {
+                return Err(TransferError::CreatedInFuture { ledger_time: now });
+            }
+            
+            store::prune_transfer_dedup(now.saturating_sub(TRANSFER_WINDOW_NS + PERMITTED_DRIFT_NS));
+            let tx_hash = transaction_hash(caller, &arg);
+            if let Some(block_index) = store::find_duplicate_transfer(&tx_hash) {
+                return Err(TransferError::Duplicate { duplicate_of: Nat::from(block_index) });
+            }
+            
+            Some((tx_hash, created_at_time))
+        }
+        None => None,
+    };
+    
+    // Sends to another principal are peer-to-peer transfers; moves between the
+    // caller's own subaccounts are not
+    let peer_day = if !is_burn && arg.to.owner != caller {
+        if arg.created_at_time.is_none() {
+            return Err(generic_error("Transfers to another principal must set created_at_time"));
+        }
+        Some(handlers::check_peer_transfer(caller, arg.to.owner, amount, now).map_err(|error| generic_error(&error))?)
+    } else {
+        None
+    };
+    
+    let balance = store::get_account_balance(caller, &from_subaccount);
+    if balance < amount {
+        return Err(TransferError::InsufficientFunds { balance: Nat::from(balance) });
+    }
+    
+    let from = block_account(caller, from_subaccount);
+    let (kind, credit) = if is_burn {
+        (EntryKind::Burn, BookAccount::Minting)
+    } else {
+        let to = block_account(arg.to.owner, to_subaccount);
+        (EntryKind::Transfer, BookAccount::User { owner: to.owner, subaccount: to.subaccount })
+    };
+    
+    let entry = store::post_entry(&JournalEntry {
+        id: 0, // Will be assigned by post_entry
+        timestamp: now,
+        kind,
+        debit: BookAccount::User { owner: from.owner, subaccount: from.subaccount },
+        credit,
+        amount,
+        report_id: None,
+        memo: arg.memo.clone(),
+        created_at_time: arg.created_at_time,
+        block_index: None,
+    })
+    .map_err(|error| generic_error(&error))?;
+    let block_index = entry.block_index.expect("User movements are always logged");
+    
+    if let Some((tx_hash, created_at_time)) = dedup {
+        store::record_transfer_for_dedup(tx_hash, created_at_time, block_index);
+    }
+    if let Some(day) = peer_day {
+        store::record_daily_transfer(caller, day, amount);
+    }
+    
+    store::append_audit_entry(
+        caller,
+        "icrc1_transfer",
+        vec![
+            format!("user:{}", caller),
+            format!("user:{}", arg.to.owner),
+            format!("token_block:{}", block_index),
+            format!("journal_entry:{}", entry.id),
+        ],
+        None,
+        Some(store::digest(&entry)),
+    );
+    
+    Ok(Nat::from(block_index))
+}
+
+// Blocks of the token log in ICRC-3 form. Nothing is archived, so every block is
+// served from this canister. Movements tied to a report (stakes, refunds, rewards)
+// are kept out of this public log; reporters see them in get_my_transactions.
+#[ic_cdk::query]
+fn icrc3_get_blocks(args: Vec<GetBlocksArgs>) -> GetBlocksResult {
+    let mut blocks = Vec::new();
+    
+    for range in args {
+        let remaining = MAX_BLOCKS_PER_REQUEST - blocks.len();
+        let length = nat_to_u64(&range.length).min(remaining as u64) as usize;
+        
+        for (id, block) in store::get_token_blocks(nat_to_u64(&range.start), length) {
+            blocks.push(BlockWithId {
+                id: Nat::from(id),
+                block: block.to_value(),
+            });
+        }
+    }
+    
+    GetBlocksResult {
+        log_length: Nat::from(store::get_token_block_count()),
+        blocks,
+        archived_blocks: Vec::new(),
+    }
+}
+
+#[ic_cdk::query]
+fn icrc3_get_archives(_args: GetArchivesArgs) -> Vec<ArchiveInfo> {
+    Vec::new()
+}
+
+#[ic_cdk::query]
+fn icrc3_supported_block_types() -> Vec<SupportedBlockType> {
+    ["1mint", "1burn", "1xfer"]
+        .iter()
+        .map(|block_type| SupportedBlockType {
+            block_type: block_type.to_string(),
+            url: ICRC1_URL.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,257 @@
+// ICRC-1 transfers and the ICRC-3 block log over internal balances
+
+use super::*;
+use crate::authority::ledger::Value;
+use crate::authority::runtime;
+
+const HOLDER: Principal = Principal::from_slice(&[2]);
+const RECIPIENT: Principal = Principal::from_slice(&[3]);
+const STARTING_BALANCE: u64 = 500;
+
+fn setup() {
+    for id in [HOLDER, RECIPIENT] {
+        store::create_or_update_user(User::new(id));
+        store::post_entry_now(EntryKind::Grant, BookAccount::Minting, BookAccount::user(id), STARTING_BALANCE, None).unwrap();
+    }
+    runtime::set_caller(HOLDER);
+}
+
+fn account(owner: Principal, subaccount: Option<Vec<u8>>) -> Account {
+    Account { owner, subaccount }
+}
+
+fn transfer_arg(to: Account, amount: u64) -> TransferArg {
+    TransferArg {
+        from_subaccount: None,
+        to,
+        amount: Nat::from(amount),
+        fee: None,
+        memo: None,
+        created_at_time: Some(api::time()),
+    }
+}
+
+fn balance(owner: Principal, subaccount: Option<Vec<u8>>) -> u64 {
+    nat_to_u64(&icrc1_balance_of(account(owner, subaccount)))
+}
+
+fn generic_message(result: Result<Nat, TransferError>) -> String {
+    match result {
+        Err(TransferError::GenericError { message, .. }) => message,
+        other => panic!("expected a generic error, got {:?}", other),
+    }
+}
+
+fn all_blocks() -> GetBlocksResult {
+    icrc3_get_blocks(vec![GetBlocksArgs { start: Nat::from(0u64), length: Nat::from(1_000u64) }])
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Map(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+        other => panic!("expected a map, got {:?}", other),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn transfer_moves_tokens_and_logs_a_block() {
+    setup();
+    
+    let block_index = icrc1_transfer(transfer_arg(account(RECIPIENT, None), 100)).unwrap();
+    
+    assert_eq!(balance(HOLDER, None), STARTING_BALANCE - 100);
+    assert_eq!(balance(RECIPIENT, None), STARTING_BALANCE + 100);
+    let blocks = all_blocks();
+    assert_eq!(blocks.log_length, Nat::from(3u64));
+    let block = &blocks.blocks[nat_to_u64(&block_index) as usize].block;
+    assert!(matches!(field(block, "btype"), Some(Value::Text(btype)) if btype == "1xfer"));
+}
+
+#[test]
+fn transfer_errors() {
+    setup();
+    let now = api::time();
+    let to = account(RECIPIENT, None);
+    
+    let error = icrc1_transfer(TransferArg { fee: Some(Nat::from(1u64)), ..transfer_arg(to.clone(), 10) }).unwrap_err();
+    assert!(matches!(error, TransferError::BadFee { ref expected_fee } if nat_to_u64(expected_fee) == 0), "{:?}", error);
+    
+    let error = icrc1_transfer(transfer_arg(account(api::id(), None), 0)).unwrap_err();
+    assert!(matches!(error, TransferError::BadBurn { ref min_burn_amount } if nat_to_u64(min_burn_amount) == 1), "{:?}", error);
+    
+    let too_old = now - TRANSFER_WINDOW_NS - PERMITTED_DRIFT_NS - 1;
+    let error = icrc1_transfer(TransferArg { created_at_time: Some(too_old), ..transfer_arg(to.clone(), 10) }).unwrap_err();
+    assert!(matches!(error, TransferError::TooOld), "{:?}", error);
+    
+    let in_future = now + PERMITTED_DRIFT_NS + 1;
+    let error = icrc1_transfer(TransferArg { created_at_time: Some(in_future), ..transfer_arg(to.clone(), 10) }).unwrap_err();
+    assert!(matches!(error, TransferError::CreatedInFuture { ledger_time } if ledger_time == now), "{:?}", error);
+    
+    // Within the permitted drift either way the transfer goes through
+    icrc1_transfer(TransferArg { created_at_time: Some(now + PERMITTED_DRIFT_NS), ..transfer_arg(to.clone(), 1) }).unwrap();
+    
+    let error = icrc1_transfer(transfer_arg(to.clone(), STARTING_BALANCE)).unwrap_err();
+    assert!(matches!(error, TransferError::InsufficientFunds { ref balance } if nat_to_u64(balance) == STARTING_BALANCE - 1), "{:?}", error);
+    
+    let message = generic_message(icrc1_transfer(TransferArg { created_at_time: None, ..transfer_arg(to.clone(), 10) }));
+    assert!(message.contains("created_at_time"), "{}", message);
+    let message = generic_message(icrc1_transfer(TransferArg { memo: Some(vec![0; MAX_MEMO_SIZE + 1]), ..transfer_arg(to, 10) }));
+    assert!(message.contains("Memos"), "{}", message);
+    
+    runtime::set_caller(Principal::anonymous());
+    generic_message(icrc1_transfer(transfer_arg(account(RECIPIENT, None), 10)));
+    
+    // Nothing but the one good transfer moved
+    assert_eq!(balance(HOLDER, None), STARTING_BALANCE - 1);
+}
+
+#[test]
+fn resubmitted_transfers_are_duplicates() {
+    setup();
+    let arg = TransferArg { memo: Some(b"rent".to_vec()), ..transfer_arg(account(RECIPIENT, None), 10) };
+    
+    let block_index = icrc1_transfer(arg.clone()).unwrap();
+    let error = icrc1_transfer(arg.clone()).unwrap_err();
+    assert!(matches!(error, TransferError::Duplicate { ref duplicate_of } if *duplicate_of == block_index), "{:?}", error);
+    assert_eq!(balance(RECIPIENT, None), STARTING_BALANCE + 10);
+    
+    // Any change to the arguments makes a new transfer
+    icrc1_transfer(TransferArg { memo: Some(b"food".to_vec()), ..arg.clone() }).unwrap();
+    
+    // Once the window has passed the transfer is too old rather than a duplicate
+    runtime::advance_time(std::time::Duration::from_nanos(TRANSFER_WINDOW_NS + PERMITTED_DRIFT_NS + 1));
+    assert!(matches!(icrc1_transfer(arg).unwrap_err(), TransferError::TooOld));
+}
+
+#[test]
+fn transfers_to_others_count_against_the_daily_limits() {
+    setup();
+    store::set_transfer_config(TransferConfig { daily_amount_limit: Some(150), daily_count_limit: None });
+    
+    icrc1_transfer(transfer_arg(account(RECIPIENT, None), 100)).unwrap();
+    let message = generic_message(icrc1_transfer(transfer_arg(account(RECIPIENT, None), 60)));
+    assert!(message.contains("150 tokens per day and 100"), "{}", message);
+    let message = generic_message(icrc1_transfer(transfer_arg(account(Principal::from_slice(&[9, 9]), None), 1)));
+    assert!(message.contains("not registered"), "{}", message);
+    
+    // Moving tokens between one's own subaccounts is not a transfer to someone else
+    icrc1_transfer(transfer_arg(account(HOLDER, Some(vec![1; 32])), 300)).unwrap();
+    assert_eq!(balance(HOLDER, Some(vec![1; 32])), 300);
+}
+
+#[test]
+fn subaccounts_are_parsed_and_kept_apart() {
+    setup();
+    let savings = vec![1; 32];
+    
+    // Moving between the caller's own subaccounts needs no created_at_time
+    let to_savings = TransferArg { created_at_time: None, ..transfer_arg(account(HOLDER, Some(savings.clone())), 40) };
+    icrc1_transfer(to_savings).unwrap();
+    assert_eq!(balance(HOLDER, Some(savings.clone())), 40);
+    assert_eq!(balance(HOLDER, None), STARTING_BALANCE - 40);
+    
+    // The all-zero subaccount is the default account
+    assert_eq!(balance(HOLDER, Some(vec![0; 32])), STARTING_BALANCE - 40);
+    
+    let from_savings = TransferArg { from_subaccount: Some(savings.clone()), ..transfer_arg(account(RECIPIENT, None), 15) };
+    icrc1_transfer(from_savings).unwrap();
+    assert_eq!(balance(HOLDER, Some(savings.clone())), 25);
+    assert_eq!(balance(RECIPIENT, None), STARTING_BALANCE + 15);
+    
+    // Blocks leave the default subaccount out and name any other
+    let blocks = all_blocks().blocks;
+    let last = &blocks.last().unwrap().block;
+    let tx = field(last, "tx").unwrap();
+    assert!(matches!(field(tx, "from"), Some(Value::Array(parts)) if parts.len() == 2));
+    assert!(matches!(field(tx, "to"), Some(Value::Array(parts)) if parts.len() == 1));
+    
+    // Anything but 32 bytes is refused, and holds nothing
+    assert_eq!(balance(HOLDER, Some(vec![1; 31])), 0);
+    for bad in [vec![1; 31], vec![1; 33], Vec::new()] {
+        let message = generic_message(icrc1_transfer(transfer_arg(account(RECIPIENT, Some(bad.clone())), 1)));
+        assert!(message.contains("32 bytes"), "{}", message);
+        let message = generic_message(icrc1_transfer(TransferArg { from_subaccount: Some(bad), ..transfer_arg(account(RECIPIENT, None), 1) }));
+        assert!(message.contains("32 bytes"), "{}", message);
+    }
+}
+
+#[test]
+fn transfers_to_the_minting_account_burn() {
+    setup();
+    let supply = nat_to_u64(&icrc1_total_supply());
+    
+    icrc1_transfer(transfer_arg(icrc1_minting_account().unwrap(), 30)).unwrap();
+    
+    assert_eq!(nat_to_u64(&icrc1_total_supply()), supply - 30);
+    assert_eq!(balance(HOLDER, None), STARTING_BALANCE - 30);
+    let blocks = all_blocks().blocks;
+    assert!(matches!(field(&blocks.last().unwrap().block, "btype"), Some(Value::Text(btype)) if btype == "1burn"));
+}
+
+#[test]
+fn blocks_are_hash_chained() {
+    setup();
+    icrc1_transfer(transfer_arg(account(RECIPIENT, None), 10)).unwrap();
+    icrc1_transfer(transfer_arg(account(api::id(), None), 5)).unwrap();
+    
+    let result = all_blocks();
+    assert_eq!(nat_to_u64(&result.log_length), 4);
+    assert!(result.archived_blocks.is_empty());
+    let blocks: Vec<&Value> = result.blocks.iter().map(|block| &block.block).collect();
+    
+    // The first block has no parent; every later block names the hash of the one before
+    assert!(field(blocks[0], "phash").is_none());
+    for pair in blocks.windows(2) {
+        let parent_hash = pair[0].hash();
+        assert!(matches!(field(pair[1], "phash"), Some(Value::Blob(phash)) if phash[..] == parent_hash[..]));
+    }
+    
+    // Ranges are served in part and can be asked for together
+    let partial = icrc3_get_blocks(vec![
+        GetBlocksArgs { start: Nat::from(1u64), length: Nat::from(1u64) },
+        GetBlocksArgs { start: Nat::from(3u64), length: Nat::from(10u64) },
+    ]);
+    let ids: Vec<u64> = partial.blocks.iter().map(|block| nat_to_u64(&block.id)).collect();
+    assert_eq!(ids, vec![1, 3]);
+    assert_eq!(partial.blocks[0].block.hash(), blocks[1].hash());
+}
+
+#[test]
+fn values_hash_as_in_the_icrc3_examples() {
+    assert_eq!(hex(&Value::Nat(Nat::from(42u64)).hash()), "684888c0ebb17f374298b65ee2807526c066094c701bcc7ebbe1c1095f494fc1");
+    assert_eq!(hex(&Value::Text("Hello, World!".to_string()).hash()), "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f");
+    assert_eq!(hex(&Value::Blob(vec![1, 2, 3, 4]).hash()), "9f64a747e1b97f131fabb6b447296c9b6f0201e79fb3c5356e6c77e89b6a806a");
+    
+    let array = Value::Array(vec![
+        Value::Nat(Nat::from(3u64)),
+        Value::Text("foo".to_string()),
+        Value::Blob(vec![5, 6]),
+    ]);
+    assert_eq!(hex(&array.hash()), "514a04011caa503990d446b7dec5d79e19c221ae607fb08b2848c67734d468d6");
+    
+    let from = vec![0x00, 0xab, 0xcd, 0xef, 0x00, 0x12, 0x34, 0x00, 0x56, 0x78, 0x9a, 0x00, 0xbc, 0xde, 0xf0, 0x00, 0x01, 0x23, 0x45, 0x67, 0x89, 0x00, 0xab, 0xcd, 0xef, 0x01];
+    let mut to = from.clone();
+    to[2] = 0x0d;
+    let map = Value::Map(vec![
+        ("from".to_string(), Value::Blob(from)),
+        ("to".to_string(), Value::Blob(to)),
+        ("amount".to_string(), Value::Nat(Nat::from(42u64))),
+        ("created_at".to_string(), Value::Nat(Nat::from(1_699_218_263u64))),
+        ("memo".to_string(), Value::Nat(Nat::from(0u64))),
+    ]);
+    assert_eq!(hex(&map.hash()), "c56ece650e1de4269c5bdeff7875949e3e2033f85b2d193c2ff4f7f78bdcfc75");
+    
+    // Map entries hash the same in any order
+    let reordered = match map {
+        Value::Map(mut entries) => {
+            entries.reverse();
+            Value::Map(entries)
+        }
+        _ => unreachable!(),
+    };
+    assert_eq!(hex(&reordered.hash()), "c56ece650e1de4269c5bdeff7875949e3e2033f85b2d193c2ff4f7f78bdcfc75");
+}
@@ -1,8 +1,9 @@
-use candid::{CandidType, Deserialize, Principal};
+use crate::authority::ledger::{Account, Value};
+use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_stable_structures::{storable::Bound, Storable};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
-use std::convert::TryFrom;
 
 // Report status enum
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
@@ -11,16 +12,346 @@ pub enum ReportStatus {
     UnderReview,
     Approved,
     Rejected,
+    Escalated,
+    Withdrawn,
+    Reopened,
 }
 
-// Evidence file
+impl ReportStatus {
+    // Whether a report in this status still has its stake held in escrow
+    pub fn holds_stake(&self) -> bool {
+        matches!(
+            self,
+            ReportStatus::Pending | ReportStatus::UnderReview | ReportStatus::Escalated | ReportStatus::Reopened
+        )
+    }
+}
+
+// Actions that move a report between statuses
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ReportAction {
+    Claim,
+    Release,
+    Escalate,
+    Verify,
+    Reject,
+    SoftReject,
+    Withdraw,
+    Reopen,
+}
+
+// A single recorded status transition
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct StatusChange {
+    pub report_id: u64,
+    pub action: ReportAction,
+    pub from: ReportStatus,
+    pub to: ReportStatus,
+    pub actor: Principal,
+    pub notes: Option<String>,
+    pub timestamp: u64,
+}
+
+impl Storable for StatusChange {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Evidence file metadata (the content is stored in chunks alongside it)
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
 pub struct EvidenceFile {
     pub id: u64,
+    pub report_id: u64,
     pub name: String,
     pub file_type: String,
-    pub data: Vec<u8>,
+    pub size: u64,
+    pub chunk_count: u32,
+    pub sha256: Vec<u8>, // Of the stored bytes (the ciphertext for encrypted files)
     pub upload_date: u64,
+    pub encryption: Option<EvidenceEncryption>, // None for plaintext evidence
+    pub source_message_id: Option<u64>, // Set when promoted from a message attachment
+}
+
+// Envelope encryption of an evidence file. The client encrypts the file with a random
+// content key and wraps that key for each reader's registered public key.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct EvidenceEncryption {
+    pub algorithm: String,
+    pub nonce: Vec<u8>,
+    pub reporter_key: Option<WrappedKey>, // Wrapped for the reporter's key for the report
+    pub wrapped_keys: Vec<(Principal, WrappedKey)>, // One entry per authority key the content key was wrapped for
+}
+
+impl EvidenceEncryption {
+    pub fn has_reader(&self, id: Principal) -> bool {
+        self.wrapped_keys.iter().any(|(reader, _)| *reader == id)
+    }
+}
+
+// Encryption details supplied when finalizing an encrypted upload. Key IDs come from
+// get_message_keys and are resolved to their owners by the canister.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct EvidenceEncryptionInput {
+    pub algorithm: String,
+    pub nonce: Vec<u8>,
+    pub wrapped_keys: Vec<WrappedKey>,
+}
+
+// An authority who can read a report but has no content key wrapped for their
+// current public key on one of its encrypted evidence files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RewrapKey {
+    pub file_id: u64,
+    pub authority: Principal,
+}
+
+impl Storable for RewrapKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(8 + 29);
+        bytes.extend_from_slice(&self.file_id.to_be_bytes());
+        bytes.extend_from_slice(self.authority.as_slice());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            file_id: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            authority: Principal::from_slice(&bytes[8..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 8 + 29,
+        is_fixed_size: false,
+    };
+}
+
+// How far one participant has read a report's conversation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReadCursorKey {
+    pub report_id: u64,
+    pub reader: Principal,
+}
+
+impl ReadCursorKey {
+    // Every cursor of one report
+    pub fn range(report_id: u64) -> (std::ops::Bound<ReadCursorKey>, std::ops::Bound<ReadCursorKey>) {
+        let start = ReadCursorKey { report_id, reader: Principal::management_canister() };
+        let end = match report_id.checked_add(1) {
+            Some(next) => std::ops::Bound::Excluded(ReadCursorKey { report_id: next, reader: Principal::management_canister() }),
+            None => std::ops::Bound::Unbounded,
+        };
+        (std::ops::Bound::Included(start), end)
+    }
+}
+
+impl Storable for ReadCursorKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(8 + 29);
+        bytes.extend_from_slice(&self.report_id.to_be_bytes());
+        bytes.extend_from_slice(self.reader.as_slice());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            report_id: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            reader: Principal::from_slice(&bytes[8..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 8 + 29,
+        is_fixed_size: false,
+    };
+}
+
+// Read receipts for a conversation: the last message ID each side has read
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReadReceipts {
+    pub report_id: u64,
+    pub reporter_read_up_to: u64,
+    pub authorities: Vec<(Principal, u64)>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct RewrapRequest {
+    pub file_id: u64,
+    pub report_id: u64,
+    pub authority: Principal,
+    pub key_id: u64, // The authority's current key, which the content key must be wrapped for
+    pub requested_at: u64,
+}
+
+impl Storable for EvidenceFile {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// An evidence upload that has been started but not yet finalized
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct EvidenceUpload {
+    pub id: u64,
+    pub report_id: u64,
+    pub uploader: Principal,
+    pub name: String,
+    pub file_type: String,
+    pub total_size: u64,
+    pub received_size: u64,
+    pub started_at: u64,
+    pub purpose: Option<UploadPurpose>, // None for uploads started before attachments existed (evidence)
+}
+
+// Open uploads of one principal ((uploader, upload id) -> ())
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UploaderUploadKey {
+    pub uploader: Principal,
+    pub upload_id: u64,
+}
+
+impl UploaderUploadKey {
+    // Every upload of one principal
+    pub fn range(uploader: Principal) -> std::ops::RangeInclusive<UploaderUploadKey> {
+        UploaderUploadKey { uploader, upload_id: 0 }..=UploaderUploadKey { uploader, upload_id: u64::MAX }
+    }
+}
+
+impl Storable for UploaderUploadKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(29 + 8);
+        bytes.extend_from_slice(self.uploader.as_slice());
+        bytes.extend_from_slice(&self.upload_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let split = bytes.len() - 8;
+        Self {
+            uploader: Principal::from_slice(&bytes[..split]),
+            upload_id: u64::from_be_bytes(bytes[split..].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 29 + 8,
+        is_fixed_size: false,
+    };
+}
+
+// What a chunked upload becomes once finalized
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum UploadPurpose {
+    Evidence,
+    Attachment,
+}
+
+// A finalized upload that can be attached to a message. Attachment bytes are as
+// opaque to the canister as message bodies; clients encrypt them the same way.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct Attachment {
+    pub id: u64,
+    pub report_id: u64,
+    pub uploader: Principal,
+    pub name: String,
+    pub file_type: String,
+    pub size: u64,
+    pub chunk_count: u32,
+    pub sha256: Vec<u8>,
+    pub uploaded_at: u64,
+    pub message_id: Option<u64>, // Set once sent with a message
+    pub evidence_file_id: Option<u64>, // Set once promoted to evidence
+}
+
+impl Storable for Attachment {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Reference to an attachment carried by a message; the bytes are fetched separately
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct MessageAttachment {
+    pub attachment_id: u64,
+    pub name: String,
+    pub file_type: String,
+    pub size: u64,
+    pub sha256: Vec<u8>,
+}
+
+impl From<&Attachment> for MessageAttachment {
+    fn from(attachment: &Attachment) -> Self {
+        MessageAttachment {
+            attachment_id: attachment.id,
+            name: attachment.name.clone(),
+            file_type: attachment.file_type.clone(),
+            size: attachment.size,
+            sha256: attachment.sha256.clone(),
+        }
+    }
+}
+
+impl Storable for EvidenceUpload {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Composite key for chunked blobs (owner is an upload ID or an evidence file ID)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChunkKey {
+    pub owner_id: u64,
+    pub index: u32,
+}
+
+impl Storable for ChunkKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(12);
+        bytes.extend_from_slice(&self.owner_id.to_be_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            owner_id: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            index: u32::from_be_bytes(bytes[8..12].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 12,
+        is_fixed_size: true,
+    };
 }
 
 // Location data
@@ -50,10 +381,11 @@ pub struct Report {
     pub reviewer: Option<Principal>,
     pub review_date: Option<u64>,
     pub review_notes: Option<String>,
+    pub reward_config_version: Option<u64>, // None for reports submitted before config versioning (version 1)
 }
 
 impl Storable for Report {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         let bytes = candid::encode_one(self).unwrap();
         Cow::Owned(bytes)
     }
@@ -62,9 +394,7 @@ impl Storable for Report {
         candid::decode_one(&bytes).unwrap()
     }
 
-    fn stable_bound() -> Bound {
-        Bound::Unbounded
-    }
+    const BOUND: Bound = Bound::Unbounded;
 }
 
 // Message for communication between authority and informer
@@ -73,9 +403,27 @@ pub struct Message {
     pub id: u64,
     pub report_id: u64,
     pub sender: MessageSender,
-    pub content: String,
+    pub content: String, // Plaintext, only used for system messages
     pub timestamp: u64,
-    pub attachment: Option<Vec<u8>>,
+    pub attachment: Option<MessageAttachment>, // Stored records with the old inline blob (always empty) decode as None
+    pub encrypted: Option<EncryptedContent>, // None for system messages and messages sent before encryption
+}
+
+// Message body encrypted by the sender. The content key is wrapped once per recipient
+// public key, so the canister only stores and routes opaque bytes.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct EncryptedContent {
+    pub algorithm: String,
+    pub sender_key_id: u64,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub wrapped_keys: Vec<WrappedKey>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct WrappedKey {
+    pub key_id: u64,
+    pub wrapped_key: Vec<u8>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
@@ -86,7 +434,97 @@ pub enum MessageSender {
 }
 
 impl Storable for Message {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Who a registered public key belongs to. Reporter keys are registered per report
+// so that the registry cannot link reports filed by the same person.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum KeyOwner {
+    Reporter { report_id: u64 },
+    Authority(Principal),
+}
+
+// Public key in the messaging key registry. Keys are never deleted: a rotated key
+// keeps its ID so that messages wrapped for it stay readable by its holder.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct PublicKeyRecord {
+    pub id: u64,
+    pub owner: KeyOwner,
+    pub algorithm: String,
+    pub public_key: Vec<u8>,
+    pub registered_at: u64,
+    pub superseded_at: Option<u64>,
+}
+
+impl Storable for PublicKeyRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Notification event types, used for inbox preferences
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum NotificationKind {
+    StatusChanged,
+    RewardCredited,
+    StakeLost,
+    NewMessage,
+    EvidenceRequested,
+    NewReport,
+}
+
+// What happened, with the details an inbox needs to render it
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub enum NotificationEvent {
+    StatusChanged { report_id: u64, from: ReportStatus, to: ReportStatus },
+    RewardCredited { report_id: u64, amount: u64 },
+    StakeLost { report_id: u64, amount: u64 },
+    NewMessage { report_id: u64, message_id: u64 },
+    EvidenceRequested { report_id: u64, note: Option<String> },
+    NewReport { report_id: u64, category: String },
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationEvent::StatusChanged { .. } => NotificationKind::StatusChanged,
+            NotificationEvent::RewardCredited { .. } => NotificationKind::RewardCredited,
+            NotificationEvent::StakeLost { .. } => NotificationKind::StakeLost,
+            NotificationEvent::NewMessage { .. } => NotificationKind::NewMessage,
+            NotificationEvent::EvidenceRequested { .. } => NotificationKind::EvidenceRequested,
+            NotificationEvent::NewReport { .. } => NotificationKind::NewReport,
+        }
+    }
+}
+
+// Entry in a principal's notification inbox
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct Notification {
+    pub id: u64,
+    pub event: NotificationEvent,
+    pub created_at: u64,
+    pub read: bool,
+}
+
+impl Storable for Notification {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         let bytes = candid::encode_one(self).unwrap();
         Cow::Owned(bytes)
     }
@@ -95,9 +533,62 @@ impl Storable for Message {
         candid::decode_one(&bytes).unwrap()
     }
 
-    fn stable_bound() -> Bound {
-        Bound::Unbounded
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Inbox key: a recipient's notifications are stored together in ID order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NotificationKey {
+    pub recipient: Principal,
+    pub id: u64,
+}
+
+impl NotificationKey {
+    // Every notification of one recipient
+    pub fn range(recipient: Principal) -> std::ops::RangeInclusive<NotificationKey> {
+        NotificationKey { recipient, id: 0 }..=NotificationKey { recipient, id: u64::MAX }
+    }
+}
+
+impl Storable for NotificationKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(29 + 8);
+        bytes.extend_from_slice(self.recipient.as_slice());
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let split = bytes.len() - 8;
+        Self {
+            recipient: Principal::from_slice(&bytes[..split]),
+            id: u64::from_be_bytes(bytes[split..].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 29 + 8,
+        is_fixed_size: false,
+    };
+}
+
+// Event types a principal does not want in their inbox (everything is on by default)
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize)]
+pub struct NotificationPreferences {
+    pub muted: Vec<NotificationKind>,
+}
+
+impl Storable for NotificationPreferences {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
     }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
 // User structure
@@ -111,8 +602,22 @@ pub struct User {
     pub stakes_lost: u64,
 }
 
+impl User {
+    // A user with no balance or history
+    pub fn new(id: Principal) -> Self {
+        User {
+            id,
+            token_balance: 0,
+            reports_submitted: Vec::new(),
+            rewards_earned: 0,
+            stakes_active: 0,
+            stakes_lost: 0,
+        }
+    }
+}
+
 impl Storable for User {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         let bytes = candid::encode_one(self).unwrap();
         Cow::Owned(bytes)
     }
@@ -121,8 +626,83 @@ impl Storable for User {
         candid::decode_one(&bytes).unwrap()
     }
 
-    fn stable_bound() -> Bound {
-        Bound::Unbounded
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Roles an authority can hold
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum AuthorityRole {
+    Admin,
+    Reviewer,
+    Investigator,
+    Auditor,
+    FinanceOfficer,
+    Partner, // Partner organisation that vets reporters and hands out invite codes
+}
+
+// Individual capabilities checked by handlers
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Permission {
+    ViewReports,
+    ReviewReports,
+    VerifyReports,
+    MessageReporters,
+    ViewStatistics,
+    ViewAuditLog,
+    ManageCategories,
+    ManageRewardConfig,
+    ManageAuthorities,
+    ViewFinances,
+    IssueVouchers,
+    ResetData,
+}
+
+impl AuthorityRole {
+    // Permission matrix
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            AuthorityRole::Admin => &[
+                Permission::ViewReports,
+                Permission::ReviewReports,
+                Permission::VerifyReports,
+                Permission::MessageReporters,
+                Permission::ViewStatistics,
+                Permission::ViewAuditLog,
+                Permission::ManageCategories,
+                Permission::ManageRewardConfig,
+                Permission::ManageAuthorities,
+                Permission::ViewFinances,
+                Permission::IssueVouchers,
+                Permission::ResetData,
+            ],
+            AuthorityRole::Reviewer => &[
+                Permission::ViewReports,
+                Permission::ReviewReports,
+                Permission::VerifyReports,
+                Permission::MessageReporters,
+                Permission::ViewStatistics,
+            ],
+            AuthorityRole::Investigator => &[
+                Permission::ViewReports,
+                Permission::ReviewReports,
+                Permission::MessageReporters,
+            ],
+            AuthorityRole::Auditor => &[
+                Permission::ViewReports,
+                Permission::ViewStatistics,
+                Permission::ViewAuditLog,
+                Permission::ViewFinances,
+            ],
+            AuthorityRole::FinanceOfficer => &[
+                Permission::ViewReports,
+                Permission::ViewStatistics,
+                Permission::ManageRewardConfig,
+                Permission::ViewFinances,
+            ],
+            AuthorityRole::Partner => &[
+                Permission::IssueVouchers,
+            ],
+        }
     }
 }
 
@@ -132,36 +712,1254 @@ pub struct Authority {
     pub id: Principal,
     pub reports_reviewed: Vec<u64>,
     pub approval_rate: f64,
+    pub roles: Vec<AuthorityRole>,
+}
+
+impl Authority {
+    pub fn has_role(&self, role: AuthorityRole) -> bool {
+        self.roles.contains(&role)
+    }
+    
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.roles.iter().any(|role| role.permissions().contains(&permission))
+    }
+}
+
+// Authority record as stored before roles were introduced
+#[derive(CandidType, Deserialize)]
+struct LegacyAuthority {
+    id: Principal,
+    reports_reviewed: Vec<u64>,
+    approval_rate: f64,
 }
 
 impl Storable for Authority {
-    fn to_bytes(&self) -> Cow<[u8]> {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         let bytes = candid::encode_one(self).unwrap();
         Cow::Owned(bytes)
     }
 
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).unwrap()
+        // Authorities stored before roles existed could do everything, so they
+        // become admins
+        candid::decode_one(&bytes).unwrap_or_else(|_| {
+            let legacy: LegacyAuthority = candid::decode_one(&bytes).unwrap();
+            Authority {
+                id: legacy.id,
+                reports_reviewed: legacy.reports_reviewed,
+                approval_rate: legacy.approval_rate,
+                roles: vec![AuthorityRole::Admin],
+            }
+        })
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Rules for multi-signature approval of sensitive actions
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct GovernanceConfig {
+    pub reward_approval_threshold: u64, // Rewards above this need a proposal
+    pub required_approvals: u32,        // Distinct authorities needed to execute
+    pub proposal_ttl_ns: u64,           // How long a proposal stays open
+}
+
+impl Storable for GovernanceConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
     }
 
-    fn stable_bound() -> Bound {
-        Bound::Unbounded
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
     }
+
+    const BOUND: Bound = Bound::Unbounded;
 }
 
-// Configuration for token rewards
+// Actions that can be put up for multi-signature approval
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
-pub struct RewardConfig {
-    pub reward_multiplier: u64,  // Multiplier for rewards (e.g., 10x stake)
-    pub min_stake_amount: u64,   // Minimum amount to stake
-    pub max_stake_amount: u64,   // Maximum amount to stake
+pub enum ProposalAction {
+    VerifyReport { report_id: u64, notes: Option<String> },
+    AddAuthority { id: Principal },
+    RemoveAuthority { id: Principal },
+    GrantRole { id: Principal, role: AuthorityRole },
+    RevokeRole { id: Principal, role: AuthorityRole },
+    UpdateGovernanceConfig { config: GovernanceConfig },
 }
 
-// Statistics for authority dashboard
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ProposalStatus {
+    Open,
+    Executed,
+    Failed,
+    Rejected,
+    Expired,
+}
+
+// A pending or resolved multi-signature proposal
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
-pub struct AuthorityStats {
-    pub reports_pending: u64,
-    pub reports_verified: u64,
-    pub reports_rejected: u64,
-    pub total_rewards_distributed: u64,
-}
\ No newline at end of file
+pub struct Proposal {
+    pub id: u64,
+    pub action: ProposalAction,
+    pub proposer: Principal,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub required_approvals: u32,
+    pub approvals: Vec<Principal>,
+    pub rejections: Vec<Principal>,
+    pub status: ProposalStatus,
+    pub resolved_at: Option<u64>,
+    pub execution_result: Option<String>,
+}
+
+impl Storable for Proposal {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// What happened to an action that may need approval
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub enum ActionOutcome {
+    Executed,
+    PendingApproval { proposal_id: u64 },
+}
+
+// Report category managed by authorities
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct Category {
+    pub id: String, // Stable slug stored on reports, e.g. "environmental"
+    pub name: String,
+    pub description: String,
+    pub parent_id: Option<String>,
+    pub min_stake_amount: Option<u64>, // Tightens the global minimum when set
+    pub max_stake_amount: Option<u64>, // Tightens the global maximum when set
+    pub active: bool,
+}
+
+impl Storable for Category {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Entry in the append-only, hash-chained audit log
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub id: u64,
+    pub caller: Principal,
+    pub method: String,
+    pub targets: Vec<String>, // e.g. "report:12", "user:<principal>"
+    pub before_digest: Option<Vec<u8>>,
+    pub after_digest: Option<Vec<u8>>,
+    pub timestamp: u64,
+    pub prev_hash: Vec<u8>,
+    pub hash: Vec<u8>,
+}
+
+impl AuditEntry {
+    // SHA-256 over every field except `hash`, with length prefixes so that
+    // field boundaries cannot be shifted
+    pub fn compute_hash(&self) -> Vec<u8> {
+        fn put(hasher: &mut Sha256, bytes: &[u8]) {
+            hasher.update((bytes.len() as u64).to_be_bytes());
+            hasher.update(bytes);
+        }
+        
+        fn put_opt(hasher: &mut Sha256, bytes: &Option<Vec<u8>>) {
+            match bytes {
+                Some(bytes) => {
+                    hasher.update([1u8]);
+                    put(hasher, bytes);
+                }
+                None => hasher.update([0u8]),
+            }
+        }
+        
+        let mut hasher = Sha256::new();
+        hasher.update(self.id.to_be_bytes());
+        put(&mut hasher, self.caller.as_slice());
+        put(&mut hasher, self.method.as_bytes());
+        hasher.update((self.targets.len() as u64).to_be_bytes());
+        for target in &self.targets {
+            put(&mut hasher, target.as_bytes());
+        }
+        put_opt(&mut hasher, &self.before_digest);
+        put_opt(&mut hasher, &self.after_digest);
+        hasher.update(self.timestamp.to_be_bytes());
+        put(&mut hasher, &self.prev_hash);
+        hasher.finalize().to_vec()
+    }
+}
+
+impl Storable for AuditEntry {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Result of recomputing the audit hash chain
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct AuditChainStatus {
+    pub entries_checked: u64,
+    pub valid: bool,
+    pub first_invalid_id: Option<u64>,
+}
+
+// Configuration for token rewards
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct RewardConfig {
+    pub version: u64,            // Incremented on every change
+    pub reward_multiplier: u64,  // Multiplier for rewards (e.g., 10x stake)
+    pub min_stake_amount: u64,   // Minimum amount to stake
+    pub max_stake_amount: u64,   // Maximum amount to stake
+}
+
+impl Storable for RewardConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Per-sender limits on peer-to-peer transfers, per UTC day (None = no limit)
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct TransferConfig {
+    pub daily_amount_limit: Option<u64>,
+    pub daily_count_limit: Option<u64>,
+}
+
+impl Storable for TransferConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Rules for granting starter tokens to new reporters
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct OnboardingConfig {
+    pub grant_amount: u64,               // Tokens granted by claim_onboarding_grant
+    pub open_grants: bool,               // Whether grants can be claimed without an invite code
+    pub daily_grant_budget: u64,         // Most tokens granted per UTC day, invite codes included
+    pub grant_cooldown_ns: Option<u64>,  // Wait between open grants to one principal (None = only one); invite codes are exempt
+    pub max_voucher_amount: u64,         // Largest grant one invite code can carry
+}
+
+impl Storable for OnboardingConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Invite code issued by an authority or partner. Only a hash of the code is kept.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct Voucher {
+    pub id: u64,
+    pub issuer: Principal,
+    pub amount: u64,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+    pub redeemed_by: Option<Principal>,
+    pub redeemed_at: Option<u64>,
+}
+
+impl Storable for Voucher {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Where slashed stakes go, as percentages of the stake that add up to 100.
+// Rounding leftovers are burned.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct SlashingPolicy {
+    pub burn_percent: u64,
+    pub treasury_percent: u64,
+    pub reviewer_pool_percent: u64,
+    pub soft_reject_refund_percent: u64, // Share of the stake refunded on a soft reject
+    pub withdrawal_refund_percent: u64,  // Share of the stake refunded when the reporter withdraws
+}
+
+// Slashing policy as stored before the withdrawal refund was configurable
+#[derive(CandidType, Deserialize)]
+struct LegacySlashingPolicy {
+    burn_percent: u64,
+    treasury_percent: u64,
+    reviewer_pool_percent: u64,
+    soft_reject_refund_percent: u64,
+}
+
+impl Storable for SlashingPolicy {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        // Withdrawals used to refund a fixed half of the stake
+        candid::decode_one(&bytes).unwrap_or_else(|_| {
+            let legacy: LegacySlashingPolicy = candid::decode_one(&bytes).unwrap();
+            SlashingPolicy {
+                burn_percent: legacy.burn_percent,
+                treasury_percent: legacy.treasury_percent,
+                reviewer_pool_percent: legacy.reviewer_pool_percent,
+                soft_reject_refund_percent: legacy.soft_reject_refund_percent,
+                withdrawal_refund_percent: 50,
+            }
+        })
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Grounds for rejecting a report without holding it against the reporter
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SoftRejectReason {
+    Duplicate,
+    OutOfJurisdiction,
+}
+
+// How often the reward payout cap resets (UTC days and calendar months)
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum PayoutPeriod {
+    Daily,
+    Monthly,
+}
+
+// Limits on the rewards paid out per period
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct TreasuryConfig {
+    pub payout_cap: Option<u64>, // Most reward tokens paid per period (None = no cap)
+    pub cap_period: PayoutPeriod,
+}
+
+impl Storable for TreasuryConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Treasury movements on one UTC day
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize)]
+pub struct TreasuryDay {
+    pub inflow: u64,
+    pub outflow: u64,
+    pub rewards_paid: u64, // Counted against the payout cap, in either staking mode
+}
+
+impl Storable for TreasuryDay {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Treasury position for finance authorities. `days` covers the requested range,
+// keyed by the start of each day; the totals cover all time.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct TreasuryReport {
+    pub balance: u64,
+    pub total_inflow: u64,
+    pub total_outflow: u64,
+    pub total_rewards_paid: u64,
+    pub config: TreasuryConfig,
+    pub period_rewards_paid: u64,         // Paid so far in the current cap period
+    pub period_remaining: Option<u64>,    // Left under the cap this period
+    pub days: Vec<(u64, TreasuryDay)>,
+}
+
+// Canister init arguments. Setting a ledger canister switches staking and payouts
+// from internal balances to that ICRC-1/ICRC-2 ledger. Proposals need distinct
+// approvers, so install with at least as many admins as the governance config requires.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct InitArgs {
+    pub ledger_canister_id: Option<Principal>,
+    pub ledger_fee: Option<u64>,
+    pub admins: Option<Vec<Principal>>,
+}
+
+// Where stakes and rewards are held. Fixed at init.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct LedgerConfig {
+    pub ledger_canister_id: Option<Principal>, // None = internal balances
+    pub fee: u64,                              // Last known ledger transfer fee
+    pub treasury_balance: Option<u64>,         // Canister account balance at the last check (None = not yet checked)
+}
+
+impl Storable for LedgerConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Why the canister is moving tokens on the ledger
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum LedgerTransferKind {
+    StakeRefund,               // Verified report: stake returned from escrow
+    Reward,                    // Verified report: reward paid from the treasury
+    WithdrawalRefund,          // Withdrawn report: partial refund from escrow
+    UnconfirmedStake,          // Stake call with unknown outcome: return whatever reached escrow
+    SlashToTreasury,           // Slashed stake: treasury share, from escrow to the canister's account
+    SlashToReviewerPool,       // Slashed stake: reviewer share, from escrow to the reviewer pool subaccount
+    SlashBurn,                 // Slashed stake: burned share, from escrow to the ledger's minting account
+    ReinstateFromTreasury,     // Reopened report: stake put back in escrow from the canister's account
+    ReinstateFromReviewerPool, // Reopened report: reviewer share put back in escrow
+}
+
+impl LedgerTransferKind {
+    // Refunds and slashes come out of the report's escrow, everything else from the canister's accounts
+    pub fn paid_from_escrow(&self) -> bool {
+        !matches!(
+            self,
+            LedgerTransferKind::Reward
+                | LedgerTransferKind::ReinstateFromTreasury
+                | LedgerTransferKind::ReinstateFromReviewerPool
+        )
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum LedgerTransferStatus {
+    Pending,
+    InFlight,
+    Completed { block_index: u64 },
+    Failed { error: String },
+}
+
+// An outgoing ledger transfer. Transfers are queued by synchronous state changes
+// and sent by a timer; retries reuse created_at_time so the ledger deduplicates them.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct LedgerTransfer {
+    pub id: u64,
+    pub report_id: u64,
+    pub kind: LedgerTransferKind,
+    pub to: Principal,     // The reporter, or the canister itself for slashes and reinstatements
+    pub amount: u64,       // Debited from the source; escrow payouts have the fee taken out of it
+    pub fee: Option<u64>,  // Fee of the last attempt, reused on retry so the ledger can deduplicate
+    pub created_at_time: u64,
+    pub attempts: u32,
+    pub last_attempt_at: Option<u64>,
+    pub status: LedgerTransferStatus,
+}
+
+impl Storable for LedgerTransfer {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// ICRC-1 subaccount; all zeroes is the default subaccount
+pub type Subaccount = [u8; 32];
+
+pub const DEFAULT_SUBACCOUNT: Subaccount = [0; 32];
+
+// Balance key for a non-default subaccount (default subaccounts live in User)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AccountKey {
+    pub owner: Principal,
+    pub subaccount: Subaccount,
+}
+
+impl Storable for AccountKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(32 + 29);
+        bytes.extend_from_slice(&self.subaccount);
+        bytes.extend_from_slice(self.owner.as_slice());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            subaccount: bytes[0..32].try_into().unwrap(),
+            owner: Principal::from_slice(&bytes[32..]),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 32 + 29,
+        is_fixed_size: false,
+    };
+}
+
+// What a token block did to balances
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub enum TokenOperation {
+    Mint { to: Account },
+    Burn { from: Account },
+    Transfer { from: Account, to: Account, fee: Option<u64> }, // fee as given by the caller
+}
+
+// One entry of the internal token's block log, hash-chained ICRC-3 style
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct TokenBlock {
+    pub parent_hash: Option<Vec<u8>>,
+    pub timestamp: u64,
+    pub operation: TokenOperation,
+    pub amount: u64,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+impl TokenBlock {
+    // ICRC-3 encoding of the block; its hash is the next block's parent hash
+    pub fn to_value(&self) -> Value {
+        fn account_value(account: &Account) -> Value {
+            let mut parts = vec![Value::Blob(account.owner.as_slice().to_vec())];
+            if let Some(subaccount) = &account.subaccount {
+                parts.push(Value::Blob(subaccount.clone()));
+            }
+            Value::Array(parts)
+        }
+        
+        let mut tx = vec![("amt".to_string(), Value::Nat(Nat::from(self.amount)))];
+        let block_type = match &self.operation {
+            TokenOperation::Mint { to } => {
+                tx.push(("to".to_string(), account_value(to)));
+                "1mint"
+            }
+            TokenOperation::Burn { from } => {
+                tx.push(("from".to_string(), account_value(from)));
+                "1burn"
+            }
+            TokenOperation::Transfer { from, to, fee } => {
+                tx.push(("from".to_string(), account_value(from)));
+                tx.push(("to".to_string(), account_value(to)));
+                if let Some(fee) = fee {
+                    tx.push(("fee".to_string(), Value::Nat(Nat::from(*fee))));
+                }
+                "1xfer"
+            }
+        };
+        if let Some(memo) = &self.memo {
+            tx.push(("memo".to_string(), Value::Blob(memo.clone())));
+        }
+        if let Some(created_at_time) = self.created_at_time {
+            tx.push(("ts".to_string(), Value::Nat(Nat::from(created_at_time))));
+        }
+        
+        let mut block = Vec::new();
+        if let Some(parent_hash) = &self.parent_hash {
+            block.push(("phash".to_string(), Value::Blob(parent_hash.clone())));
+        }
+        block.push(("ts".to_string(), Value::Nat(Nat::from(self.timestamp))));
+        block.push(("btype".to_string(), Value::Text(block_type.to_string())));
+        block.push(("tx".to_string(), Value::Map(tx)));
+        Value::Map(block)
+    }
+}
+
+impl Storable for TokenBlock {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// A book in the internal token journal
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BookAccount {
+    User { owner: Principal, subaccount: Option<Vec<u8>> }, // Spendable balance (None = default subaccount)
+    Escrow { report_id: u64 },                              // Stake held while a report is undecided
+    Minting,                                                // Issues and burns tokens; has no balance
+    SlashedPool,                                            // Stakes lost before the slashing policy
+    Treasury,                                               // Pays out rewards
+    ReviewerPool,                                           // Reviewer incentives from slashed stakes
+}
+
+impl BookAccount {
+    pub fn user(owner: Principal) -> Self {
+        BookAccount::User { owner, subaccount: None }
+    }
+    
+    // Storage key of a canister-owned book. For the minting account the stored
+    // value is the supply it has issued and not yet burned.
+    pub fn system_key(&self) -> Option<u8> {
+        match self {
+            BookAccount::Minting => Some(0),
+            BookAccount::SlashedPool => Some(1),
+            BookAccount::Treasury => Some(2),
+            BookAccount::ReviewerPool => Some(3),
+            BookAccount::User { .. } | BookAccount::Escrow { .. } => None,
+        }
+    }
+}
+
+// Why tokens moved
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum EntryKind {
+    OpeningBalance, // Balance that existed before the journal did
+    Grant,
+    StakeLock,
+    StakeRefund,
+    StakeSlash,
+    StakeReinstate, // A reopened report's slashed stake put back in escrow
+    RewardMint,     // Reward issued into the treasury (before it was funded)
+    TreasuryFunding,
+    RewardPayout,   // Reward paid from the treasury to a reporter
+    Transfer,
+    Burn,
+}
+
+// One immutable movement of `amount` tokens from the debit account to the credit account
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub kind: EntryKind,
+    pub debit: BookAccount,
+    pub credit: BookAccount,
+    pub amount: u64,
+    pub report_id: Option<u64>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+    pub block_index: Option<u64>, // ICRC-3 block recording the movement, if it touched a user outside a report
+}
+
+impl JournalEntry {
+    // How the movement looks to ICRC-1 holders: between users it is a transfer;
+    // tokens leaving or reaching users through other books are burned or minted.
+    // The block log is public, so stake, refund and reward movements tied to a
+    // report get no block: it would link the reporter's principal to the report.
+    pub fn token_operation(&self) -> Option<TokenOperation> {
+        fn account(owner: &Principal, subaccount: &Option<Vec<u8>>) -> Account {
+            Account { owner: *owner, subaccount: subaccount.clone() }
+        }
+        
+        if self.report_id.is_some() {
+            return None;
+        }
+        
+        match (&self.debit, &self.credit) {
+            (BookAccount::User { owner: from, subaccount: from_sub }, BookAccount::User { owner: to, subaccount: to_sub }) => {
+                Some(TokenOperation::Transfer { from: account(from, from_sub), to: account(to, to_sub), fee: None })
+            }
+            (BookAccount::User { owner, subaccount }, _) => Some(TokenOperation::Burn { from: account(owner, subaccount) }),
+            (_, BookAccount::User { owner, subaccount }) => Some(TokenOperation::Mint { to: account(owner, subaccount) }),
+            _ => None,
+        }
+    }
+}
+
+impl Storable for JournalEntry {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Journal entries involving one principal ((party, entry id) -> ())
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PartyEntryKey {
+    pub party: Principal,
+    pub entry_id: u64,
+}
+
+impl Storable for PartyEntryKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(29 + 8);
+        bytes.extend_from_slice(self.party.as_slice());
+        bytes.extend_from_slice(&self.entry_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let split = bytes.len() - 8;
+        Self {
+            party: Principal::from_slice(&bytes[..split]),
+            entry_id: u64::from_be_bytes(bytes[split..].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 29 + 8,
+        is_fixed_size: false,
+    };
+}
+
+// Result of check_ledger_invariants. The books balance when `violations` is empty.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct LedgerInvariants {
+    pub total_supply: u64,     // Issued by the minting account and not burned
+    pub user_balances: u64,
+    pub escrow_balances: u64,
+    pub slashed_pool: u64,
+    pub treasury: u64,
+    pub reviewer_pool: u64,
+    pub violations: Vec<String>,
+}
+
+// Statistics for authority dashboard
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct AuthorityStats {
+    pub reports_pending: u64,
+    pub reports_verified: u64,
+    pub reports_rejected: u64,
+    pub reports_under_review: u64,
+    pub reports_escalated: u64,
+    pub reports_withdrawn: u64,
+    pub reports_reopened: u64,
+    pub total_rewards_distributed: u64,
+}
+
+impl AuthorityStats {
+    // The counter tracking reports currently in `status`
+    pub fn count_for(&mut self, status: &ReportStatus) -> &mut u64 {
+        match status {
+            ReportStatus::Pending => &mut self.reports_pending,
+            ReportStatus::UnderReview => &mut self.reports_under_review,
+            ReportStatus::Approved => &mut self.reports_verified,
+            ReportStatus::Rejected => &mut self.reports_rejected,
+            ReportStatus::Escalated => &mut self.reports_escalated,
+            ReportStatus::Withdrawn => &mut self.reports_withdrawn,
+            ReportStatus::Reopened => &mut self.reports_reopened,
+        }
+    }
+}
+
+impl Storable for AuthorityStats {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let bytes = candid::encode_one(self).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).unwrap()
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Composite key for per-report indexes (messages, status changes, ...)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReportItemKey {
+    pub report_id: u64,
+    pub item_id: u64,
+}
+
+impl ReportItemKey {
+    // All keys belonging to one report
+    pub fn range(report_id: u64) -> std::ops::RangeInclusive<ReportItemKey> {
+        ReportItemKey { report_id, item_id: 0 }..=ReportItemKey { report_id, item_id: u64::MAX }
+    }
+}
+
+impl Storable for ReportItemKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&self.report_id.to_be_bytes());
+        bytes.extend_from_slice(&self.item_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Self {
+            report_id: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            item_id: u64::from_be_bytes(bytes[8..16].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 16,
+        is_fixed_size: true,
+    };
+}
+
+impl ReportStatus {
+    // Stable one-byte tag used in index keys
+    pub fn index_tag(&self) -> u8 {
+        match self {
+            ReportStatus::Pending => 0,
+            ReportStatus::UnderReview => 1,
+            ReportStatus::Approved => 2,
+            ReportStatus::Rejected => 3,
+            ReportStatus::Escalated => 4,
+            ReportStatus::Withdrawn => 5,
+            ReportStatus::Reopened => 6,
+        }
+    }
+}
+
+// Secondary indexes over reports. Each index groups reports into buckets
+// (e.g. one per status) ordered by a sort value, then by report id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReportIndex {
+    Date,
+    Stake,
+    StatusDate(ReportStatus),
+    CategoryDate(String),
+    Submitter(Principal),
+}
+
+impl ReportIndex {
+    // Every index entry a report should have
+    pub fn all_for(report: &Report) -> Vec<ReportIndex> {
+        vec![
+            ReportIndex::Date,
+            ReportIndex::Stake,
+            ReportIndex::StatusDate(report.status.clone()),
+            ReportIndex::CategoryDate(report.category.clone()),
+            ReportIndex::Submitter(report.submitter_id),
+        ]
+    }
+    
+    // Key prefix identifying this index and bucket
+    pub fn prefix(&self) -> Vec<u8> {
+        match self {
+            ReportIndex::Date => vec![0],
+            ReportIndex::Stake => vec![1],
+            ReportIndex::StatusDate(status) => vec![2, status.index_tag()],
+            ReportIndex::CategoryDate(category) => {
+                // Category ids are validated to be short; truncation only guards legacy data
+                let bytes = category.as_bytes();
+                let mut prefix = vec![3];
+                prefix.extend_from_slice(&bytes[..bytes.len().min(MAX_INDEX_PREFIX_LEN - 1)]);
+                prefix
+            }
+            ReportIndex::Submitter(submitter) => {
+                let mut prefix = vec![4];
+                prefix.extend_from_slice(submitter.as_slice());
+                prefix
+            }
+        }
+    }
+    
+    pub fn sort_value(&self, report: &Report) -> u64 {
+        match self {
+            ReportIndex::Stake => report.stake_amount,
+            ReportIndex::Date | ReportIndex::StatusDate(_) | ReportIndex::CategoryDate(_) => report.date_submitted,
+            ReportIndex::Submitter(_) => report.id,
+        }
+    }
+    
+    pub fn key(&self, report: &Report) -> IndexKey {
+        IndexKey {
+            prefix: self.prefix(),
+            sort_value: self.sort_value(report),
+            report_id: report.id,
+        }
+    }
+}
+
+// Key in the report index map: (index prefix, sort value, report id)
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IndexKey {
+    pub prefix: Vec<u8>,
+    pub sort_value: u64,
+    pub report_id: u64,
+}
+
+// Longest prefix an index key can hold (tag byte plus a category id)
+pub const MAX_INDEX_PREFIX_LEN: usize = 128;
+
+impl Storable for IndexKey {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        let mut bytes = Vec::with_capacity(1 + self.prefix.len() + 16);
+        bytes.push(self.prefix.len() as u8);
+        bytes.extend_from_slice(&self.prefix);
+        bytes.extend_from_slice(&self.sort_value.to_be_bytes());
+        bytes.extend_from_slice(&self.report_id.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let prefix_len = bytes[0] as usize;
+        let rest = &bytes[1 + prefix_len..];
+        Self {
+            prefix: bytes[1..1 + prefix_len].to_vec(),
+            sort_value: u64::from_be_bytes(rest[0..8].try_into().unwrap()),
+            report_id: u64::from_be_bytes(rest[8..16].try_into().unwrap()),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: (1 + MAX_INDEX_PREFIX_LEN + 16) as u32,
+        is_fixed_size: false,
+    };
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ReportSortKey {
+    DateSubmitted,
+    StakeAmount,
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+// Position in a report listing; pass the `next_cursor` of one page to get the next
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReportCursor {
+    pub sort_value: u64,
+    pub report_id: u64,
+}
+
+// Filters, ordering and paging for list_reports
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReportQuery {
+    pub status: Option<ReportStatus>,
+    pub category: Option<String>,
+    pub submitted_from: Option<u64>, // Inclusive
+    pub submitted_to: Option<u64>,   // Inclusive
+    pub reviewer: Option<Principal>,
+    pub min_stake: Option<u64>,
+    pub max_stake: Option<u64>,
+    pub has_evidence: Option<bool>,
+    pub sort_by: ReportSortKey,
+    pub direction: SortDirection,
+    pub cursor: Option<ReportCursor>,
+    pub page_size: u32,
+}
+
+// Lightweight view of a report for dashboard listings
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReportListItem {
+    pub id: u64,
+    pub title: String,
+    pub category: String,
+    pub status: ReportStatus,
+    pub date_submitted: u64,
+    pub stake_amount: u64,
+    pub reward_amount: u64,
+    pub evidence_count: u32,
+    pub reviewer: Option<Principal>,
+    pub unread_messages: u64, // For the calling authority
+}
+
+impl From<&Report> for ReportListItem {
+    fn from(report: &Report) -> Self {
+        ReportListItem {
+            id: report.id,
+            title: report.title.clone(),
+            category: report.category.clone(),
+            status: report.status.clone(),
+            date_submitted: report.date_submitted,
+            stake_amount: report.stake_amount,
+            reward_amount: report.reward_amount,
+            evidence_count: report.evidence_count,
+            reviewer: report.reviewer,
+            unread_messages: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReportPage {
+    pub reports: Vec<ReportListItem>,
+    pub next_cursor: Option<ReportCursor>,
+}
+// How a report's reporter appears to authorities: a per-report pseudonym, never the principal
+pub type ReporterPseudonym = String;
+
+// Who performed an action or sent a message, as shown in responses
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub enum Participant {
+    Authority(Principal),
+    Reporter(ReporterPseudonym),
+    System,
+}
+
+// A report as seen by authorities
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct AuthorityReportView {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub category: String,
+    pub date_submitted: u64,
+    pub incident_date: Option<String>,
+    pub location: Option<Location>,
+    pub reporter: ReporterPseudonym,
+    pub evidence_count: u32,
+    pub evidence_files: Vec<u64>,
+    pub stake_amount: u64,
+    pub reward_amount: u64,
+    pub status: ReportStatus,
+    pub reviewer: Option<Principal>,
+    pub review_date: Option<u64>,
+    pub review_notes: Option<String>,
+    pub reward_config_version: Option<u64>,
+}
+
+impl AuthorityReportView {
+    pub fn new(report: Report, reporter: ReporterPseudonym) -> Self {
+        AuthorityReportView {
+            id: report.id,
+            title: report.title,
+            description: report.description,
+            category: report.category,
+            date_submitted: report.date_submitted,
+            incident_date: report.incident_date,
+            location: report.location,
+            reporter,
+            evidence_count: report.evidence_count,
+            evidence_files: report.evidence_files,
+            stake_amount: report.stake_amount,
+            reward_amount: report.reward_amount,
+            status: report.status,
+            reviewer: report.reviewer,
+            review_date: report.review_date,
+            review_notes: report.review_notes,
+            reward_config_version: report.reward_config_version,
+        }
+    }
+}
+
+// A report as seen by the reporter who filed it
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ReporterReportView {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub category: String,
+    pub date_submitted: u64,
+    pub incident_date: Option<String>,
+    pub location: Option<Location>,
+    pub evidence_count: u32,
+    pub evidence_files: Vec<u64>,
+    pub stake_amount: u64,
+    pub reward_amount: u64,
+    pub status: ReportStatus,
+    pub review_date: Option<u64>,
+    pub review_notes: Option<String>,
+    pub unread_messages: u64,
+}
+
+impl From<Report> for ReporterReportView {
+    fn from(report: Report) -> Self {
+        ReporterReportView {
+            id: report.id,
+            title: report.title,
+            description: report.description,
+            category: report.category,
+            date_submitted: report.date_submitted,
+            incident_date: report.incident_date,
+            location: report.location,
+            evidence_count: report.evidence_count,
+            evidence_files: report.evidence_files,
+            stake_amount: report.stake_amount,
+            reward_amount: report.reward_amount,
+            status: report.status,
+            review_date: report.review_date,
+            review_notes: report.review_notes,
+            unread_messages: 0,
+        }
+    }
+}
+
+// A message with the reporter shown by pseudonym
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct MessageView {
+    pub id: u64,
+    pub report_id: u64,
+    pub sender: Participant,
+    pub content: String,
+    pub timestamp: u64,
+    pub attachment: Option<MessageAttachment>,
+    pub encrypted: Option<EncryptedContent>,
+}
+
+impl MessageView {
+    pub fn new(message: Message, reporter: &ReporterPseudonym) -> Self {
+        let sender = match message.sender {
+            MessageSender::Authority(id) => Participant::Authority(id),
+            MessageSender::Reporter(_) => Participant::Reporter(reporter.clone()),
+            MessageSender::System => Participant::System,
+        };
+        
+        MessageView {
+            id: message.id,
+            report_id: message.report_id,
+            sender,
+            content: message.content,
+            timestamp: message.timestamp,
+            attachment: message.attachment,
+            encrypted: message.encrypted,
+        }
+    }
+}
+
+// A status change with the reporter shown by pseudonym
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct StatusChangeView {
+    pub report_id: u64,
+    pub action: ReportAction,
+    pub from: ReportStatus,
+    pub to: ReportStatus,
+    pub actor: Participant,
+    pub notes: Option<String>,
+    pub timestamp: u64,
+}
+
+impl StatusChangeView {
+    pub fn new(change: StatusChange, submitter_id: Principal, reporter: &ReporterPseudonym) -> Self {
+        let actor = if change.actor == submitter_id {
+            Participant::Reporter(reporter.clone())
+        } else {
+            Participant::Authority(change.actor)
+        };
+        
+        StatusChangeView {
+            report_id: change.report_id,
+            action: change.action,
+            from: change.from,
+            to: change.to,
+            actor,
+            notes: change.notes,
+            timestamp: change.timestamp,
+        }
+    }
+}
+
+// A ledger transfer as seen by authorities: a payout to the reporter shows the
+// report's pseudonym, and moves between the canister's own accounts show System
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct LedgerTransferView {
+    pub id: u64,
+    pub report_id: u64,
+    pub kind: LedgerTransferKind,
+    pub recipient: Participant,
+    pub amount: u64,
+    pub fee: Option<u64>,
+    pub created_at_time: u64,
+    pub attempts: u32,
+    pub last_attempt_at: Option<u64>,
+    pub status: LedgerTransferStatus,
+}
+
+impl LedgerTransferView {
+    pub fn new(transfer: LedgerTransfer, canister_id: Principal, reporter: ReporterPseudonym) -> Self {
+        let recipient = if transfer.to == canister_id {
+            Participant::System
+        } else {
+            Participant::Reporter(reporter)
+        };
+        
+        LedgerTransferView {
+            id: transfer.id,
+            report_id: transfer.report_id,
+            kind: transfer.kind,
+            recipient,
+            amount: transfer.amount,
+            fee: transfer.fee,
+            created_at_time: transfer.created_at_time,
+            attempts: transfer.attempts,
+            last_attempt_at: transfer.last_attempt_at,
+            status: transfer.status,
+        }
+    }
+}
+
+// A registry key as shown in responses, with reporter keys labelled by pseudonym
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct PublicKeyView {
+    pub id: u64,
+    pub owner: Participant,
+    pub algorithm: String,
+    pub public_key: Vec<u8>,
+    pub registered_at: u64,
+    pub superseded_at: Option<u64>,
+}
+
+impl PublicKeyView {
+    pub fn new(key: PublicKeyRecord, owner: Participant) -> Self {
+        PublicKeyView {
+            id: key.id,
+            owner,
+            algorithm: key.algorithm,
+            public_key: key.public_key,
+            registered_at: key.registered_at,
+            superseded_at: key.superseded_at,
+        }
+    }
+}
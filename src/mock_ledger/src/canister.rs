@@ -0,0 +1,58 @@
+use crate::{
+    with_ledger, Account, ApproveArgs, ApproveError, InitArgs, Ledger, TransferArg, TransferError, TransferFromArgs,
+    TransferFromError, DEFAULT_FEE,
+};
+use candid::Nat;
+
+#[ic_cdk::init]
+fn init(args: Option<InitArgs>) {
+    let args = args.unwrap_or(InitArgs { minting_account: None, fee: None });
+    let minting_account = args.minting_account.unwrap_or(Account { owner: ic_cdk::api::id(), subaccount: None });
+
+    with_ledger(|ledger| *ledger = Ledger::new(minting_account, args.fee.unwrap_or(DEFAULT_FEE)));
+}
+
+#[ic_cdk::query]
+fn icrc1_fee() -> Nat {
+    Nat::from(with_ledger(|ledger| ledger.fee))
+}
+
+#[ic_cdk::query]
+fn icrc1_minting_account() -> Option<Account> {
+    Some(with_ledger(|ledger| ledger.minting_account.clone()))
+}
+
+#[ic_cdk::query]
+fn icrc1_balance_of(account: Account) -> Nat {
+    Nat::from(with_ledger(|ledger| ledger.balance_of(&account)))
+}
+
+#[ic_cdk::update]
+fn icrc1_transfer(arg: TransferArg) -> Result<Nat, TransferError> {
+    let caller = ic_cdk::caller();
+    with_ledger(|ledger| ledger.transfer(caller, arg))
+}
+
+#[ic_cdk::update]
+fn icrc2_approve(args: ApproveArgs) -> Result<Nat, ApproveError> {
+    let caller = ic_cdk::caller();
+    with_ledger(|ledger| ledger.approve(caller, args))
+}
+
+#[ic_cdk::update]
+fn icrc2_transfer_from(args: TransferFromArgs) -> Result<Nat, TransferFromError> {
+    let caller = ic_cdk::caller();
+    with_ledger(|ledger| ledger.transfer_from(caller, args))
+}
+
+// Give an account test tokens (for controllers)
+#[ic_cdk::update]
+fn mint(account: Account, amount: u64) -> Result<Nat, String> {
+    if !ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        return Err("Only controllers can mint test tokens".to_string());
+    }
+
+    Ok(Nat::from(with_ledger(|ledger| ledger.mint(&account, amount))))
+}
+
+ic_cdk::export_candid!();
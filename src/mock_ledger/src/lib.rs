@@ -0,0 +1,404 @@
+use candid::{CandidType, Deserialize, Nat, Principal};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+// Stand-in ICRC-1/ICRC-2 ledger for exercising Whispr_backend's ledger-backed
+// staking. It keeps balances on the heap, has no transaction window and never
+// archives; it is for local deployments and tests only.
+//
+// The canister endpoints wrap a plain Ledger, so Rust tests can also drive it in
+// process through `handle`, passing the same Candid bytes a real call would.
+
+// Fee charged when InitArgs leaves it out (ICP's transfer fee)
+const DEFAULT_FEE: u64 = 10_000;
+
+type Subaccount = [u8; 32];
+
+const DEFAULT_SUBACCOUNT: Subaccount = [0; 32];
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Vec<u8>>,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TransferError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    TemporarilyUnavailable,
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ApproveArgs {
+    pub from_subaccount: Option<Vec<u8>>,
+    pub spender: Account,
+    pub amount: Nat,
+    pub expected_allowance: Option<Nat>,
+    pub expires_at: Option<u64>,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ApproveError {
+    BadFee { expected_fee: Nat },
+    InsufficientFunds { balance: Nat },
+    AllowanceChanged { current_allowance: Nat },
+    Expired { ledger_time: u64 },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TransferFromArgs {
+    pub spender_subaccount: Option<Vec<u8>>,
+    pub from: Account,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TransferFromError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    InsufficientAllowance { allowance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    Duplicate { duplicate_of: Nat },
+    TemporarilyUnavailable,
+    GenericError { error_code: Nat, message: String },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InitArgs {
+    pub minting_account: Option<Account>, // Defaults to the ledger canister itself
+    pub fee: Option<u64>,
+}
+
+// A balance-holding account, with None and all zeroes both the default subaccount
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct AccountKey {
+    owner: Principal,
+    subaccount: Subaccount,
+}
+
+impl AccountKey {
+    fn from_account(account: &Account) -> Option<Self> {
+        let subaccount = match &account.subaccount {
+            Some(bytes) => bytes.as_slice().try_into().ok()?,
+            None => DEFAULT_SUBACCOUNT,
+        };
+        Some(AccountKey { owner: account.owner, subaccount })
+    }
+}
+
+fn nat_to_u64(value: &Nat) -> Option<u64> {
+    u64::try_from(&value.0).ok()
+}
+
+fn generic_error(message: &str) -> (Nat, String) {
+    (Nat::from(0u64), message.to_string())
+}
+
+// A movement the ledger has been asked to make, checked the same way for
+// icrc1_transfer and icrc2_transfer_from
+struct Movement {
+    from: AccountKey,
+    to: AccountKey,
+    amount: u64,
+    fee: u64,
+    created_at_time: Option<u64>,
+    dedup_key: Vec<u8>,
+}
+
+enum MovementError {
+    BadFee(u64),
+    BadBurn(u64),
+    InsufficientFunds(u64),
+    Duplicate(u64),
+    Generic(Nat, String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    pub fee: u64,
+    pub minting_account: Account,
+    balances: BTreeMap<AccountKey, u64>,
+    allowances: BTreeMap<(AccountKey, AccountKey), u64>,
+    transactions: BTreeMap<Vec<u8>, u64>, // Deduplication: caller and arguments -> block index
+    next_block: u64,
+}
+
+impl Ledger {
+    pub fn new(minting_account: Account, fee: u64) -> Self {
+        Ledger {
+            fee,
+            minting_account,
+            balances: BTreeMap::new(),
+            allowances: BTreeMap::new(),
+            transactions: BTreeMap::new(),
+            next_block: 0,
+        }
+    }
+
+    pub fn balance_of(&self, account: &Account) -> u64 {
+        AccountKey::from_account(account)
+            .and_then(|key| self.balances.get(&key).copied())
+            .unwrap_or(0)
+    }
+
+    // Credit an account out of nothing, as the minting account would
+    pub fn mint(&mut self, account: &Account, amount: u64) -> u64 {
+        if let Some(key) = AccountKey::from_account(account) {
+            *self.balances.entry(key).or_insert(0) += amount;
+        }
+        self.next_block()
+    }
+
+    // Sum of every balance, for checking that movements conserve tokens
+    pub fn total_supply(&self) -> u64 {
+        self.balances.values().sum()
+    }
+
+    pub fn transfer(&mut self, caller: Principal, arg: TransferArg) -> Result<Nat, TransferError> {
+        let from = Account { owner: caller, subaccount: arg.from_subaccount.clone() };
+        let movement = self.movement(&from, &arg.to, &arg.amount, &arg.fee, arg.created_at_time, (caller, &arg));
+
+        let movement = match movement {
+            Ok(movement) => movement,
+            Err(error) => return Err(transfer_error(error)),
+        };
+
+        self.apply(movement).map_err(transfer_error)
+    }
+
+    pub fn approve(&mut self, caller: Principal, args: ApproveArgs) -> Result<Nat, ApproveError> {
+        let from = AccountKey::from_account(&Account { owner: caller, subaccount: args.from_subaccount.clone() });
+        let spender = AccountKey::from_account(&args.spender);
+        let (from, spender) = match (from, spender) {
+            (Some(from), Some(spender)) => (from, spender),
+            _ => {
+                let (error_code, message) = generic_error("Subaccounts must be 32 bytes");
+                return Err(ApproveError::GenericError { error_code, message });
+            }
+        };
+
+        if args.fee.as_ref().is_some_and(|fee| nat_to_u64(fee) != Some(self.fee)) {
+            return Err(ApproveError::BadFee { expected_fee: Nat::from(self.fee) });
+        }
+
+        let current = self.allowances.get(&(from, spender)).copied().unwrap_or(0);
+        if args.expected_allowance.as_ref().is_some_and(|expected| nat_to_u64(expected) != Some(current)) {
+            return Err(ApproveError::AllowanceChanged { current_allowance: Nat::from(current) });
+        }
+
+        let balance = self.balances.get(&from).copied().unwrap_or(0);
+        if balance < self.fee {
+            return Err(ApproveError::InsufficientFunds { balance: Nat::from(balance) });
+        }
+
+        let amount = nat_to_u64(&args.amount).unwrap_or(u64::MAX);
+        self.balances.insert(from, balance - self.fee);
+        self.allowances.insert((from, spender), amount);
+
+        Ok(Nat::from(self.next_block()))
+    }
+
+    pub fn transfer_from(&mut self, caller: Principal, args: TransferFromArgs) -> Result<Nat, TransferFromError> {
+        let spender = Account { owner: caller, subaccount: args.spender_subaccount.clone() };
+        let movement = self.movement(&args.from, &args.to, &args.amount, &args.fee, args.created_at_time, (caller, &args));
+
+        let movement = match movement {
+            Ok(movement) => movement,
+            Err(error) => return Err(transfer_from_error(error)),
+        };
+
+        // The spender must be allowed the amount plus the fee
+        let spender = match AccountKey::from_account(&spender) {
+            Some(spender) => spender,
+            None => {
+                let (error_code, message) = generic_error("Subaccounts must be 32 bytes");
+                return Err(TransferFromError::GenericError { error_code, message });
+            }
+        };
+        let allowance = self.allowances.get(&(movement.from, spender)).copied().unwrap_or(0);
+        let needed = movement.amount.saturating_add(movement.fee);
+        if allowance < needed {
+            return Err(TransferFromError::InsufficientAllowance { allowance: Nat::from(allowance) });
+        }
+
+        let from = movement.from;
+        let block_index = self.apply(movement).map_err(transfer_from_error)?;
+        self.allowances.insert((from, spender), allowance - needed);
+
+        Ok(block_index)
+    }
+
+    fn next_block(&mut self) -> u64 {
+        let index = self.next_block;
+        self.next_block += 1;
+        index
+    }
+
+    // Check a requested movement. Burns (to the minting account) carry no fee.
+    fn movement(
+        &self,
+        from: &Account,
+        to: &Account,
+        amount: &Nat,
+        fee: &Option<Nat>,
+        created_at_time: Option<u64>,
+        request: impl CandidType,
+    ) -> Result<Movement, MovementError> {
+        let (from, to) = match (AccountKey::from_account(from), AccountKey::from_account(to)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                let (error_code, message) = generic_error("Subaccounts must be 32 bytes");
+                return Err(MovementError::Generic(error_code, message));
+            }
+        };
+
+        let amount = match nat_to_u64(amount) {
+            Some(amount) => amount,
+            None => {
+                let (error_code, message) = generic_error("Amount is too large");
+                return Err(MovementError::Generic(error_code, message));
+            }
+        };
+
+        let is_burn = Some(to) == AccountKey::from_account(&self.minting_account);
+        let expected_fee = if is_burn { 0 } else { self.fee };
+        if fee.as_ref().is_some_and(|fee| nat_to_u64(fee) != Some(expected_fee)) {
+            return Err(MovementError::BadFee(expected_fee));
+        }
+
+        if is_burn && amount == 0 {
+            return Err(MovementError::BadBurn(1));
+        }
+
+        let dedup_key = candid::encode_one(request).unwrap_or_default();
+        if created_at_time.is_some() {
+            if let Some(block_index) = self.transactions.get(&dedup_key) {
+                return Err(MovementError::Duplicate(*block_index));
+            }
+        }
+
+        Ok(Movement { from, to, amount, fee: expected_fee, created_at_time, dedup_key })
+    }
+
+    fn apply(&mut self, movement: Movement) -> Result<Nat, MovementError> {
+        let debit = movement.amount.saturating_add(movement.fee);
+        let balance = self.balances.get(&movement.from).copied().unwrap_or(0);
+        if balance < debit {
+            return Err(MovementError::InsufficientFunds(balance));
+        }
+
+        // The fee is burned; a burn's amount leaves circulation as well
+        self.balances.insert(movement.from, balance - debit);
+        if Some(movement.to) != AccountKey::from_account(&self.minting_account) {
+            *self.balances.entry(movement.to).or_insert(0) += movement.amount;
+        }
+
+        let block_index = self.next_block();
+        if movement.created_at_time.is_some() {
+            self.transactions.insert(movement.dedup_key, block_index);
+        }
+
+        Ok(Nat::from(block_index))
+    }
+}
+
+fn transfer_error(error: MovementError) -> TransferError {
+    match error {
+        MovementError::BadFee(expected_fee) => TransferError::BadFee { expected_fee: Nat::from(expected_fee) },
+        MovementError::BadBurn(min_burn_amount) => TransferError::BadBurn { min_burn_amount: Nat::from(min_burn_amount) },
+        MovementError::InsufficientFunds(balance) => TransferError::InsufficientFunds { balance: Nat::from(balance) },
+        MovementError::Duplicate(duplicate_of) => TransferError::Duplicate { duplicate_of: Nat::from(duplicate_of) },
+        MovementError::Generic(error_code, message) => TransferError::GenericError { error_code, message },
+    }
+}
+
+fn transfer_from_error(error: MovementError) -> TransferFromError {
+    match error {
+        MovementError::BadFee(expected_fee) => TransferFromError::BadFee { expected_fee: Nat::from(expected_fee) },
+        MovementError::BadBurn(min_burn_amount) => {
+            TransferFromError::BadBurn { min_burn_amount: Nat::from(min_burn_amount) }
+        }
+        MovementError::InsufficientFunds(balance) => {
+            TransferFromError::InsufficientFunds { balance: Nat::from(balance) }
+        }
+        MovementError::Duplicate(duplicate_of) => TransferFromError::Duplicate { duplicate_of: Nat::from(duplicate_of) },
+        MovementError::Generic(error_code, message) => TransferFromError::GenericError { error_code, message },
+    }
+}
+
+thread_local! {
+    static LEDGER: RefCell<Ledger> = RefCell::new(Ledger::new(
+        Account { owner: Principal::anonymous(), subaccount: None },
+        DEFAULT_FEE,
+    ));
+}
+
+// Run a closure against the ledger state
+pub fn with_ledger<R>(f: impl FnOnce(&mut Ledger) -> R) -> R {
+    LEDGER.with(|ledger| f(&mut ledger.borrow_mut()))
+}
+
+// Answer a call the way the canister would, from Candid-encoded arguments to a
+// Candid-encoded reply
+pub fn handle(caller: Principal, method: &str, arg: &[u8]) -> Result<Vec<u8>, String> {
+    fn reply(result: candid::Result<Vec<u8>>) -> Result<Vec<u8>, String> {
+        result.map_err(|error| error.to_string())
+    }
+
+    match method {
+        "icrc1_fee" => reply(candid::encode_one(Nat::from(with_ledger(|ledger| ledger.fee)))),
+        "icrc1_minting_account" => reply(candid::encode_one(Some(with_ledger(|ledger| ledger.minting_account.clone())))),
+        "icrc1_balance_of" => {
+            let account: Account = candid::decode_one(arg).map_err(|error| error.to_string())?;
+            reply(candid::encode_one(Nat::from(with_ledger(|ledger| ledger.balance_of(&account)))))
+        }
+        "icrc1_transfer" => {
+            let arg: TransferArg = candid::decode_one(arg).map_err(|error| error.to_string())?;
+            reply(candid::encode_one(with_ledger(|ledger| ledger.transfer(caller, arg))))
+        }
+        "icrc2_approve" => {
+            let args: ApproveArgs = candid::decode_one(arg).map_err(|error| error.to_string())?;
+            reply(candid::encode_one(with_ledger(|ledger| ledger.approve(caller, args))))
+        }
+        "icrc2_transfer_from" => {
+            let args: TransferFromArgs = candid::decode_one(arg).map_err(|error| error.to_string())?;
+            reply(candid::encode_one(with_ledger(|ledger| ledger.transfer_from(caller, args))))
+        }
+        _ => Err(format!("The stand-in ledger has no method {}", method)),
+    }
+}
+
+// The endpoints are only compiled for the canister: linked natively into another
+// canister's tests, their exports would clash with that canister's own
+#[cfg(target_arch = "wasm32")]
+mod canister;